@@ -1065,6 +1065,22 @@ impl Builder {
         )
     }
 
+    /// Build an integer literal with value `i` that is printed as `text` (for example `0xff`)
+    /// instead of in decimal.
+    pub fn int_lit_with_text<S, T>(self, i: u128, text: S, ty: T) -> Lit
+    where
+        S: IntoSymbol,
+        T: Make<LitIntType>,
+    {
+        let ty = ty.make(&self);
+        let mut lit = Lit::from_lit_kind(
+            LitKind::Int(i, ty),
+            self.span
+        );
+        lit.token.symbol = text.into_symbol();
+        lit
+    }
+
     pub fn float_lit<S, T>(self, s: S, ty: T) -> Lit
     where
         S: IntoSymbol,
@@ -1759,6 +1775,34 @@ impl Builder {
         )
     }
 
+    pub fn trait_impl_item<Pa, T>(self, trait_path: Pa, ty: T, items: Vec<ImplItem>) -> P<Item>
+    where
+        Pa: Make<Path>,
+        T: Make<P<Ty>>,
+    {
+        let trait_ref = TraitRef {
+            path: trait_path.make(&self),
+            ref_id: DUMMY_NODE_ID,
+        };
+        let ty = ty.make(&self);
+        Self::item(
+            Ident::invalid(),
+            self.attrs,
+            self.vis,
+            self.span,
+            self.id,
+            ItemKind::Impl(
+                self.unsafety,
+                ImplPolarity::Positive,
+                Defaultness::Final,
+                self.generics,
+                Some(trait_ref),
+                ty,
+                items,
+            ),
+        )
+    }
+
     pub fn extern_crate_item<I>(self, name: I, rename: Option<I>) -> P<Item>
     where
         I: Make<Ident>,
@@ -1911,6 +1955,27 @@ impl Builder {
         }
     }
 
+    pub fn fn_impl_item<I, S, B>(self, name: I, sig: S, block: B) -> ImplItem
+    where
+        I: Make<Ident>,
+        S: Make<FnSig>,
+        B: Make<P<Block>>,
+    {
+        let name = name.make(&self);
+        let sig = sig.make(&self);
+        let block = block.make(&self);
+        Self::impl_item_(
+            name,
+            self.attrs,
+            self.vis,
+            Defaultness::Final,
+            self.generics,
+            self.span,
+            self.id,
+            ImplItemKind::Method(sig, block),
+        )
+    }
+
     pub fn mac_impl_item<M>(self, mac: M) -> ImplItem
     where
         M: Make<Mac>,
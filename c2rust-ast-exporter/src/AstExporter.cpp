@@ -1815,6 +1815,10 @@ class TranslateASTVisitor final
                         } else if (auto *aa = dyn_cast<AliasAttr>(attr)) {
                             cbor_encode_text_stringz(
                                 &attr_info, aa->getAliasee().str().c_str());
+                        } else if (auto *ca = dyn_cast<CleanupAttr>(attr)) {
+                            cbor_encode_string(
+                                &attr_info,
+                                ca->getFunctionDecl()->getNameAsString());
                         }
                     }
                 }
@@ -2065,7 +2069,8 @@ class TranslateASTVisitor final
 
         auto base = (value == 0 || prefix[0] != '0')
                         ? 10U
-                        : (prefix[1] == 'x' || prefix[1] == 'X') ? 16U : 8U;
+                        : (prefix[1] == 'x' || prefix[1] == 'X') ? 16U
+                        : (prefix[1] == 'b' || prefix[1] == 'B') ? 2U : 8U;
 
         std::vector<void *> childIds;
         encode_entry(IL, TagIntegerLiteral, childIds,
@@ -2084,15 +2089,48 @@ class TranslateASTVisitor final
 #endif // CLANG_VERSION_MAJOR
 
     bool VisitImaginaryLiteral(ImaginaryLiteral *L) {
-        printWarning("Encountered unsupported imaginary literal", L);
+        // The imaginary part is always an integer or floating literal, which
+        // we flatten into a single value
+        double value;
+        auto sub = L->getSubExpr();
+        if (auto *FL = dyn_cast<FloatingLiteral>(sub)) {
+            value = FL->getValueAsApproximateDouble();
+        } else if (auto *IL = dyn_cast<IntegerLiteral>(sub)) {
+            value = IL->getValue().roundToDouble();
+        } else {
+            printWarning("Encountered unsupported imaginary literal", L);
+            return true;
+        }
+
+        std::vector<void *> childIds;
+        encode_entry(L, TagImaginaryLiteral, childIds, [value](CborEncoder *array) {
+            cbor_encode_double(array, value);
+        });
         return true;
     }
 
     bool VisitCharacterLiteral(CharacterLiteral *L) {
+
+        // Keep the spelling so that multi-character constants like 'ABCD'
+        // can be annotated with it
+        auto &sourceManager = Context->getSourceManager();
+        auto prefix = sourceManager.getCharacterData(L->getLocation());
+        auto end = prefix;
+        while (*end && *end != '\'')
+            end++;
+        if (*end)
+            end++;
+        while (*end && *end != '\'' && *end != '\n')
+            end += (*end == '\\' && end[1]) ? 2 : 1;
+        if (*end == '\'')
+            end++;
+        std::string spelling(prefix, end);
+
         std::vector<void *> childIds;
-        encode_entry(L, TagCharacterLiteral, childIds, [L](CborEncoder *array) {
+        encode_entry(L, TagCharacterLiteral, childIds, [L, &spelling](CborEncoder *array) {
             auto lit = L->getValue();
             cbor_encode_uint(array, lit);
+            cbor_encode_string(array, spelling);
         });
         return true;
     }
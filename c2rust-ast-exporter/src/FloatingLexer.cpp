@@ -7,6 +7,8 @@
 
 #include "FloatingLexer.h"
 
+#include <cctype>
+
 using std::string;
 
 // Extract a floating point literal from a given character buffer.
@@ -20,11 +22,24 @@ using std::string;
 // "1.000" -> "1.000"
 // "1.2e+3" -> "1.2e+3"
 // "1e-5" -> "1e-5"
+// "0x1.8p3" -> "0x1.8p3"
 string matchFloatingLiteral(const char *prefix) {
 
-    // Hex literals are not supported
-    if (strcmp("0x", prefix) == 0 || strcmp("0X", prefix) == 0) {
-        return "";
+    // Hex literals are passed through unchanged. Rust has no syntax for them,
+    // so the transpiler emits their exact bit pattern instead.
+    if (strncmp("0x", prefix, 2) == 0 || strncmp("0X", prefix, 2) == 0) {
+        string output(prefix, 2);
+        prefix += 2;
+        for (;;) {
+            auto c = *prefix++;
+            auto last = output.back();
+            if (isxdigit(c) || '.' == c || 'P' == c || 'p' == c ||
+                ((c == '+' || c == '-') && (last == 'P' || last == 'p'))) {
+                output.push_back(c);
+            } else {
+                return output;
+            }
+        }
     }
 
     string output;
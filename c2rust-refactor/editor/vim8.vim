@@ -167,6 +167,8 @@ function! RefactorOutputHandler(channel, msg)
                 exec cur_line
             endif
         endif
+    elseif json["msg"] == "progress"
+        " Progress reports are only informational
     elseif json["msg"] == "error"
         redraw
         echohl ErrorMsg
@@ -19,7 +19,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use syntax::ast::{Crate, NodeId, CRATE_NODE_ID};
 use syntax::ast::{Expr, Item, Pat, Stmt, Ty};
 use syntax::ptr::P;
-use syntax::source_map::SourceMap;
+use syntax::source_map::{SourceMap, Span};
 use syntax::symbol::Symbol;
 use syntax::visit::Visitor;
 
@@ -135,10 +135,44 @@ pub struct RefactorState {
     /// Commands run so far
     commands: Vec<String>,
 
+    /// Diagnostics reported by commands and not yet collected by `drain_diagnostics`
+    diagnostics: Vec<Diagnostic>,
+
     /// Generation number for TyCtxt references
     tcx_gen: TyCtxtGeneration,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+/// A problem reported by a command that didn't stop the command from running.  The location is
+/// resolved from the reported `Span` when the `transform_crate` that reported it finishes, since
+/// the `Span` is only meaningful until the crate is reloaded.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    /// Line number, starting from 1
+    pub line: u32,
+    /// Column number in characters, starting from 0
+    pub col: u32,
+    pub message: String,
+}
+
 // #[cfg_attr(feature = "profile", flame)]
 // fn parse_crate(queries: &interface::Compiler) -> Crate {
 //     let mut krate = queries.parse().unwrap().take();
@@ -214,6 +248,8 @@ impl RefactorState {
 
             commands: vec![],
 
+            diagnostics: vec![],
+
             disk_state: None,
 
             node_map: NodeMap::new(),
@@ -240,6 +276,11 @@ impl RefactorState {
         mem::replace(&mut self.commands, vec![])
     }
 
+    /// Take the diagnostics reported by commands since the last call.
+    pub fn drain_diagnostics(&mut self) -> Vec<Diagnostic> {
+        mem::replace(&mut self.diagnostics, vec![])
+    }
+
     /// Load the crate from disk.  This also resets a bunch of internal state, since we won't be
     /// rewriting with the previous `orig_crate` any more.
     #[cfg_attr(feature = "profile", flame)]
@@ -253,6 +294,18 @@ impl RefactorState {
         self.node_id_counter = NodeIdCounter::new(FRESH_NODE_ID_START);
     }
 
+    /// Like `load_crate`, but with `cfgs` (each in `--cfg` syntax, such as `feature="x"`) added to
+    /// the crate configuration.  The extra configuration lasts until the crate is next loaded,
+    /// since it's fixed when the compiler is created.
+    pub fn load_crate_with_cfgs(&mut self, cfgs: &[String]) {
+        let saved = self.config.crate_cfg.clone();
+        self.config
+            .crate_cfg
+            .extend(interface::parse_cfgspecs(cfgs.to_owned()));
+        self.load_crate();
+        self.config.crate_cfg = saved;
+    }
+
     /// Save the crate to disk, by writing out the new source text produced by rewriting.
     ///
     /// Note that we allow multiple calls to `save_crate` with no intervening `load_crate`.  The
@@ -295,6 +348,7 @@ impl RefactorState {
 
         let disk_state = &mut self.disk_state;
         let marks = &mut self.marks;
+        let diagnostics = &mut self.diagnostics;
         let parsed_nodes = &mut self.parsed_nodes;
         let source_map = self.compiler.source_map();
         let session = self.compiler.session();
@@ -443,6 +497,17 @@ impl RefactorState {
                 }
             }
 
+            for (severity, span, message) in cs.diagnostics.get_mut().drain(..) {
+                let loc = source_map.lookup_char_pos(span.lo());
+                diagnostics.push(Diagnostic {
+                    severity,
+                    file: loc.file.name.to_string(),
+                    line: loc.line as u32,
+                    col: loc.col.0 as u32,
+                    message,
+                });
+            }
+
             *marks = cs.marks.into_inner();
             parsed_nodes.append(cs.parsed_nodes.into_inner());
             *krate = Some(cs.krate.into_inner());
@@ -537,6 +602,37 @@ impl RefactorState {
         Ok(())
     }
 
+    /// Get the minimum phase and documentation of command `cmd_name`.  See
+    /// `Registry::query_command`.
+    pub fn query_command(
+        &mut self,
+        cmd_name: &str,
+        args: &[String],
+    ) -> Result<CommandInfo, String> {
+        self.cmd_reg.query_command(cmd_name, args)
+    }
+
+    /// Check whether command `cmd_name` is pure when given `args`.  See `Command::is_pure`.
+    pub fn is_pure_command(&mut self, cmd_name: &str, args: &[String]) -> Result<bool, String> {
+        Ok(self.cmd_reg.get_command(cmd_name, args)?.is_pure())
+    }
+
+    /// Get the name of the command that `cmd_name` refers to, following any aliases.
+    pub fn canonical_command_name(&self, cmd_name: &str) -> String {
+        self.cmd_reg.canonical_name(cmd_name).to_owned()
+    }
+
+    /// Get the names and documentation of all registered commands, sorted by name.
+    pub fn command_list(&self) -> Vec<(String, CommandInfo)> {
+        let mut commands = self
+            .cmd_reg
+            .commands()
+            .map(|(name, info)| (name.to_owned(), info))
+            .collect::<Vec<_>>();
+        commands.sort_by(|a, b| a.0.cmp(&b.0));
+        commands
+    }
+
     pub fn marks(&self) -> &HashSet<(NodeId, Symbol)> {
         &self.marks
     }
@@ -579,6 +675,8 @@ pub struct CommandState {
 
     new_comments: RefCell<Vec<(NodeId, Comment)>>,
 
+    diagnostics: RefCell<Vec<(Severity, Span, String)>>,
+
     krate_changed: Cell<bool>,
     marks_changed: Cell<bool>,
 }
@@ -599,6 +697,8 @@ impl CommandState {
             new_parsed_node_ids: RefCell::new(Vec::new()),
             new_comments: RefCell::new(Vec::new()),
 
+            diagnostics: RefCell::new(Vec::new()),
+
             krate_changed: Cell::new(false),
             marks_changed: Cell::new(false),
 
@@ -627,6 +727,18 @@ impl CommandState {
         self.new_comments.borrow_mut().push((node, comment));
     }
 
+    /// Report a problem at `span` without failing the command.  Interactive clients receive these
+    /// after the command finishes; otherwise they are logged.
+    pub fn report<S: Into<String>>(&self, severity: Severity, span: Span, message: S) {
+        self.diagnostics
+            .borrow_mut()
+            .push((severity, span, message.into()));
+    }
+
+    pub fn warn<S: Into<String>>(&self, span: Span, message: S) {
+        self.report(Severity::Warning, span, message)
+    }
+
     pub fn marks(&self) -> cell::Ref<HashSet<(NodeId, Symbol)>> {
         self.marks.borrow()
     }
@@ -717,20 +829,60 @@ impl CommandState {
 /// Implementation of a refactoring command.
 pub trait Command {
     fn run(&mut self, state: &mut RefactorState);
+
+    /// Return the minimum phase the compiler must reach to run this command, or `None` if that
+    /// isn't known ahead of time (for example, because the command doesn't run the compiler).
+    fn min_phase(&self) -> Option<Phase> {
+        None
+    }
+
+    /// Return whether the command only analyzes the crate: it leaves the source unchanged, and
+    /// given the same source and marks it always produces the same marks and diagnostics.  The
+    /// interactive server reuses the results of pure commands while their inputs are unchanged.
+    fn is_pure(&self) -> bool {
+        false
+    }
 }
 
 /// A command builder is a function that takes some string arguments and produces a `Command`.
 pub type Builder = dyn FnMut(&[String]) -> Box<dyn Command> + Send;
 
+/// Documentation for a registered command, for display in user interfaces.  Commands registered
+/// without a description get the default, which accepts any number of arguments.
+#[derive(Clone, Debug, Default)]
+pub struct CommandInfo {
+    /// Usage summary, such as `rename_items_regex PAT REPL [FILTER]`.
+    pub usage: String,
+    pub description: String,
+    /// The minimum phase the command needs, if it is known without building the command.
+    pub phase: Option<Phase>,
+    pub min_args: usize,
+    /// The maximum number of arguments, or `None` if there is no limit.
+    pub max_args: Option<usize>,
+}
+
 /// Tracks known refactoring command builders, and allows invoking them by name.
 pub struct Registry {
     commands: HashMap<String, Box<Builder>>,
+    infos: HashMap<String, CommandInfo>,
+    /// Maps each alias to the name it stands for, which may be another alias.
+    aliases: HashMap<String, String>,
+}
+
+/// Follow `aliases` from `name` to the name of the command it refers to.
+fn resolve_alias<'a>(aliases: &'a HashMap<String, String>, mut name: &'a str) -> &'a str {
+    while let Some(target) = aliases.get(name) {
+        name = target.as_str();
+    }
+    name
 }
 
 impl Registry {
     pub fn new() -> Registry {
         Registry {
             commands: HashMap::new(),
+            infos: HashMap::new(),
+            aliases: HashMap::new(),
         }
     }
 
@@ -741,13 +893,71 @@ impl Registry {
         self.commands.insert(name.to_owned(), Box::new(builder));
     }
 
+    /// Make `alias` another name for the command `target`, which may itself be an alias.  Fails if
+    /// `alias` is already the name of a command, or if the aliases would form a cycle.
+    pub fn register_alias(&mut self, alias: &str, target: &str) -> Result<(), String> {
+        if self.commands.contains_key(alias) {
+            return Err(format!("{:?} is already the name of a command", alias));
+        }
+        let mut name = target;
+        loop {
+            if name == alias {
+                return Err(format!("alias {:?} for {:?} would refer to itself", alias, target));
+            }
+            match self.aliases.get(name) {
+                Some(next) => name = next.as_str(),
+                None => break,
+            }
+        }
+        self.aliases.insert(alias.to_owned(), target.to_owned());
+        Ok(())
+    }
+
+    /// Get the name of the command that `name` refers to, following any aliases.
+    pub fn canonical_name<'a>(&'a self, name: &'a str) -> &'a str {
+        resolve_alias(&self.aliases, name)
+    }
+
     pub fn get_command(&mut self, name: &str, args: &[String]) -> Result<Box<dyn Command>, String> {
-        let builder = match self.commands.get_mut(name) {
+        let builder = match self.commands.get_mut(resolve_alias(&self.aliases, name)) {
             Some(command) => command,
             None => return Err(format!("Invalid command: {:#?}", name)),
         };
         Ok(builder(args))
     }
+
+    /// Attach documentation to the command `name`.
+    pub fn describe(&mut self, name: &str, info: CommandInfo) {
+        self.infos.insert(name.to_owned(), info);
+    }
+
+    /// Get the documentation of command `name`, with `phase` set to the minimum phase needed to
+    /// run it with `args`.  This builds the command, but doesn't run it.
+    pub fn query_command(&mut self, name: &str, args: &[String]) -> Result<CommandInfo, String> {
+        let mut info = self
+            .infos
+            .get(self.canonical_name(name))
+            .cloned()
+            .unwrap_or_default();
+        if args.len() < info.min_args || info.max_args.map_or(false, |max| args.len() > max) {
+            return Err(format!(
+                "wrong number of arguments for {}: expected `{}`",
+                name, info.usage
+            ));
+        }
+        let cmd = self.get_command(name, args)?;
+        info.phase = cmd.min_phase().or(info.phase);
+        Ok(info)
+    }
+
+    /// Iterate over the names and documentation of all registered commands, in no particular
+    /// order.  Aliases are not listed separately.
+    pub fn commands<'a>(&'a self) -> impl Iterator<Item = (&'a str, CommandInfo)> + 'a {
+        self.commands.keys().map(move |name| {
+            let info = self.infos.get(name).cloned().unwrap_or_default();
+            (name as &str, info)
+        })
+    }
 }
 
 /// Wraps a `FnMut` to produce a `Command`.
@@ -770,6 +980,7 @@ where
 {
     func: F,
     phase: Phase,
+    pure: bool,
 }
 
 impl<F> DriverCommand<F>
@@ -777,7 +988,17 @@ where
     F: FnMut(&CommandState, &RefactorCtxt),
 {
     pub fn new(phase: Phase, func: F) -> DriverCommand<F> {
-        DriverCommand { func, phase }
+        DriverCommand {
+            func,
+            phase,
+            pure: false,
+        }
+    }
+
+    /// Mark the command as pure, for a `func` that only reads the crate.  See
+    /// `Command::is_pure`.
+    pub fn pure(self) -> DriverCommand<F> {
+        DriverCommand { pure: true, ..self }
     }
 }
 
@@ -790,6 +1011,14 @@ where
             .transform_crate(self.phase, |st, cx| (self.func)(st, cx))
             .expect("Failed to run compiler");
     }
+
+    fn min_phase(&self) -> Option<Phase> {
+        Some(self.phase)
+    }
+
+    fn is_pure(&self) -> bool {
+        self.pure
+    }
 }
 
 /// # `commit` Command
@@ -860,12 +1089,28 @@ fn register_commit(reg: &mut Registry) {
             rs.clear_marks();
         }))
     });
+    reg.describe("commit", CommandInfo {
+        usage: "commit [git]".to_owned(),
+        description: "Write the current crate to disk, then read it back in, clearing all marks. \
+                      With `git`, also commit the changes made since the last commit."
+            .to_owned(),
+        phase: None,
+        min_args: 0,
+        max_args: Some(1),
+    });
 
     reg.register("write", |_args| {
         Box::new(FuncCommand(|rs: &mut RefactorState| {
             rs.save_crate();
         }))
     });
+    reg.describe("write", CommandInfo {
+        usage: "write".to_owned(),
+        description: "Write the current crate to disk.".to_owned(),
+        phase: None,
+        min_args: 0,
+        max_args: Some(0),
+    });
 
     reg.register("dump_crate", |_args| {
         Box::new(FuncCommand(|rs: &mut RefactorState| {
@@ -873,8 +1118,108 @@ fn register_commit(reg: &mut Registry) {
             eprintln!("{:#?}", rs.krate.as_ref().unwrap());
         }))
     });
+    reg.describe("dump_crate", CommandInfo {
+        usage: "dump_crate".to_owned(),
+        description: "Print the AST of the current crate to stderr.".to_owned(),
+        phase: None,
+        min_args: 0,
+        max_args: Some(0),
+    });
 }
 
 pub fn register_commands(reg: &mut Registry) {
     register_commit(reg);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_reports_command_phase() {
+        let mut reg = Registry::new();
+        reg.register("typeck_only", |_args| {
+            Box::new(DriverCommand::new(Phase::Phase3, |_st, _cx| {}))
+        });
+        reg.describe("typeck_only", CommandInfo {
+            usage: "typeck_only".to_owned(),
+            description: "Do nothing after typechecking.".to_owned(),
+            phase: None,
+            min_args: 0,
+            max_args: Some(0),
+        });
+
+        let info = reg.query_command("typeck_only", &[]).unwrap();
+        let cmd = reg.get_command("typeck_only", &[]).unwrap();
+        assert_eq!(info.phase, cmd.min_phase());
+        assert_eq!(info.phase, Some(Phase::Phase3));
+        assert_eq!(info.usage, "typeck_only");
+
+        assert!(reg.query_command("typeck_only", &["x".to_owned()]).is_err());
+        assert!(reg.query_command("no_such_command", &[]).is_err());
+    }
+
+    #[test]
+    fn list_registered_commands() {
+        let mut reg = Registry::new();
+        reg.register("expand_only", |_args| {
+            Box::new(DriverCommand::new(Phase::Phase2, |_st, _cx| {}))
+        });
+        reg.describe("expand_only", CommandInfo {
+            phase: Some(Phase::Phase2),
+            ..CommandInfo::default()
+        });
+        reg.register("parse_only", |_args| {
+            Box::new(DriverCommand::new(Phase::Phase1, |_st, _cx| {}))
+        });
+        reg.describe("parse_only", CommandInfo {
+            phase: Some(Phase::Phase1),
+            ..CommandInfo::default()
+        });
+
+        let mut commands = reg
+            .commands()
+            .map(|(name, info)| (name.to_owned(), info.phase))
+            .collect::<Vec<_>>();
+        commands.sort();
+        assert_eq!(
+            commands,
+            vec![
+                ("expand_only".to_owned(), Some(Phase::Phase2)),
+                ("parse_only".to_owned(), Some(Phase::Phase1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn commands_through_aliases() {
+        let mut reg = Registry::new();
+        reg.register("parse_only", |_args| {
+            Box::new(DriverCommand::new(Phase::Phase1, |_st, _cx| {}))
+        });
+        reg.describe("parse_only", CommandInfo {
+            usage: "parse_only".to_owned(),
+            max_args: Some(0),
+            ..CommandInfo::default()
+        });
+        reg.register_alias("po", "parse_only").unwrap();
+        reg.register_alias("p", "po").unwrap();
+
+        assert_eq!(reg.canonical_name("p"), "parse_only");
+        assert_eq!(reg.canonical_name("other"), "other");
+        let cmd = reg.get_command("p", &[]).unwrap();
+        assert_eq!(cmd.min_phase(), Some(Phase::Phase1));
+        let info = reg.query_command("po", &[]).unwrap();
+        assert_eq!(info.usage, "parse_only");
+        assert_eq!(info.phase, Some(Phase::Phase1));
+        assert!(reg.query_command("po", &["x".to_owned()]).is_err());
+
+        assert!(reg.register_alias("po", "p").is_err());
+        assert!(reg.register_alias("q", "q").is_err());
+        assert!(reg.register_alias("parse_only", "p").is_err());
+        assert_eq!(reg.canonical_name("po"), "parse_only");
+
+        let names = reg.commands().map(|(name, _)| name.to_owned()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["parse_only"]);
+    }
+}
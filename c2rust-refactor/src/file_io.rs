@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 use std::mem;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -12,6 +12,7 @@ use syntax::source_map::{Span, DUMMY_SP};
 use syntax::symbol::Symbol;
 use syntax_pos::hygiene::SyntaxContext;
 
+use crate::rewrite::files::FileEdit;
 use crate::rewrite::{self, TextRewrite};
 
 #[allow(unused_variables)]
@@ -33,6 +34,11 @@ pub trait FileIO {
 
     fn read_file(&self, path: &Path) -> io::Result<String>;
     fn write_file(&self, path: &Path, s: &str) -> io::Result<()>;
+    /// Write the rewritten contents `s` of `path`.  `edits` describes the same change as a list
+    /// of replacements in the original text, for implementations that can use it.
+    fn write_file_edits(&self, path: &Path, s: &str, edits: &[FileEdit]) -> io::Result<()> {
+        self.write_file(path, s)
+    }
     fn save_rewrites(
         &self,
         sm: &SourceMap,
@@ -101,6 +107,41 @@ impl RealState {
     }
 }
 
+/// Write `s` to `dest` through a temporary file in the same directory, which is then renamed over
+/// `dest`.  If writing fails or is interrupted, `dest` keeps its old contents.
+fn write_atomically(dest: &Path, s: &str) -> io::Result<()> {
+    write_atomically_with(dest, |file| file.write_all(s.as_bytes()))
+}
+
+/// Like `write_atomically`, with `write` filling in the temporary file.  The new file gets the
+/// permissions of the one it replaces.
+fn write_atomically_with<F>(dest: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut fs::File) -> io::Result<()>,
+{
+    let file_name = dest.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} does not name a file", dest),
+        )
+    })?;
+    let tmp = dest.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        write(&mut file)?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(dest) {
+            fs::set_permissions(&tmp, metadata.permissions())?;
+        }
+        fs::rename(&tmp, dest)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
 pub struct RealFileIO {
     output_modes: Vec<OutputMode>,
     state: Mutex<RealState>,
@@ -172,7 +213,7 @@ impl FileIO for RealFileIO {
             for &mode in &self.output_modes {
                 if let Some(dest) = mode.write_dest(path) {
                     info!("writing to {:?}", dest);
-                    fs::write(&dest, s)?;
+                    write_atomically(&dest, s)?;
                 }
             }
 
@@ -271,3 +312,79 @@ impl FileLoader for ArcFileIO {
         self.0.read_file(path)
     }
 }
+
+/// A `FileLoader` serving source files from memory, for working on crates that aren't on disk.
+/// Paths are looked up exactly as given, so they should match the crate root as passed to rustc
+/// and the paths rustc derives from it for `mod` items.
+pub struct VirtualFileLoader {
+    files: HashMap<PathBuf, String>,
+}
+
+impl VirtualFileLoader {
+    pub fn new(files: HashMap<PathBuf, String>) -> VirtualFileLoader {
+        VirtualFileLoader { files }
+    }
+}
+
+impl FileLoader for VirtualFileLoader {
+    fn file_exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn abs_path(&self, path: &Path) -> Option<PathBuf> {
+        if self.files.contains_key(path) {
+            Some(path.to_owned())
+        } else {
+            None
+        }
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no in-memory file {}", path.display()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_write_keeps_original() {
+        let dir = std::env::temp_dir().join("c2rust-atomic-write");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lib.rs");
+        fs::write(&path, "fn f() {}\n").unwrap();
+
+        let result = write_atomically_with(&path, |file| {
+            file.write_all(b"fn g(")?;
+            Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fn f() {}\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        write_atomically(&path, "fn g() {}\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fn g() {}\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join("c2rust-atomic-write-mode.rs");
+        fs::write(&path, "fn f() {}\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomically(&path, "fn g() {}\n").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+}
@@ -0,0 +1,502 @@
+//! Newline-delimited JSON backend, for editor plugins other than Vim 8.
+//!
+//! Each message is a single JSON object on its own line, using the same encoding as
+//! `vim8_backend`.  Unlike that backend, a malformed request is reported back to the client as an
+//! `error` message instead of stopping the input thread.
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+
+use crate::interact::vim8_backend::{decode_message, encode_message};
+use crate::interact::WrapSender;
+use crate::interact::{ToClient, ToServer};
+
+pub fn init<U, F>(to_server: WrapSender<ToServer, U, F>) -> SyncSender<ToClient>
+where
+    U: Send + 'static,
+    F: Fn(ToServer) -> U + Send + 'static,
+{
+    let (client_send, client_recv) = mpsc::sync_channel(1);
+
+    thread::spawn(move || {
+        let out = io::stdout();
+        let mut out = out.lock();
+
+        for msg in client_recv.iter() {
+            info!("sending: {:?}", msg);
+            out.write_all(encode_line(msg).as_bytes()).unwrap();
+            out.flush().unwrap();
+        }
+    });
+
+    let error_send = client_send.clone();
+    thread::spawn(move || {
+        let in_ = io::stdin();
+        let mut in_ = in_.lock();
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match in_.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match decode_line(&line) {
+                Ok(msg) => {
+                    info!("received: {:?}", msg);
+                    to_server.send(msg).unwrap();
+                }
+                Err(text) => {
+                    error_send
+                        .send(ToClient::Error {
+                            text,
+                            location: None,
+                        })
+                        .unwrap();
+                }
+            }
+        }
+    });
+
+    client_send
+}
+
+/// Encode `msg` as a single line of JSON, including the trailing newline.
+fn encode_line(msg: ToClient) -> String {
+    let mut s = encode_message(msg).dump();
+    s.push('\n');
+    s
+}
+
+fn decode_line(line: &str) -> Result<ToServer, String> {
+    let json = json::parse(line).map_err(|e| format!("invalid JSON: {}", e))?;
+    decode_message(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{CommandInfo, Diagnostic, Severity};
+    use crate::driver::Phase;
+    use crate::interact::{MarkContext, MarkInfo};
+    use crate::rewrite::files::FileEdit;
+    use json::JsonValue;
+
+    fn mark_info() -> MarkInfo {
+        MarkInfo {
+            id: 7,
+            stable_id: "/3/0".to_owned(),
+            file: "src/lib.rs".to_owned(),
+            start_line: 1,
+            start_col: 2,
+            end_line: 3,
+            end_col: 4,
+            labels: vec!["target".to_owned()],
+            virtual_file: false,
+            kind: None,
+            excerpt: None,
+            context: None,
+        }
+    }
+
+    fn encoded(msg: ToClient) -> JsonValue {
+        let line = encode_line(msg);
+        assert!(line.ends_with('\n') && !line[..line.len() - 1].contains('\n'));
+        json::parse(&line).unwrap()
+    }
+
+    #[test]
+    fn encode_client_messages() {
+        let info = object! {
+            "id" => 7,
+            "stable_id" => "/3/0",
+            "file" => "src/lib.rs",
+            "start_line" => 1,
+            "start_col" => 2,
+            "end_line" => 3,
+            "end_col" => 4,
+            "labels" => vec!["target"]
+        };
+
+        assert_eq!(
+            encoded(ToClient::Mark { info: mark_info() }),
+            object! { "msg" => "mark", "info" => info.clone() }
+        );
+        let mut picked = info.clone();
+        picked["kind"] = "stmt".into();
+        assert_eq!(
+            encoded(ToClient::Mark {
+                info: MarkInfo {
+                    kind: Some("stmt".to_owned()),
+                    ..mark_info()
+                }
+            }),
+            object! { "msg" => "mark", "info" => picked }
+        );
+        assert_eq!(
+            encoded(ToClient::MarkList {
+                infos: vec![mark_info()]
+            }),
+            object! { "msg" => "mark-list", "infos" => vec![info.clone()] }
+        );
+        let mut inspected = info.clone();
+        inspected["labels"] = json::JsonValue::new_array();
+        inspected["kind"] = "expr".into();
+        inspected["excerpt"] = "1".into();
+        assert_eq!(
+            encoded(ToClient::NodeInfo {
+                info: MarkInfo {
+                    labels: vec![],
+                    kind: Some("expr".to_owned()),
+                    excerpt: Some("1".to_owned()),
+                    ..mark_info()
+                }
+            }),
+            object! { "msg" => "node-info", "info" => inspected }
+        );
+        let mut with_context = info.clone();
+        with_context["context"] = object! {
+            "before" => vec!["fn f() {"],
+            "after" => Vec::<String>::new()
+        };
+        assert_eq!(
+            encoded(ToClient::Mark {
+                info: MarkInfo {
+                    context: Some(MarkContext {
+                        before: vec!["fn f() {".to_owned()],
+                        after: vec![],
+                    }),
+                    ..mark_info()
+                }
+            }),
+            object! { "msg" => "mark", "info" => with_context }
+        );
+        let mut with_text = info;
+        with_text["excerpt"] = "1 + 2".into();
+        assert_eq!(
+            encoded(ToClient::MarkList {
+                infos: vec![MarkInfo {
+                    excerpt: Some("1 + 2".to_owned()),
+                    ..mark_info()
+                }]
+            }),
+            object! { "msg" => "mark-list", "infos" => vec![with_text] }
+        );
+        assert_eq!(
+            encoded(ToClient::MarkType {
+                id: 3,
+                ty: "u8".to_owned()
+            }),
+            object! { "msg" => "mark-type", "id" => 3, "type" => "u8" }
+        );
+        assert_eq!(
+            encoded(ToClient::CallsMarked {
+                name: "printf".to_owned(),
+                count: 2
+            }),
+            object! { "msg" => "calls-marked", "name" => "printf", "count" => 2 }
+        );
+        assert_eq!(
+            encoded(ToClient::GetBufferText {
+                file: "a.rs".to_owned()
+            }),
+            object! { "msg" => "get-buffer-text", "file" => "a.rs" }
+        );
+        assert_eq!(
+            encoded(ToClient::NewBufferText {
+                file: "a.rs".to_owned(),
+                content: "fn f() {}\n".to_owned()
+            }),
+            object! { "msg" => "new-buffer-text", "file" => "a.rs", "content" => "fn f() {}\n" }
+        );
+        assert_eq!(
+            encoded(ToClient::NewBufferDiff {
+                file: "a.rs".to_owned(),
+                edits: vec![FileEdit {
+                    lo: (2, 4),
+                    hi: (2, 9),
+                    text: "g(3)".to_owned(),
+                }]
+            }),
+            object! {
+                "msg" => "new-buffer-diff",
+                "file" => "a.rs",
+                "edits" => vec![object! {
+                    "start_line" => 2,
+                    "start_col" => 4,
+                    "end_line" => 2,
+                    "end_col" => 9,
+                    "text" => "g(3)"
+                }]
+            }
+        );
+        assert_eq!(
+            encoded(ToClient::Diagnostics {
+                items: vec![Diagnostic {
+                    severity: Severity::Warning,
+                    file: "a.rs".to_owned(),
+                    line: 3,
+                    col: 8,
+                    message: "can't rename across a macro boundary".to_owned(),
+                }]
+            }),
+            object! {
+                "msg" => "diagnostics",
+                "items" => vec![object! {
+                    "severity" => "warning",
+                    "file" => "a.rs",
+                    "line" => 3,
+                    "col" => 8,
+                    "message" => "can't rename across a macro boundary"
+                }]
+            }
+        );
+        assert_eq!(
+            encoded(ToClient::Progress {
+                phase: "running command".to_owned(),
+                message: "rename".to_owned(),
+            }),
+            object! { "msg" => "progress", "phase" => "running command", "message" => "rename" }
+        );
+        assert_eq!(
+            encoded(ToClient::CommandPreview {
+                files: vec!["a.rs".to_owned()],
+                marks_changed: false,
+                krate_changed: true,
+            }),
+            object! {
+                "msg" => "command-preview",
+                "files" => vec!["a.rs"],
+                "marks_changed" => false,
+                "krate_changed" => true
+            }
+        );
+        assert_eq!(
+            encoded(ToClient::CommandDetails {
+                name: "write".to_owned(),
+                info: CommandInfo {
+                    usage: "write".to_owned(),
+                    description: "Write the current crate to disk.".to_owned(),
+                    phase: Some(Phase::Phase2),
+                    min_args: 0,
+                    max_args: None,
+                },
+            }),
+            object! {
+                "msg" => "command-details",
+                "name" => "write",
+                "phase" => "phase2",
+                "usage" => "write",
+                "description" => "Write the current crate to disk.",
+                "min_args" => 0,
+                "max_args" => JsonValue::Null
+            }
+        );
+        assert_eq!(
+            encoded(ToClient::CommandList {
+                commands: vec![("commit".to_owned(), CommandInfo::default())],
+            }),
+            object! {
+                "msg" => "command-list",
+                "commands" => vec![object! {
+                    "name" => "commit",
+                    "phase" => "unknown",
+                    "usage" => "",
+                    "description" => "",
+                    "min_args" => 0,
+                    "max_args" => JsonValue::Null
+                }]
+            }
+        );
+        assert_eq!(
+            encoded(ToClient::Error {
+                text: "oops".to_owned(),
+                location: Some("src/lib.rs:1:1".to_owned())
+            }),
+            object! { "msg" => "error", "text" => "oops", "location" => "src/lib.rs:1:1" }
+        );
+    }
+
+    #[test]
+    fn decode_server_messages() {
+        match decode_line(
+            r#"{"msg": "add-mark", "file": "a.rs", "line": 1, "col": 2, "kind": "expr", "label": "x"}"#,
+        ) {
+            Ok(ToServer::AddMark {
+                file,
+                line: 1,
+                col: 2,
+                kind,
+                label,
+                end_line: None,
+                end_col: None,
+            }) => assert_eq!(
+                (&file as &str, &kind as &str, &label as &str),
+                ("a.rs", "expr", "x")
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(
+            r#"{"msg": "inspect-node", "file": "a.rs", "line": 1, "col": 2, "kind": "expr"}"#,
+        ) {
+            Ok(ToServer::InspectNode {
+                file,
+                line: 1,
+                col: 2,
+                kind,
+            }) => assert_eq!((&file as &str, &kind as &str), ("a.rs", "expr")),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(
+            r#"{"msg": "add-mark-by-name", "name": "f", "kind": "item", "label": "x"}"#,
+        ) {
+            Ok(ToServer::AddMarkByName { name, kind, label }) => assert_eq!(
+                (&name as &str, &kind as &str, &label as &str),
+                ("f", "item", "x")
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "mark-calls-of", "name": "printf", "label": "x"}"#) {
+            Ok(ToServer::MarkCallsOf { name, label }) => {
+                assert_eq!((&name as &str, &label as &str), ("printf", "x"))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "remove-mark", "id": 3}"#) {
+            Ok(ToServer::RemoveMark { id: 3 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "remove-marks-by-label", "label": "target"}"#) {
+            Ok(ToServer::RemoveMarksByLabel { label }) => assert_eq!(label, "target"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "clear-marks"}"#) {
+            Ok(ToServer::ClearMarks) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "get-mark-info", "id": 3}"#) {
+            Ok(ToServer::GetMarkInfo {
+                id: 3,
+                context_lines: 0,
+            }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "get-mark-info", "id": 3, "context_lines": 2}"#) {
+            Ok(ToServer::GetMarkInfo {
+                id: 3,
+                context_lines: 2,
+            }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "get-mark-type", "id": 3}"#) {
+            Ok(ToServer::GetMarkType { id: 3 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "get-mark-list"}"#) {
+            Ok(ToServer::GetMarkList { context_lines: 0 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "get-mark-list", "context_lines": 1}"#) {
+            Ok(ToServer::GetMarkList { context_lines: 1 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "get-mark-list-with-text", "max_len": 40}"#) {
+            Ok(ToServer::GetMarkListWithText { max_len: 40 }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(
+            r#"{"msg": "get-marks-in-range", "file": "a.rs", "start_line": 3, "end_line": 5}"#,
+        ) {
+            Ok(ToServer::GetMarksInRange {
+                file,
+                start_line: 3,
+                end_line: 5,
+            }) => assert_eq!(file, "a.rs"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "save-marks", "path": "marks.json"}"#) {
+            Ok(ToServer::SaveMarks { path }) => assert_eq!(path, "marks.json"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "load-marks", "path": "marks.json"}"#) {
+            Ok(ToServer::LoadMarks { path }) => assert_eq!(path, "marks.json"),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "set-buffers-available", "files": ["a.rs", "b.rs"]}"#) {
+            Ok(ToServer::SetBuffersAvailable { files }) => assert_eq!(files, vec!["a.rs", "b.rs"]),
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "buffer-text", "file": "a.rs", "content": "x"}"#) {
+            Ok(ToServer::BufferText { file, content }) => {
+                assert_eq!((&file as &str, &content as &str), ("a.rs", "x"))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "run-command", "name": "rename", "args": ["a", "b"]}"#) {
+            Ok(ToServer::RunCommand { name, args }) => {
+                assert_eq!(name, "rename");
+                assert_eq!(args, vec!["a", "b"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(
+            r#"{"msg": "run-command-with-args", "name": "rename", "args": [], "rustc_args": ["--cfg", "x"]}"#,
+        ) {
+            Ok(ToServer::RunCommandWithArgs {
+                name,
+                args,
+                rustc_args,
+            }) => {
+                assert_eq!(name, "rename");
+                assert!(args.is_empty());
+                assert_eq!(rustc_args, vec!["--cfg", "x"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "preview-command", "name": "rename", "args": ["a"]}"#) {
+            Ok(ToServer::PreviewCommand { name, args }) => {
+                assert_eq!(name, "rename");
+                assert_eq!(args, vec!["a"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "undo"}"#) {
+            Ok(ToServer::Undo) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "query-command", "name": "commit", "args": ["git"]}"#) {
+            Ok(ToServer::QueryCommand { name, args }) => {
+                assert_eq!(name, "commit");
+                assert_eq!(args, vec!["git"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(r#"{"msg": "list-commands"}"#) {
+            Ok(ToServer::ListCommands) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode_line(
+            r#"{"msg": "run-commands", "commands": [{"name": "a", "args": []}, {"name": "b", "args": ["x"]}]}"#,
+        ) {
+            Ok(ToServer::RunCommands { commands }) => assert_eq!(
+                commands,
+                vec![
+                    ("a".to_owned(), vec![]),
+                    ("b".to_owned(), vec!["x".to_owned()])
+                ]
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(decode_line("not json").is_err());
+        assert!(decode_line(r#"{"msg": "no-such-message"}"#).is_err());
+        assert!(decode_line(r#"{"msg": "remove-mark"}"#).is_err());
+    }
+}
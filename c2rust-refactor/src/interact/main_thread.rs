@@ -1,10 +1,19 @@
 //! The main thread for interactive mode.
 //!
 //! The main thread runs a loop receiving and processing client requests.
+use rustc::hir::Node;
+use rustc::session::Session;
 use rustc_interface::interface::{self, Config};
-use std::collections::{HashMap, HashSet};
+use std::any::Any;
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io;
+use std::mem;
+use std::ops::Range;
 use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -16,25 +25,121 @@ use syntax::source_map::Span;
 use syntax::source_map::{FileLoader, RealFileLoader};
 use syntax::symbol::Symbol;
 use syntax::visit::{self, FnKind, Visitor};
-use syntax_pos::FileName;
+use syntax_pos::{BytePos, FileName, Loc};
 
 use crate::ast_manip::{GetNodeId, GetSpan, Visit};
 use crate::command::{self, RefactorState};
 use crate::driver;
 use crate::file_io::FileIO;
 use crate::interact::worker::{self, ToWorker};
-use crate::interact::WrapSender;
-use crate::interact::{plain_backend, vim8_backend};
+use crate::interact::{canonicalize_lossy, WrapSender};
+use crate::interact::{json_backend, plain_backend, vim8_backend};
 use crate::interact::{ToClient, ToServer};
 use crate::pick_node;
+use crate::rewrite::files::{is_real_file, FileEdit};
 use crate::RefactorCtxt;
 use c2rust_ast_builder::IntoSymbol;
 
-use super::MarkInfo;
+use super::{ColumnEncoding, MarkContext, MarkInfo};
+
+thread_local! {
+    /// Location of the most recent panic on this thread, recorded by the hook installed in
+    /// `interact_command`.
+    static PANIC_LOCATION: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Install a panic hook that records the panic location for `run_loop`, then defers to the
+/// previous hook.
+fn install_panic_hook() {
+    let prev = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(loc) = info.location() {
+            PANIC_LOCATION.with(|l| *l.borrow_mut() = Some(loc.to_string()));
+        }
+        prev(info);
+    }));
+}
+
+/// Extract the message from a panic payload.  `panic!` produces a `&'static str` payload when
+/// given a plain string literal and a `String` when given format arguments.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else if let Some(s) = payload.downcast_ref::<&'static str>() {
+        (*s).to_owned()
+    } else {
+        "An error occurred of unknown type".to_owned()
+    }
+}
+
+fn hash_text(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Span info for marked nodes, saved from an earlier compile so that mark queries don't need to
+/// rebuild the crate while the source is unchanged.
+struct SpanCache {
+    /// Hashes of the contents of every file read by the compile that produced `infos`.
+    sources: HashMap<PathBuf, u64>,
+    /// Locations of nodes, with `labels` left empty and no `excerpt` or `context`.
+    infos: HashMap<NodeId, MarkInfo>,
+}
+
+impl SpanCache {
+    /// Check whether every source file still has the contents the cache was built from.
+    fn is_current<F>(&self, mut read_file: F) -> bool
+    where
+        F: FnMut(&Path) -> io::Result<String>,
+    {
+        self.sources
+            .iter()
+            .all(|(path, &hash)| match read_file(path) {
+                Ok(text) => hash_text(&text) == hash,
+                Err(_) => false,
+            })
+    }
+}
+
+/// The outcome of running a pure command, saved so that running it again on the same crate and
+/// marks doesn't need a compile.
+struct CachedCommand {
+    /// Hashes of the contents of every file read while running the command.
+    sources: HashMap<PathBuf, u64>,
+    /// The marks the command started from.
+    marks: HashSet<(NodeId, Symbol)>,
+    /// The marks the command left behind.
+    result_marks: HashSet<(NodeId, Symbol)>,
+    /// Messages other than progress reports sent to the client while running the command.
+    messages: Vec<ToClient>,
+}
+
+/// Number of commands that can be undone, unless `InteractState::history_depth` is changed.
+const DEFAULT_HISTORY_DEPTH: usize = 20;
+
+/// The state from before a command ran, for `Undo` to go back to.
+struct UndoEntry {
+    /// Original text of each file the command rewrote.
+    files: HashMap<PathBuf, String>,
+    marks: HashSet<(NodeId, Symbol)>,
+}
 
 struct InteractState {
     to_client: SyncSender<ToClient>,
     buffers_available: Arc<Mutex<HashSet<PathBuf>>>,
+    file_io: Arc<InteractiveFileIO>,
+    span_cache: Option<SpanCache>,
+    /// Results of pure commands, by canonical command name and arguments.
+    command_cache: HashMap<(String, Vec<String>), CachedCommand>,
+    /// While running a pure command, the messages to save in its `CachedCommand`.
+    recorded_messages: Option<Vec<ToClient>>,
+    /// States from before each of the most recent commands, oldest first.
+    history: VecDeque<UndoEntry>,
+    /// Maximum number of entries kept in `history`.
+    history_depth: usize,
+    /// How the client counts columns, in both requests and replies.
+    column_encoding: ColumnEncoding,
 
     state: RefactorState,
 }
@@ -43,42 +148,321 @@ impl InteractState {
     fn new(
         state: RefactorState,
         buffers_available: Arc<Mutex<HashSet<PathBuf>>>,
+        file_io: Arc<InteractiveFileIO>,
         _to_worker: SyncSender<ToWorker>,
         to_client: SyncSender<ToClient>,
     ) -> InteractState {
         InteractState {
             to_client,
             buffers_available,
+            file_io,
+            span_cache: None,
+            command_cache: HashMap::new(),
+            recorded_messages: None,
+            history: VecDeque::new(),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+            column_encoding: ColumnEncoding::Chars,
             state,
         }
     }
 
     fn run_loop(&mut self, main_recv: Receiver<ToServer>) {
         for msg in main_recv.iter() {
-            let result = panic::catch_unwind(AssertUnwindSafe(|| {
-                self.handle_one(msg);
-            }));
+            PANIC_LOCATION.with(|loc| loc.borrow_mut().take());
+            let result = panic::catch_unwind(AssertUnwindSafe(|| self.handle_one(msg)));
+            // Don't let a failed `PreviewCommand` suppress the output of later commands.
+            self.file_io.preview_files.lock().unwrap().take();
 
-            if let Err(e) = result {
-                let text = if let Some(s) = e.downcast_ref::<String>() {
-                    s.clone()
-                } else {
-                    "An error occurred of unknown type".to_owned()
-                };
-                self.to_client.send(ToClient::Error { text }).unwrap();
-            }
+            let error = match result {
+                Ok(Ok(())) => continue,
+                Ok(Err(text)) => ToClient::Error {
+                    text,
+                    location: None,
+                },
+                Err(e) => ToClient::Error {
+                    text: panic_message(&*e),
+                    location: PANIC_LOCATION.with(|loc| loc.borrow_mut().take()),
+                },
+            };
+            self.to_client.send(error).unwrap();
         }
+
+        // Only the worker thread sends to `main_recv`, so the loop ends only if it has exited.
+        let _ = self.to_client.send(ToClient::Error {
+            text: "worker thread exited unexpectedly; shutting down".to_owned(),
+            location: None,
+        });
     }
 
     fn run_compiler<F, R>(&mut self, phase: driver::Phase, func: F) -> interface::Result<R>
     where
         F: FnOnce(&Crate, &RefactorCtxt) -> R,
     {
-        self.state
-            .transform_crate(phase, |st, cx| func(&st.krate(), cx))
+        self.file_io.files_read.lock().unwrap().clear();
+        let result = self
+            .state
+            .transform_crate(phase, |st, cx| func(&st.krate(), cx));
+
+        // Start a fresh cache if this compile saw different source text than the cached one.
+        let sources = mem::replace(
+            &mut *self.file_io.files_read.lock().unwrap(),
+            HashMap::new(),
+        );
+        if self
+            .span_cache
+            .as_ref()
+            .map_or(true, |c| c.sources != sources)
+        {
+            self.span_cache = Some(SpanCache {
+                sources,
+                infos: HashMap::new(),
+            });
+        }
+        result
+    }
+
+    /// Get cached span info for all of `ids`, or `None` if any of them is missing or the source
+    /// has changed since the cache was filled.
+    fn cached_infos<'a, I>(&self, ids: I) -> Option<HashMap<NodeId, MarkInfo>>
+    where
+        I: IntoIterator<Item = &'a NodeId>,
+    {
+        let cache = self.span_cache.as_ref()?;
+        let infos = ids
+            .into_iter()
+            .map(|id| cache.infos.get(id).map(|info| (*id, info.clone())))
+            .collect::<Option<HashMap<_, _>>>()?;
+        if !cache.is_current(|path| self.file_io.read_file(path)) {
+            return None;
+        }
+        Some(infos)
+    }
+
+    fn cache_infos<I: IntoIterator<Item = MarkInfo>>(&mut self, infos: I) {
+        if let Some(ref mut cache) = self.span_cache {
+            for mut info in infos {
+                info.labels.clear();
+                info.excerpt = None;
+                info.context = None;
+                cache.infos.insert(NodeId::from_usize(info.id), info);
+            }
+        }
+    }
+
+    /// Tell the client that the server has reached `phase` of handling a request.
+    fn progress(&self, phase: &str, message: String) {
+        info!("{}: {}", phase, message);
+        self.to_client
+            .send(ToClient::Progress {
+                phase: phase.to_owned(),
+                message,
+            })
+            .unwrap();
+    }
+
+    /// Forward any diagnostics reported by commands to the client.
+    fn send_diagnostics(&mut self) {
+        let items = self.state.drain_diagnostics();
+        if !items.is_empty() {
+            let msg = ToClient::Diagnostics { items };
+            if let Some(ref mut recorded) = self.recorded_messages {
+                recorded.push(msg.clone());
+            }
+            self.to_client.send(msg).unwrap();
+        }
+    }
+
+    /// Load the crate, with `cfgs` added to its configuration, run each of `commands` against it,
+    /// and save the result.  Stops at the first command that fails, without saving.
+    fn run_commands(
+        &mut self,
+        commands: &[(String, Vec<String>)],
+        cfgs: &[String],
+    ) -> Result<(), String> {
+        self.span_cache = None;
+        self.progress("starting driver", "loading crate".to_owned());
+        // Loading the crate clears the marks, but the commands should see the client's marks.
+        // Node IDs are assigned the same way on every load, so the marks still apply.
+        let marks = self.state.marks().clone();
+        if cfgs.is_empty() {
+            self.state.load_crate();
+        } else {
+            self.state.load_crate_with_cfgs(cfgs);
+        }
+        *self.state.marks_mut() = marks;
+
+        for (i, (name, args)) in commands.iter().enumerate() {
+            self.progress(
+                "running command",
+                format!("{} {:?} ({} of {})", name, args, i + 1, commands.len()),
+            );
+            let result = self.state.run(name, args);
+            self.send_diagnostics();
+            result.map_err(|e| {
+                if commands.len() == 1 {
+                    format!("command `{}` failed: {}", name, e)
+                } else {
+                    format!("command {} (`{}`) failed: {}", i + 1, name, e)
+                }
+            })?;
+        }
+
+        self.progress("rewriting files", "saving crate".to_owned());
+        self.state.save_crate();
+        Ok(())
+    }
+
+    /// Like `run_commands`, but remember the buffer text and marks from before the commands ran,
+    /// so that `Undo` can restore them.
+    fn run_undoable_commands(
+        &mut self,
+        commands: &[(String, Vec<String>)],
+        cfgs: &[String],
+    ) -> Result<(), String> {
+        let marks = self.state.marks().clone();
+        self.file_io.undo_texts.lock().unwrap().clear();
+        let result = self.run_commands(commands, cfgs);
+        let files = mem::replace(
+            &mut *self.file_io.undo_texts.lock().unwrap(),
+            HashMap::new(),
+        );
+
+        if !files.is_empty() || *self.state.marks() != marks {
+            self.push_history(UndoEntry { files, marks });
+        }
+        result
     }
 
-    fn handle_one(&mut self, msg: ToServer) {
+    /// Remember `entry` for `Undo`, forgetting the oldest entry if the history is full.
+    fn push_history(&mut self, entry: UndoEntry) {
+        self.history.push_back(entry);
+        while self.history.len() > self.history_depth {
+            self.history.pop_front();
+        }
+    }
+
+    /// Run the pure command `name`, replaying its results from `command_cache` instead if it
+    /// already ran with the same arguments on the current crate source and marks.
+    fn run_pure_command(&mut self, name: &str, args: Vec<String>) -> Result<(), String> {
+        let key = (self.state.canonical_command_name(name), args);
+        let marks = self.state.marks().clone();
+
+        let hit = match self.command_cache.get(&key) {
+            Some(cached) => {
+                cached.marks == marks
+                    && cached.sources.iter().all(|(path, &hash)| {
+                        match self.file_io.read_file(path) {
+                            Ok(text) => hash_text(&text) == hash,
+                            Err(_) => false,
+                        }
+                    })
+            }
+            None => false,
+        };
+        if hit {
+            let cached = &self.command_cache[&key];
+            self.progress(
+                "running command",
+                format!("{} {:?} (cached)", key.0, key.1),
+            );
+            for msg in &cached.messages {
+                self.to_client.send(msg.clone()).unwrap();
+            }
+            let result_marks = cached.result_marks.clone();
+            if result_marks != marks {
+                self.push_history(UndoEntry {
+                    files: HashMap::new(),
+                    marks,
+                });
+                *self.state.marks_mut() = result_marks;
+            }
+            return Ok(());
+        }
+
+        self.file_io.files_read.lock().unwrap().clear();
+        self.recorded_messages = Some(Vec::new());
+        let result = self.run_undoable_commands(&[key.clone()], &[]);
+        let messages = self.recorded_messages.take().unwrap();
+        let sources = mem::replace(
+            &mut *self.file_io.files_read.lock().unwrap(),
+            HashMap::new(),
+        );
+        result?;
+
+        let result_marks = self.state.marks().clone();
+        self.command_cache.insert(
+            key,
+            CachedCommand {
+                sources,
+                marks,
+                result_marks,
+                messages,
+            },
+        );
+        Ok(())
+    }
+
+    /// Get info about all current marks, compiling only if the cache can't provide it.
+    fn mark_infos(&mut self) -> Vec<MarkInfo> {
+        let marks = self.state.marks().clone();
+        let ids = marks.iter().map(|&(id, _)| id).collect::<HashSet<_>>();
+        let spans = match self.cached_infos(&ids) {
+            Some(spans) => spans,
+            None => self.compile_mark_infos(ids, None, 0),
+        };
+        label_mark_infos(&marks, &spans)
+    }
+
+    /// Like `mark_infos`, but with up to `max_excerpt` characters of each marked node's source
+    /// text and `context_lines` lines of source around it.  The cache doesn't keep source text, so
+    /// this always compiles.
+    fn mark_infos_with_text(
+        &mut self,
+        max_excerpt: Option<usize>,
+        context_lines: u32,
+    ) -> Vec<MarkInfo> {
+        let marks = self.state.marks().clone();
+        let ids = marks.iter().map(|&(id, _)| id).collect::<HashSet<_>>();
+        let spans = self.compile_mark_infos(ids, max_excerpt, context_lines);
+        label_mark_infos(&marks, &spans)
+    }
+
+    /// Compile the crate to get unlabeled info about the nodes `ids`, and cache it.  With
+    /// `max_excerpt`, each info includes up to that many characters of the node's source text,
+    /// and with nonzero `context_lines`, up to that many lines above and below the node.
+    fn compile_mark_infos(
+        &mut self,
+        ids: HashSet<NodeId>,
+        max_excerpt: Option<usize>,
+        context_lines: u32,
+    ) -> HashMap<NodeId, MarkInfo> {
+        self.progress("starting driver", "collecting mark locations".to_owned());
+        let columns = self.column_encoding;
+        let spans = self
+            .run_compiler(driver::Phase::Phase2, |krate, cx| {
+                let mut stable_ids = pick_node::stable_node_ids(krate, &ids);
+                collect_spans(krate, ids.clone())
+                    .into_iter()
+                    .map(|(id, span)| {
+                        let stable_id = stable_ids.remove(&id).unwrap_or_default();
+                        let mut info = span_mark_info(id, span, stable_id, cx, columns);
+                        if let Some(max_len) = max_excerpt {
+                            let snippet = cx.session().source_map().span_to_snippet(span);
+                            info.excerpt = snippet.ok().map(|s| truncate_excerpt(&s, max_len));
+                        }
+                        if context_lines > 0 {
+                            info.context = Some(mark_context(span, context_lines, cx));
+                        }
+                        (id, info)
+                    })
+                    .collect::<HashMap<_, _>>()
+            })
+            .expect("Failed to run compiler");
+        self.cache_infos(spans.values().cloned());
+        spans
+    }
+
+    fn handle_one(&mut self, msg: ToServer) -> Result<(), String> {
         use super::ToClient::*;
         use super::ToServer::*;
 
@@ -89,53 +473,173 @@ impl InteractState {
                 col,
                 kind,
                 label,
+                end_line,
+                end_col,
             } => {
-                let kind = pick_node::NodeKind::from_str(&kind).unwrap();
+                let kinds = pick_node::parse_node_kinds(&kind).map_err(|e| e.to_string())?;
                 let label = label.into_symbol();
+                let columns = self.column_encoding;
 
-                let (id, mark_info) = self
-                    .run_compiler(driver::Phase::Phase2, |krate, cx| {
-                        let info = pick_node::pick_node_at_loc(
-                            &krate,
-                            cx.session(),
-                            kind,
-                            &file,
-                            line,
-                            col,
-                        )
-                        .unwrap_or_else(|| {
-                            panic!("no {:?} node at {}:{}:{}", kind, file, line, col)
-                        });
-
-                        let lo = cx.session().source_map().lookup_char_pos(info.span.lo());
-                        let hi = cx.session().source_map().lookup_char_pos(info.span.hi());
-                        let file = filename_to_str(&lo.file.name);
-                        (
+                let (id, matched, mut mark_info) = self
+                    .run_compiler(driver::Phase::Phase2, |krate, cx| -> Result<_, String> {
+                        let byte_col =
+                            |line, col| byte_column(cx.session(), &file, line, col, columns);
+                        let (matched, info) = match (end_line, end_col) {
+                            (Some(end_line), Some(end_col)) => {
+                                pick_node::pick_first_kind(&kinds, |kind| {
+                                    pick_node::pick_node_covering_loc_range(
+                                        &krate,
+                                        cx.session(),
+                                        kind,
+                                        &file,
+                                        (line, byte_col(line, col)),
+                                        (end_line, byte_col(end_line, end_col)),
+                                    )
+                                })
+                                .ok_or_else(|| {
+                                    format!(
+                                        "no {} node covering {}:{}:{} to {}:{}",
+                                        kind, file, line, col, end_line, end_col
+                                    )
+                                })?
+                            }
+                            _ => pick_node::pick_first_kind(&kinds, |kind| {
+                                pick_node::pick_node_at_loc(
+                                    &krate,
+                                    cx.session(),
+                                    kind,
+                                    &file,
+                                    line,
+                                    byte_col(line, col),
+                                )
+                            })
+                            .ok_or_else(|| {
+                                format!("no {} node at {}:{}:{}", kind, file, line, col)
+                            })?,
+                        };
+
+                        let stable_id = stable_node_id(&krate, info.id);
+                        Ok((
                             info.id,
-                            MarkInfo {
-                                id: info.id.as_usize(),
-                                file,
-                                start_line: lo.line as u32,
-                                start_col: lo.col.0 as u32,
-                                end_line: hi.line as u32,
-                                end_col: hi.col.0 as u32,
-                                labels: vec![(&label.as_str() as &str).to_owned()],
-                            },
-                        )
+                            matched,
+                            span_mark_info(info.id, info.span, stable_id, cx, columns),
+                        ))
                     })
-                    .expect("Failed to run compiler");
+                    .expect("Failed to run compiler")?;
 
+                self.cache_infos(Some(mark_info.clone()));
+                check_real_file(&mark_info)?;
                 self.state.marks_mut().insert((id, label));
+                mark_info.labels = vec![(&label.as_str() as &str).to_owned()];
+                mark_info.kind = Some(matched.as_str().to_owned());
                 self.to_client.send(Mark { info: mark_info }).unwrap();
             }
 
+            InspectNode {
+                file,
+                line,
+                col,
+                kind,
+            } => {
+                let kinds = pick_node::parse_node_kinds(&kind).map_err(|e| e.to_string())?;
+                let columns = self.column_encoding;
+
+                let info = self
+                    .run_compiler(driver::Phase::Phase2, |krate, cx| -> Result<_, String> {
+                        let byte_col = byte_column(cx.session(), &file, line, col, columns);
+                        let (matched, node) = pick_node::pick_first_kind(&kinds, |kind| {
+                            pick_node::pick_node_at_loc(
+                                &krate,
+                                cx.session(),
+                                kind,
+                                &file,
+                                line,
+                                byte_col,
+                            )
+                        })
+                        .ok_or_else(|| format!("no {} node at {}:{}:{}", kind, file, line, col))?;
+
+                        let stable_id = stable_node_id(&krate, node.id);
+                        let mut info = span_mark_info(node.id, node.span, stable_id, cx, columns);
+                        info.kind = Some(matched.as_str().to_owned());
+                        info.excerpt = cx.session().source_map().span_to_snippet(node.span).ok();
+                        Ok(info)
+                    })
+                    .expect("Failed to run compiler")?;
+
+                check_real_file(&info)?;
+                self.to_client.send(NodeInfo { info }).unwrap();
+            }
+
+            AddMarkByName { name, kind, label } => {
+                let kind = pick_node::NodeKind::from_str(&kind).map_err(|e| e.to_string())?;
+                let label = label.into_symbol();
+                let columns = self.column_encoding;
+
+                let mut mark_info = self
+                    .run_compiler(driver::Phase::Phase2, |krate, cx| {
+                        pick_node::pick_unique_node_by_name(&krate, kind, &name).map(|info| {
+                            let stable_id = stable_node_id(&krate, info.id);
+                            span_mark_info(info.id, info.span, stable_id, cx, columns)
+                        })
+                    })
+                    .expect("Failed to run compiler")?;
+
+                self.cache_infos(Some(mark_info.clone()));
+                check_real_file(&mark_info)?;
+                let id = NodeId::from_usize(mark_info.id);
+                self.state.marks_mut().insert((id, label));
+                mark_info.labels = vec![(&label.as_str() as &str).to_owned()];
+                self.to_client.send(Mark { info: mark_info }).unwrap();
+            }
+
+            MarkCallsOf { name, label } => {
+                let label = label.into_symbol();
+                let columns = self.column_encoding;
+
+                let infos = self
+                    .run_compiler(driver::Phase::Phase3, |krate, cx| {
+                        let calls = pick_node::pick_calls_of(&krate, cx, &name);
+                        let ids = calls.iter().map(|info| info.id).collect();
+                        let mut stable_ids = pick_node::stable_node_ids(&krate, &ids);
+                        calls
+                            .into_iter()
+                            .map(|info| {
+                                let stable_id = stable_ids.remove(&info.id).unwrap_or_default();
+                                span_mark_info(info.id, info.span, stable_id, cx, columns)
+                            })
+                            .filter(|info| !info.virtual_file)
+                            .collect::<Vec<_>>()
+                    })
+                    .expect("Failed to run compiler");
+
+                self.cache_infos(infos.iter().cloned());
+                let count = infos.len();
+                for info in infos {
+                    let id = NodeId::from_usize(info.id);
+                    self.state.marks_mut().insert((id, label));
+                }
+                self.to_client.send(CallsMarked { name, count }).unwrap();
+            }
+
             RemoveMark { id } => {
                 self.state
                     .marks_mut()
                     .retain(|&(mark_id, _)| mark_id.as_usize() != id);
             }
 
-            GetMarkInfo { id } => {
+            RemoveMarksByLabel { label } => {
+                remove_label(self.state.marks_mut(), label.into_symbol());
+                let infos = self.mark_infos();
+                self.to_client.send(MarkList { infos }).unwrap();
+            }
+
+            ClearMarks => {
+                self.state.clear_marks();
+                self.to_client.send(MarkList { infos: vec![] }).unwrap();
+            }
+
+            GetMarkInfo { id, context_lines } => {
                 let id = NodeId::from_usize(id);
 
                 let mut labels = Vec::new();
@@ -146,63 +650,229 @@ impl InteractState {
                 }
                 labels.sort();
 
-                let msg = self
-                    .run_compiler(driver::Phase::Phase2, |_krate, cx| {
-                        let hir_id = cx.hir_map().node_to_hir_id(id);
-                        let span = cx.hir_map().span(hir_id);
-                        let lo = cx.session().source_map().lookup_char_pos(span.lo());
-                        let hi = cx.session().source_map().lookup_char_pos(span.hi());
-                        let file = filename_to_str(&lo.file.name);
-                        let info = MarkInfo {
-                            id: id.as_usize(),
-                            file,
-                            start_line: lo.line as u32,
-                            start_col: lo.col.0 as u32,
-                            end_line: hi.line as u32,
-                            end_col: hi.col.0 as u32,
-                            labels,
-                        };
-                        Mark { info }
+                // The cache doesn't keep context lines, so only use it if none were asked for.
+                let cached = if context_lines == 0 {
+                    self.cached_infos(&[id])
+                } else {
+                    None
+                };
+                let mut info = match cached {
+                    Some(mut infos) => infos.remove(&id).unwrap(),
+                    None => {
+                        let columns = self.column_encoding;
+                        let info = self
+                            .run_compiler(driver::Phase::Phase2, |krate, cx| {
+                                let hir_id = cx.hir_map().node_to_hir_id(id);
+                                let span = cx.hir_map().span(hir_id);
+                                let stable_id = stable_node_id(&krate, id);
+                                let mut info = span_mark_info(id, span, stable_id, cx, columns);
+                                if context_lines > 0 {
+                                    info.context = Some(mark_context(span, context_lines, cx));
+                                }
+                                info
+                            })
+                            .expect("Failed to run compiler");
+                        self.cache_infos(Some(info.clone()));
+                        info
+                    }
+                };
+                check_real_file(&info)?;
+                info.labels = labels;
+                self.to_client.send(Mark { info }).unwrap();
+            }
+
+            GetMarkType { id } => {
+                let node_id = NodeId::from_usize(id);
+                let ty = self
+                    .run_compiler(driver::Phase::Phase3, |_krate, cx| {
+                        match cx.hir_map().find(node_id) {
+                            Some(Node::Expr(_)) => cx
+                                .opt_node_type(node_id)
+                                .map(|ty| ty.to_string())
+                                .ok_or_else(|| format!("no type information for node {}", id)),
+                            Some(_) => Err(format!("node {} is not an expression", id)),
+                            None => Err(format!("no node {} in the crate", id)),
+                        }
                     })
-                    .expect("Failed to run compiler");
-                self.to_client.send(msg).unwrap();
+                    .expect("Failed to run compiler")?;
+                self.to_client.send(MarkType { id, ty }).unwrap();
+            }
+
+            GetMarkList { context_lines } => {
+                let infos = if context_lines == 0 {
+                    self.mark_infos()
+                } else {
+                    self.mark_infos_with_text(None, context_lines)
+                };
+                self.to_client.send(MarkList { infos }).unwrap();
+            }
+
+            GetMarkListWithText { max_len } => {
+                let infos = self.mark_infos_with_text(Some(max_len), 0);
+                self.to_client.send(MarkList { infos }).unwrap();
+            }
+
+            GetMarksInRange {
+                file,
+                start_line,
+                end_line,
+            } => {
+                let infos = marks_in_range(self.mark_infos(), &file, start_line, end_line);
+                self.to_client.send(MarkList { infos }).unwrap();
+            }
+
+            SaveMarks { path } => {
+                let text = encode_saved_marks(self.mark_infos());
+                fs::write(&path, text)
+                    .map_err(|e| format!("failed to write marks to {}: {}", path, e))?;
             }
 
-            GetMarkList => {
-                let msg = self
-                    .state
-                    .transform_crate(driver::Phase::Phase2, |st, cx| {
-                        let infos = collect_mark_infos(&st.marks(), &st.krate(), &cx);
-                        MarkList { infos }
+            LoadMarks { path } => {
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("failed to read marks from {}: {}", path, e))?;
+                let saved = decode_saved_marks(&text)?;
+                let columns = self.column_encoding;
+
+                let (found, missing) = self
+                    .run_compiler(driver::Phase::Phase2, |krate, cx| {
+                        let mut found = Vec::new();
+                        let mut missing = Vec::new();
+                        for info in saved {
+                            let byte_col = |line, col| {
+                                byte_column(cx.session(), &info.file, line, col, columns)
+                            };
+                            let node = pick_node::pick_node_covering_loc_range(
+                                krate,
+                                cx.session(),
+                                pick_node::NodeKind::Any,
+                                &info.file,
+                                (info.start_line, byte_col(info.start_line, info.start_col)),
+                                (info.end_line, byte_col(info.end_line, info.end_col)),
+                            );
+                            match node {
+                                Some(node) => found.push((node.id, info.labels)),
+                                None => missing.push(format!(
+                                    "{}:{}:{}",
+                                    info.file, info.start_line, info.start_col
+                                )),
+                            }
+                        }
+                        (found, missing)
                     })
                     .expect("Failed to run compiler");
-                self.to_client.send(msg).unwrap();
+
+                for (id, labels) in found {
+                    for label in labels {
+                        self.state.marks_mut().insert((id, label.into_symbol()));
+                    }
+                }
+                let infos = self.mark_infos();
+                self.to_client.send(MarkList { infos }).unwrap();
+
+                if !missing.is_empty() {
+                    return Err(format!(
+                        "no nodes found for saved marks at {}",
+                        missing.join(", ")
+                    ));
+                }
             }
 
             SetBuffersAvailable { files } => {
+                self.span_cache = None;
+                self.command_cache.clear();
                 let mut buffers = self.buffers_available.lock().unwrap();
                 *buffers = files
                     .into_iter()
-                    .filter_map(|x| fs::canonicalize(&x).ok())
+                    .map(|x| canonicalize_lossy(Path::new(&x)))
                     .collect();
             }
 
             RunCommand { name, args } => {
-                info!("running command {} with args {:?}", name, args);
+                if self.state.is_pure_command(&name, &args)? {
+                    self.run_pure_command(&name, args)?;
+                } else {
+                    self.command_cache.clear();
+                    self.run_undoable_commands(&[(name, args)], &[])?;
+                }
+            }
+
+            RunCommandWithArgs {
+                name,
+                args,
+                rustc_args,
+            } => {
+                self.command_cache.clear();
+                let cfgs = parse_cfg_args(&rustc_args)?;
+                self.run_undoable_commands(&[(name, args)], &cfgs)?;
+            }
+
+            PreviewCommand { name, args } => {
+                let old_marks = self.state.marks().clone();
+
+                *self.file_io.preview_files.lock().unwrap() = Some(Vec::new());
+                let result = self.run_commands(&[(name, args)], &[]);
+                let mut files = self.file_io.preview_files.lock().unwrap().take().unwrap();
+                files.sort();
+                files.dedup();
+                let marks_changed = *self.state.marks() != old_marks;
+
+                // Throw away the command's changes to the in-memory crate and marks.
                 self.state.load_crate();
-                match self.state.run(&name, &args) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        eprintln!("{:?}", e);
-                        panic!("Invalid command.");
-                    }
+                *self.state.marks_mut() = old_marks;
+
+                result?;
+                self.to_client
+                    .send(CommandPreview {
+                        krate_changed: !files.is_empty(),
+                        files,
+                        marks_changed,
+                    })
+                    .unwrap();
+            }
+
+            RunCommands { commands } => {
+                self.command_cache.clear();
+                self.run_undoable_commands(&commands, &[])?;
+            }
+
+            Undo => {
+                let entry = self
+                    .history
+                    .pop_back()
+                    .ok_or_else(|| "nothing to undo".to_owned())?;
+                self.command_cache.clear();
+                let mut files = entry.files.into_iter().collect::<Vec<_>>();
+                files.sort();
+                for (path, content) in files {
+                    let file = path.to_str().unwrap().to_owned();
+                    self.to_client
+                        .send(NewBufferText { file, content })
+                        .unwrap();
                 }
-                self.state.save_crate();
+
+                self.span_cache = None;
+                self.state.load_crate();
+                *self.state.marks_mut() = entry.marks;
+                let infos = self.mark_infos();
+                self.to_client.send(MarkList { infos }).unwrap();
+            }
+
+            QueryCommand { name, args } => {
+                let info = self.state.query_command(&name, &args)?;
+                let name = self.state.canonical_command_name(&name);
+                self.to_client.send(CommandDetails { name, info }).unwrap();
+            }
+
+            ListCommands => {
+                let commands = self.state.command_list();
+                self.to_client.send(CommandList { commands }).unwrap();
             }
 
             // Other messages are handled by the worker thread
             BufferText { .. } => unreachable!(),
         }
+
+        Ok(())
     }
 }
 
@@ -210,36 +880,249 @@ fn filename_to_str(filename: &FileName) -> String {
     match filename {
         &FileName::Real(ref pathbuf) => pathbuf.to_str().expect("Invalid path name").to_owned(),
         &FileName::Macros(ref macros) => format!("<{}>", macros),
-        other => panic!("Need to implement name conversion for {:?}", other),
+        other => other.to_string(),
     }
 }
 
-fn collect_mark_infos(
-    marks: &HashSet<(NodeId, Symbol)>,
-    krate: &Crate,
+/// Fail if `info` describes a node that clients can't see, because it lies in a virtual file.
+fn check_real_file(info: &MarkInfo) -> Result<(), String> {
+    if info.virtual_file {
+        Err(format!(
+            "node {} is in virtual file {}, which can't be marked",
+            info.id, info.file
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Extract the cfg specs from `--cfg SPEC` and `--cfg=SPEC` flags in `args`.  Other rustc flags
+/// are rejected, since only the crate configuration can be changed without rebuilding the
+/// session options.
+fn parse_cfg_args(args: &[String]) -> Result<Vec<String>, String> {
+    let mut cfgs = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let spec = if arg == "--cfg" {
+            iter.next()
+                .ok_or_else(|| "missing value after `--cfg`".to_owned())?
+                .clone()
+        } else if arg.starts_with("--cfg=") {
+            arg["--cfg=".len()..].to_owned()
+        } else {
+            return Err(format!(
+                "unsupported rustc flag `{}`; only `--cfg` is allowed",
+                arg
+            ));
+        };
+
+        // rustc exits the process on a malformed spec, so check it here first.
+        let (name, value) = match spec.find('=') {
+            Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+            None => (&spec[..], None),
+        };
+        let valid_name = name
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        let valid_value = value.map_or(true, |v| {
+            v.len() >= 2 && v.starts_with('"') && v.ends_with('"')
+        });
+        if !valid_name || !valid_value {
+            return Err(format!("invalid cfg spec `{}`", spec));
+        }
+        cfgs.push(spec);
+    }
+    Ok(cfgs)
+}
+
+/// Remove every mark labeled `label`.
+fn remove_label(marks: &mut HashSet<(NodeId, Symbol)>, label: Symbol) {
+    marks.retain(|&(_, mark_label)| mark_label != label);
+}
+
+/// Serialize marks for `SaveMarks`.  Node IDs are not stable across sessions, so each mark is
+/// stored with the location of its node, which `LoadMarks` uses to find the node again.
+fn encode_saved_marks(infos: Vec<MarkInfo>) -> String {
+    let marks = infos
+        .into_iter()
+        .map(|i| {
+            object! {
+                "file" => i.file,
+                "start_line" => i.start_line,
+                "start_col" => i.start_col,
+                "end_line" => i.end_line,
+                "end_col" => i.end_col,
+                "labels" => i.labels
+            }
+        })
+        .collect::<Vec<_>>();
+    json::stringify_pretty(object! { "marks" => marks }, 2)
+}
+
+/// Parse marks written by `encode_saved_marks`.  The `id` of each result is meaningless.
+fn decode_saved_marks(text: &str) -> Result<Vec<MarkInfo>, String> {
+    let json = json::parse(text).map_err(|e| format!("invalid marks file: {}", e))?;
+    let bad = || "invalid marks file: unexpected structure".to_owned();
+
+    let mut infos = Vec::new();
+    for m in json["marks"].members() {
+        let labels = m["labels"]
+            .members()
+            .map(|l| l.as_str().map(|s| s.to_owned()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(bad)?;
+        infos.push(MarkInfo {
+            id: 0,
+            stable_id: String::new(),
+            file: m["file"].as_str().ok_or_else(bad)?.to_owned(),
+            start_line: m["start_line"].as_u32().ok_or_else(bad)?,
+            start_col: m["start_col"].as_u32().ok_or_else(bad)?,
+            end_line: m["end_line"].as_u32().ok_or_else(bad)?,
+            end_col: m["end_col"].as_u32().ok_or_else(bad)?,
+            labels,
+            virtual_file: false,
+            kind: None,
+            excerpt: None,
+            context: None,
+        });
+    }
+    Ok(infos)
+}
+
+/// Get the `stable_node_ids` identifier of node `id`.
+fn stable_node_id(krate: &Crate, id: NodeId) -> String {
+    let mut ids = HashSet::new();
+    ids.insert(id);
+    pick_node::stable_node_ids(krate, &ids)
+        .remove(&id)
+        .unwrap_or_default()
+}
+
+/// Build an unlabeled `MarkInfo` describing the location of node `id`, with columns counted as
+/// `columns` says.
+fn span_mark_info(
+    id: NodeId,
+    span: Span,
+    stable_id: String,
     cx: &RefactorCtxt,
+    columns: ColumnEncoding,
+) -> MarkInfo {
+    let lo = cx.session().source_map().lookup_char_pos(span.lo());
+    let hi = cx.session().source_map().lookup_char_pos(span.hi());
+    let file = filename_to_str(&lo.file.name);
+
+    MarkInfo {
+        id: id.as_usize(),
+        stable_id,
+        file,
+        start_line: lo.line as u32,
+        start_col: loc_column(&lo, span.lo(), columns),
+        end_line: hi.line as u32,
+        end_col: loc_column(&hi, span.hi(), columns),
+        labels: vec![],
+        virtual_file: !is_real_file(&lo.file.name),
+        kind: None,
+        excerpt: None,
+        context: None,
+    }
+}
+
+/// Get the column of `loc`, the location of `pos`, counted as `columns` says.
+fn loc_column(loc: &Loc, pos: BytePos, columns: ColumnEncoding) -> u32 {
+    let (line_start, _) = loc.file.line_bounds(loc.line - 1);
+    let byte = (pos - line_start).0 as usize;
+    match columns {
+        ColumnEncoding::Chars => loc.col.0 as u32,
+        ColumnEncoding::Utf8Bytes => byte as u32,
+        ColumnEncoding::Utf16 => match loc.file.get_line(loc.line - 1) {
+            Some(text) => columns.column(&text, byte),
+            None => loc.col.0 as u32,
+        },
+    }
+}
+
+/// Convert column `col` of `line` in `file`, counted as `columns` says, into the byte offset
+/// within the line that `pick_node` expects.  Columns on lines that can't be found are assumed to
+/// be byte offsets already.
+fn byte_column(session: &Session, file: &str, line: u32, col: u32, columns: ColumnEncoding) -> u32 {
+    let text = session
+        .source_map()
+        .get_source_file(&FileName::Real(PathBuf::from(file)))
+        .and_then(|fm| fm.get_line((line as usize).wrapping_sub(1)).map(|l| l.into_owned()));
+    match text {
+        Some(text) if columns != ColumnEncoding::Utf8Bytes => {
+            columns.byte_offset(&text, col) as u32
+        }
+        _ => col,
+    }
+}
+
+/// Get up to `n` lines of source on either side of `span`, stopping at the edges of its file.
+fn mark_context(span: Span, n: u32, cx: &RefactorCtxt) -> MarkContext {
+    let source_map = cx.session().source_map();
+    let lo = source_map.lookup_char_pos(span.lo());
+    let hi = source_map.lookup_char_pos(span.hi());
+    let lines = |range: Range<usize>| {
+        range
+            .map(|i| {
+                lo.file
+                    .get_line(i)
+                    .map_or_else(String::new, |l| l.into_owned())
+            })
+            .collect()
+    };
+
+    // `Loc` line numbers are 1-based, but `get_line` takes 0-based indices.
+    let n = n as usize;
+    let first = lo.line - 1;
+    let last = hi.line - 1;
+    let end = cmp::min(last + 1 + n, lo.file.count_lines());
+    MarkContext {
+        before: lines(first.saturating_sub(n)..first),
+        after: lines(last + 1..end),
+    }
+}
+
+/// Cut `text` down to at most `max_len` characters, ending with an ellipsis if anything was cut.
+fn truncate_excerpt(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        return text.to_owned();
+    }
+    let mut s = text
+        .chars()
+        .take(max_len.saturating_sub(1))
+        .collect::<String>();
+    s.push('\u{2026}');
+    s
+}
+
+/// Keep only the marks on nodes in `file` that overlap lines `start_line` through `end_line`.
+fn marks_in_range(
+    infos: Vec<MarkInfo>,
+    file: &str,
+    start_line: u32,
+    end_line: u32,
 ) -> Vec<MarkInfo> {
-    let ids = marks.iter().map(|&(id, _)| id).collect();
-    let span_map = collect_spans(krate, ids);
+    infos
+        .into_iter()
+        .filter(|i| i.file == file && i.start_line <= end_line && i.end_line >= start_line)
+        .collect()
+}
 
+/// Attach the labels in `marks` to the node locations in `spans`, leaving out marks in virtual
+/// files.
+fn label_mark_infos(
+    marks: &HashSet<(NodeId, Symbol)>,
+    spans: &HashMap<NodeId, MarkInfo>,
+) -> Vec<MarkInfo> {
     let mut infos = HashMap::with_capacity(marks.len());
     for &(id, label) in marks {
-        let info = infos.entry(id).or_insert_with(|| {
-            let span = span_map[&id];
-            let lo = cx.session().source_map().lookup_char_pos(span.lo());
-            let hi = cx.session().source_map().lookup_char_pos(span.hi());
-            let file = filename_to_str(&lo.file.name);
-
-            MarkInfo {
-                id: id.as_usize(),
-                file,
-                start_line: lo.line as u32,
-                start_col: lo.col.0 as u32,
-                end_line: hi.line as u32,
-                end_col: hi.col.0 as u32,
-                labels: vec![],
-            }
-        });
+        if spans[&id].virtual_file {
+            continue;
+        }
+        let info = infos.entry(id).or_insert_with(|| spans[&id].clone());
         info.labels.push((&label.as_str() as &str).to_owned());
     }
 
@@ -253,14 +1136,45 @@ fn collect_mark_infos(
 }
 
 pub fn interact_command(args: &[String], config: Config, registry: command::Registry) {
+    interact_command_with_loader(args, config, registry, Arc::new(RealFileLoader))
+}
+
+/// Like `interact_command`, but reading source files the client has no buffer for through
+/// `file_loader` instead of from disk.  With a `VirtualFileLoader`, the server can work on a crate
+/// that exists only in memory.
+pub fn interact_command_with_loader(
+    args: &[String],
+    config: Config,
+    registry: command::Registry,
+    file_loader: Arc<dyn FileLoader + Send + Sync>,
+) {
     let (to_main, main_recv) = mpsc::channel();
     let (to_worker, worker_recv) = mpsc::sync_channel(1);
 
+    // Options after the backend name are given as `key=value`.
+    let mut column_encoding = ColumnEncoding::Chars;
+    let mut buffer_diffs = false;
+    for arg in args.iter().skip(1) {
+        let mut parts = arg.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some("columns"), Some(name)) => {
+                column_encoding = ColumnEncoding::from_name(name)
+                    .unwrap_or_else(|| panic!("unknown column encoding {:?}", name));
+            }
+            (Some("diffs"), Some("on")) => buffer_diffs = true,
+            (Some("diffs"), Some("off")) => buffer_diffs = false,
+            _ => panic!("unknown interact option {:?}", arg),
+        }
+    }
+
     let backend_to_worker = WrapSender::new(to_worker.clone(), ToWorker::InputMessage);
-    let to_client = if !args.is_empty() && &args[0] == "vim8" {
-        vim8_backend::init(backend_to_worker)
-    } else {
-        plain_backend::init(backend_to_worker)
+    let to_client = match args.get(0).map(|s| s as &str) {
+        Some("vim8") => vim8_backend::init(backend_to_worker),
+        Some("json") => json_backend::init(backend_to_worker),
+        Some("plain-framed") => {
+            plain_backend::init(backend_to_worker, plain_backend::Framing::LengthPrefixed)
+        }
+        _ => plain_backend::init(backend_to_worker, plain_backend::Framing::Lines),
     };
 
     let to_client_ = to_client.clone();
@@ -270,53 +1184,188 @@ pub fn interact_command(args: &[String], config: Config, registry: command::Regi
 
     let buffers_available = Arc::new(Mutex::new(HashSet::new()));
 
+    install_panic_hook();
+
     let file_io = Arc::new(InteractiveFileIO {
         buffers_available: buffers_available.clone(),
+        file_loader,
         to_worker: to_worker.clone(),
         to_client: to_client.clone(),
+        files_read: Arc::new(Mutex::new(HashMap::new())),
+        texts_read: Arc::new(Mutex::new(HashMap::new())),
+        undo_texts: Arc::new(Mutex::new(HashMap::new())),
+        crlf_files: Arc::new(Mutex::new(HashSet::new())),
+        preview_files: Arc::new(Mutex::new(None)),
+        buffer_diffs,
     });
 
-    driver::run_refactoring(config, registry, file_io, HashSet::new(), |state| {
-        InteractState::new(state, buffers_available, to_worker, to_client).run_loop(main_recv);
+    driver::run_refactoring(config, registry, file_io.clone(), HashSet::new(), |state| {
+        let mut st = InteractState::new(state, buffers_available, file_io, to_worker, to_client);
+        st.column_encoding = column_encoding;
+        st.run_loop(main_recv);
     });
 }
 
 #[derive(Clone)]
 struct InteractiveFileIO {
     buffers_available: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Where to read files that aren't available as client buffers.
+    file_loader: Arc<dyn FileLoader + Send + Sync>,
     to_worker: SyncSender<ToWorker>,
     to_client: SyncSender<ToClient>,
+    /// Hashes of the files read so far, used to validate `InteractState::span_cache`.
+    files_read: Arc<Mutex<HashMap<PathBuf, u64>>>,
+    /// Text of each file as last read, which is what a command's rewrite of it replaces.
+    texts_read: Arc<Mutex<HashMap<PathBuf, String>>>,
+    /// Text from before the first write of each file written outside of previews, for
+    /// `InteractState::run_undoable_commands` to collect.
+    undo_texts: Arc<Mutex<HashMap<PathBuf, String>>>,
+    /// Files that used CRLF line endings when read.  rustc normalizes these to LF, so line
+    /// endings must be restored in anything sent back to the client.
+    crlf_files: Arc<Mutex<HashSet<PathBuf>>>,
+    /// While previewing a command, the names of files that would have been written.  Nothing is
+    /// sent to the client while this is `Some`.
+    preview_files: Arc<Mutex<Option<Vec<String>>>>,
+    /// The client understands `NewBufferDiff`, as requested with the `diffs=on` interact option.
+    /// Otherwise every rewrite is sent as `NewBufferText`.
+    buffer_diffs: bool,
+}
+
+impl InteractiveFileIO {
+    /// Send the client the update produced by `mk_msg` for `path`, or just record `path` if a
+    /// command is being previewed.
+    fn send_update<F>(&self, path: &Path, mk_msg: F)
+    where
+        F: FnOnce(String) -> ToClient,
+    {
+        let canon = canonicalize_lossy(path);
+        let file = canon.to_str().unwrap().to_owned();
+        if let Some(ref mut files) = *self.preview_files.lock().unwrap() {
+            files.push(file);
+            return;
+        }
+        if let Some(text) = self.texts_read.lock().unwrap().get(&canon) {
+            self.undo_texts
+                .lock()
+                .unwrap()
+                .entry(canon)
+                .or_insert_with(|| text.clone());
+        }
+        self.to_client.send(mk_msg(file)).unwrap();
+    }
+
+    /// Convert `s` back to CRLF line endings if `path` originally used them.
+    fn restore_line_endings(&self, path: &Path, s: &str) -> String {
+        let crlf = {
+            self.crlf_files
+                .lock()
+                .unwrap()
+                .contains(&canonicalize_lossy(path))
+        };
+        if crlf {
+            s.replace("\r\n", "\n").replace('\n', "\r\n")
+        } else {
+            s.to_owned()
+        }
+    }
 }
 
 impl FileIO for InteractiveFileIO {
+    fn abs_path(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path).or_else(|e| self.file_loader.abs_path(path).ok_or(e))
+    }
+
+    fn file_exists(&self, path: &Path) -> bool {
+        let available = {
+            self.buffers_available
+                .lock()
+                .unwrap()
+                .contains(&canonicalize_lossy(path))
+        };
+        available || self.file_loader.file_exists(path)
+    }
+
     fn read_file(&self, path: &Path) -> io::Result<String> {
-        let canon = fs::canonicalize(path)?;
+        let canon = canonicalize_lossy(path);
 
         let available = { self.buffers_available.lock().unwrap().contains(&canon) };
 
-        if available {
+        let text = if available {
             let (send, recv) = mpsc::sync_channel(1);
             self.to_worker
-                .send(ToWorker::NeedFile(canon, send))
-                .unwrap();
-            Ok(recv.recv().unwrap())
+                .send(ToWorker::NeedFile(canon.clone(), send))
+                .map_err(|_| worker_died())?;
+            recv.recv().map_err(|_| worker_died())?
         } else {
-            RealFileLoader.read_file(&canon)
+            self.file_loader.read_file(&canon)?
+        };
+        {
+            let mut crlf_files = self.crlf_files.lock().unwrap();
+            if text.contains("\r\n") {
+                crlf_files.insert(canon.clone());
+            } else {
+                crlf_files.remove(&canon);
+            }
         }
+        self.files_read
+            .lock()
+            .unwrap()
+            .insert(canon.clone(), hash_text(&text));
+        self.texts_read.lock().unwrap().insert(canon, text.clone());
+        Ok(text)
     }
 
     fn write_file(&self, path: &Path, s: &str) -> io::Result<()> {
-        let path = fs::canonicalize(path)?;
-        self.to_client
-            .send(ToClient::NewBufferText {
-                file: path.to_str().unwrap().to_owned(),
-                content: s.to_owned(),
+        let content = self.restore_line_endings(path, s);
+        self.send_update(path, |file| ToClient::NewBufferText { file, content });
+        Ok(())
+    }
+
+    fn write_file_edits(&self, path: &Path, s: &str, edits: &[FileEdit]) -> io::Result<()> {
+        if !self.buffer_diffs {
+            return self.write_file(path, s);
+        }
+        // Edit coordinates are line/column based, and rustc's columns already exclude the `\r`,
+        // so only the text needs converting.
+        let s = self.restore_line_endings(path, s);
+        let edits = edits
+            .iter()
+            .map(|e| FileEdit {
+                text: self.restore_line_endings(path, &e.text),
+                ..e.clone()
             })
-            .unwrap();
+            .collect::<Vec<_>>();
+        self.send_update(path, |file| buffer_update(file, &s, &edits));
         Ok(())
     }
 }
 
+/// The error reported when the worker thread has exited, so buffer contents can't be requested
+/// from the client.
+fn worker_died() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "interactive worker thread exited unexpectedly",
+    )
+}
+
+/// Choose how to send the client the new contents `s` of `file`: as `edits` if they are
+/// substantially smaller than the full text, and as the full text otherwise.
+fn buffer_update(file: String, s: &str, edits: &[FileEdit]) -> ToClient {
+    let edit_len: usize = edits.iter().map(|e| e.text.len()).sum();
+    if edits.is_empty() || edit_len * 2 > s.len() {
+        ToClient::NewBufferText {
+            file,
+            content: s.to_owned(),
+        }
+    } else {
+        ToClient::NewBufferDiff {
+            file,
+            edits: edits.to_owned(),
+        }
+    }
+}
+
 struct CollectSpanVisitor {
     ids: HashSet<NodeId>,
     spans: HashMap<NodeId, Span>,
@@ -389,3 +1438,1064 @@ fn collect_spans<T: Visit>(target: &T, ids: HashSet<NodeId>) -> HashMap<NodeId,
     target.visit(&mut v);
     v.spans
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_io::VirtualFileLoader;
+    use std::process::Command;
+
+    #[test]
+    fn span_cache_detects_changed_sources() {
+        let path = PathBuf::from("/src/lib.rs");
+        let mut sources = HashMap::new();
+        sources.insert(path.clone(), hash_text("fn f() {}"));
+        let cache = SpanCache {
+            sources,
+            infos: HashMap::new(),
+        };
+
+        assert!(cache.is_current(|_| Ok("fn f() {}".to_owned())));
+        assert!(!cache.is_current(|_| Ok("fn g() {}".to_owned())));
+        assert!(!cache.is_current(|_| Err(io::Error::new(io::ErrorKind::NotFound, "gone"))));
+    }
+
+    fn edit(lo: (u32, u32), hi: (u32, u32), text: &str) -> FileEdit {
+        FileEdit {
+            lo,
+            hi,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn localized_change_sent_as_diff() {
+        let new_text = "fn f() {\n    g(3)\n}\n";
+        let edits = [edit((2, 4), (2, 9), "g(3)")];
+        match buffer_update("a.rs".to_owned(), new_text, &edits) {
+            ToClient::NewBufferDiff { file, edits: sent } => {
+                assert_eq!(file, "a.rs");
+                assert_eq!(sent, edits);
+            }
+            other => panic!("expected a diff, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn large_change_sent_as_full_text() {
+        let new_text = "fn g() {}\n";
+        let edits = [edit((1, 0), (3, 1), "fn g() {}")];
+        match buffer_update("a.rs".to_owned(), new_text, &edits) {
+            ToClient::NewBufferText { file, content } => {
+                assert_eq!(file, "a.rs");
+                assert_eq!(content, new_text);
+            }
+            other => panic!("expected full text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cfg_args() {
+        let args = vec![
+            "--cfg".to_owned(),
+            "feature=\"x\"".to_owned(),
+            "--cfg=debug_mode".to_owned(),
+        ];
+        assert_eq!(
+            parse_cfg_args(&args),
+            Ok(vec!["feature=\"x\"".to_owned(), "debug_mode".to_owned()])
+        );
+
+        assert!(parse_cfg_args(&["-O".to_owned()]).is_err());
+        assert!(parse_cfg_args(&["--cfg".to_owned()]).is_err());
+        assert!(parse_cfg_args(&["--cfg=feature=x".to_owned()]).is_err());
+        assert!(parse_cfg_args(&["--cfg=1x".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn remove_marks_by_label() {
+        syntax::with_default_globals(|| {
+            let target = Symbol::intern("target");
+            let other = Symbol::intern("other");
+            let mut marks = HashSet::new();
+            marks.insert((NodeId::from_usize(1), target));
+            marks.insert((NodeId::from_usize(2), target));
+            marks.insert((NodeId::from_usize(2), other));
+            marks.insert((NodeId::from_usize(3), other));
+
+            remove_label(&mut marks, target);
+
+            let mut expected = HashSet::new();
+            expected.insert((NodeId::from_usize(2), other));
+            expected.insert((NodeId::from_usize(3), other));
+            assert_eq!(marks, expected);
+        })
+    }
+
+    #[test]
+    fn marks_in_virtual_files_filtered() {
+        syntax::with_default_globals(|| {
+            let expansion = FileName::Macros("format_args".to_owned());
+            let real = FileName::Real(PathBuf::from("/src/lib.rs"));
+            assert!(!is_real_file(&expansion));
+            assert!(is_real_file(&real));
+
+            let info = |id, name: &FileName| MarkInfo {
+                id,
+                stable_id: format!("/{}", id),
+                file: filename_to_str(name),
+                start_line: 1,
+                start_col: 0,
+                end_line: 1,
+                end_col: 4,
+                labels: vec![],
+                virtual_file: !is_real_file(name),
+                kind: None,
+                excerpt: None,
+                context: None,
+            };
+            let mut spans = HashMap::new();
+            spans.insert(NodeId::from_usize(1), info(1, &real));
+            spans.insert(NodeId::from_usize(2), info(2, &expansion));
+
+            let target = Symbol::intern("target");
+            let mut marks = HashSet::new();
+            marks.insert((NodeId::from_usize(1), target));
+            marks.insert((NodeId::from_usize(2), target));
+
+            let infos = label_mark_infos(&marks, &spans);
+            assert_eq!(infos.iter().map(|i| i.id).collect::<Vec<_>>(), vec![1]);
+            assert!(check_real_file(&infos[0]).is_ok());
+            assert!(check_real_file(&spans[&NodeId::from_usize(2)]).is_err());
+        })
+    }
+
+    #[test]
+    fn saved_marks_round_trip() {
+        let infos = vec![
+            MarkInfo {
+                id: 12,
+                stable_id: "/0".to_owned(),
+                file: "/src/lib.rs".to_owned(),
+                start_line: 1,
+                start_col: 0,
+                end_line: 3,
+                end_col: 1,
+                labels: vec!["a".to_owned(), "b".to_owned()],
+                virtual_file: false,
+                kind: None,
+                excerpt: None,
+                context: None,
+            },
+            MarkInfo {
+                id: 40,
+                stable_id: "/1/0/2".to_owned(),
+                file: "/src/main.rs".to_owned(),
+                start_line: 5,
+                start_col: 4,
+                end_line: 5,
+                end_col: 9,
+                labels: vec!["target".to_owned()],
+                virtual_file: false,
+                kind: None,
+                excerpt: None,
+                context: None,
+            },
+        ];
+
+        let decoded = decode_saved_marks(&encode_saved_marks(infos.clone())).unwrap();
+        assert_eq!(decoded.len(), infos.len());
+        for (d, i) in decoded.iter().zip(&infos) {
+            assert_eq!(
+                (
+                    &d.file,
+                    d.start_line,
+                    d.start_col,
+                    d.end_line,
+                    d.end_col,
+                    &d.labels
+                ),
+                (
+                    &i.file,
+                    i.start_line,
+                    i.start_col,
+                    i.end_line,
+                    i.end_col,
+                    &i.labels
+                )
+            );
+        }
+
+        assert!(decode_saved_marks("{\"marks\": [{\"file\": 1}]}").is_err());
+    }
+
+    /// Build an `InteractiveFileIO` whose only available buffer is `path`, answering requests for
+    /// it with `content`.
+    fn buffer_file_io(path: &Path, content: &'static str) -> InteractiveFileIO {
+        let (to_client, _client_recv) = mpsc::sync_channel(1);
+        file_io_with_client(path, content, to_client)
+    }
+
+    /// Like `buffer_file_io`, but sending client messages to `to_client`.
+    fn file_io_with_client(
+        path: &Path,
+        content: &'static str,
+        to_client: SyncSender<ToClient>,
+    ) -> InteractiveFileIO {
+        let (to_worker, worker_recv) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            for msg in worker_recv.iter() {
+                if let ToWorker::NeedFile(_, send) = msg {
+                    send.send(content.to_owned()).unwrap();
+                }
+            }
+        });
+        file_io_with_worker(path, to_worker, to_client)
+    }
+
+    /// Build an `InteractiveFileIO` whose only available buffer is `path`, requesting its
+    /// contents through `to_worker`.
+    fn file_io_with_worker(
+        path: &Path,
+        to_worker: SyncSender<ToWorker>,
+        to_client: SyncSender<ToClient>,
+    ) -> InteractiveFileIO {
+        let mut buffers = HashSet::new();
+        buffers.insert(canonicalize_lossy(path));
+        InteractiveFileIO {
+            buffers_available: Arc::new(Mutex::new(buffers)),
+            file_loader: Arc::new(RealFileLoader),
+            to_worker,
+            to_client,
+            files_read: Arc::new(Mutex::new(HashMap::new())),
+            texts_read: Arc::new(Mutex::new(HashMap::new())),
+            undo_texts: Arc::new(Mutex::new(HashMap::new())),
+            crlf_files: Arc::new(Mutex::new(HashSet::new())),
+            preview_files: Arc::new(Mutex::new(None)),
+            buffer_diffs: false,
+        }
+    }
+
+    /// Load `src` from `path` as a library crate and run `f` on an `InteractState` for it, with
+    /// `registry` providing the commands.
+    /// Messages for the client arrive on the receiver passed to `f`.
+    fn with_interact_state<F, R>(
+        path: &Path,
+        src: &'static str,
+        registry: command::Registry,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(&mut InteractState, &Receiver<ToClient>) -> R,
+        R: Send,
+    {
+        fs::write(path, src).unwrap();
+        let (to_client, client_recv) = mpsc::sync_channel(16);
+        let file_io = file_io_with_client(path, src, to_client.clone());
+        run_interact_state(path, registry, file_io, to_client, client_recv, f)
+    }
+
+    /// Run `f` on an `InteractState` for the library crate rooted at `path`, reading its source
+    /// through `file_io`, which sends client messages to `to_client`.
+    fn run_interact_state<F, R>(
+        path: &Path,
+        registry: command::Registry,
+        file_io: InteractiveFileIO,
+        to_client: SyncSender<ToClient>,
+        client_recv: Receiver<ToClient>,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(&mut InteractState, &Receiver<ToClient>) -> R,
+        R: Send,
+    {
+        let sysroot = Command::new("rustc")
+            .args(&["--print", "sysroot"])
+            .output()
+            .unwrap();
+        let args = vec![
+            "rustc".to_owned(),
+            "--crate-type=lib".to_owned(),
+            "--sysroot".to_owned(),
+            String::from_utf8(sysroot.stdout).unwrap().trim().to_owned(),
+            path.to_str().unwrap().to_owned(),
+        ];
+        let config = driver::create_config(&args);
+
+        let (to_worker, _worker_recv) = mpsc::sync_channel(1);
+        let file_io = Arc::new(file_io);
+        let buffers_available = file_io.buffers_available.clone();
+        driver::run_refactoring(config, registry, file_io.clone(), HashSet::new(), |state| {
+            let mut st =
+                InteractState::new(state, buffers_available, file_io, to_worker, to_client);
+            f(&mut st, &client_recv)
+        })
+    }
+
+    /// Receive the next message for the client that isn't a progress report.
+    fn reply(client_recv: &Receiver<ToClient>) -> ToClient {
+        client_recv
+            .iter()
+            .find(|msg| match msg {
+                ToClient::Progress { .. } => false,
+                _ => true,
+            })
+            .unwrap()
+    }
+
+    /// Receive the reply to a mark request and return the ID of the marked node.
+    fn marked_id(client_recv: &Receiver<ToClient>) -> usize {
+        match reply(client_recv) {
+            ToClient::Mark { info } => info.id,
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mark_type_of_integer_expr() {
+        let path = std::env::temp_dir().join("c2rust-interact-mark-type.rs");
+        let file = path.to_str().unwrap().to_owned();
+        let src = "pub fn f() -> u8 {\n    1\n}\n";
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            st.handle_one(ToServer::AddMark {
+                file: file.clone(),
+                line: 2,
+                col: 4,
+                kind: "expr".to_owned(),
+                label: "target".to_owned(),
+                end_line: None,
+                end_col: None,
+            })
+            .unwrap();
+            let id = marked_id(client_recv);
+
+            st.handle_one(ToServer::GetMarkType { id }).unwrap();
+            match reply(client_recv) {
+                ToClient::MarkType { id: ty_id, ty } => {
+                    assert_eq!(ty_id, id);
+                    assert_eq!(ty, "u8");
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+
+            // Items have types too, but aren't expressions.
+            st.handle_one(ToServer::AddMarkByName {
+                name: "f".to_owned(),
+                kind: "item".to_owned(),
+                label: "target".to_owned(),
+            })
+            .unwrap();
+            let id = marked_id(client_recv);
+            let err = st.handle_one(ToServer::GetMarkType { id }).unwrap_err();
+            assert_eq!(err, format!("node {} is not an expression", id));
+        });
+    }
+
+    #[test]
+    fn column_encodings() {
+        let line = "a\u{1f600}b";
+        let cols = |byte| {
+            (
+                ColumnEncoding::Utf8Bytes.column(line, byte),
+                ColumnEncoding::Utf16.column(line, byte),
+                ColumnEncoding::Chars.column(line, byte),
+            )
+        };
+        assert_eq!(cols(1), (1, 1, 1));
+        assert_eq!(cols(5), (5, 3, 2));
+        assert_eq!(ColumnEncoding::Utf16.byte_offset(line, 3), 5);
+        // Halfway through the surrogate pair rounds up to the next character.
+        assert_eq!(ColumnEncoding::Utf16.byte_offset(line, 2), 5);
+        assert_eq!(ColumnEncoding::Chars.byte_offset(line, 2), 5);
+        assert_eq!(ColumnEncoding::Utf16.byte_offset(line, 5), 7);
+    }
+
+    #[test]
+    fn utf16_mark_columns() {
+        let path = std::env::temp_dir().join("c2rust-interact-utf16-columns.rs");
+        let file = path.to_str().unwrap().to_owned();
+        let src = "pub const S: &str = \"\u{1f600}\"; pub fn g() {}\n";
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            st.column_encoding = ColumnEncoding::Utf16;
+            st.handle_one(ToServer::AddMarkByName {
+                name: "g".to_owned(),
+                kind: "item".to_owned(),
+                label: "target".to_owned(),
+            })
+            .unwrap();
+            let (id, cols) = match reply(client_recv) {
+                ToClient::Mark { info } => (info.id, (info.start_col, info.end_col)),
+                other => panic!("unexpected message {:?}", other),
+            };
+            // As in LSP, the emoji before `g` counts as two UTF-16 code units.
+            assert_eq!(cols, (26, 39));
+
+            // Requests count columns the same way: `g` itself is at UTF-16 column 33.
+            st.handle_one(ToServer::AddMark {
+                file: file.clone(),
+                line: 1,
+                col: 33,
+                kind: "item".to_owned(),
+                label: "other".to_owned(),
+                end_line: None,
+                end_col: None,
+            })
+            .unwrap();
+            assert_eq!(marked_id(client_recv), id);
+        });
+    }
+
+    #[test]
+    fn truncated_excerpts() {
+        assert_eq!(truncate_excerpt("1 + 2", 5), "1 + 2");
+        assert_eq!(truncate_excerpt("1 + 2", 4), "1 +\u{2026}");
+        assert_eq!(truncate_excerpt("\u{e9}\u{e9}\u{e9}", 2), "\u{e9}\u{2026}");
+        assert_eq!(truncate_excerpt("abc", 0), "\u{2026}");
+    }
+
+    #[test]
+    fn mark_list_with_text() {
+        let path = std::env::temp_dir().join("c2rust-interact-mark-text.rs");
+        let file = path.to_str().unwrap().to_owned();
+        let src = "pub fn f() -> u8 {\n    1 + 2\n}\n";
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            st.handle_one(ToServer::AddMark {
+                file: file.clone(),
+                line: 2,
+                col: 4,
+                kind: "expr".to_owned(),
+                label: "target".to_owned(),
+                end_line: Some(2),
+                end_col: Some(9),
+            })
+            .unwrap();
+            marked_id(client_recv);
+
+            for &(max_len, excerpt) in &[(40, "1 + 2"), (4, "1 +\u{2026}")] {
+                st.handle_one(ToServer::GetMarkListWithText { max_len })
+                    .unwrap();
+                match reply(client_recv) {
+                    ToClient::MarkList { infos } => {
+                        assert_eq!(infos.len(), 1);
+                        assert_eq!(infos[0].excerpt.as_ref().map(|s| s as &str), Some(excerpt));
+                    }
+                    other => panic!("unexpected message {:?}", other),
+                }
+            }
+
+            // Plain mark lists still leave the text out.
+            st.handle_one(ToServer::GetMarkList { context_lines: 0 })
+                .unwrap();
+            match reply(client_recv) {
+                ToClient::MarkList { infos } => assert_eq!(infos[0].excerpt, None),
+                other => panic!("unexpected message {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn mark_calls_of_function() {
+        let path = std::env::temp_dir().join("c2rust-interact-mark-calls.rs");
+        let src = concat!(
+            "pub fn f() {}\n",
+            "\n",
+            "pub fn g() {\n",
+            "    f();\n",
+            "    let p: fn() = f;\n",
+            "    p();\n",
+            "    f();\n",
+            "}\n",
+        );
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            st.handle_one(ToServer::MarkCallsOf {
+                name: "f".to_owned(),
+                label: "calls".to_owned(),
+            })
+            .unwrap();
+            match reply(client_recv) {
+                ToClient::CallsMarked { name, count } => {
+                    assert_eq!((&name as &str, count), ("f", 2))
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+
+            // The call through `p` is skipped.
+            st.handle_one(ToServer::GetMarkListWithText { max_len: 10 }).unwrap();
+            match reply(client_recv) {
+                ToClient::MarkList { infos } => {
+                    let mut marked = infos
+                        .iter()
+                        .map(|i| (i.start_line, i.excerpt.clone().unwrap(), i.labels.clone()))
+                        .collect::<Vec<_>>();
+                    marked.sort();
+                    let calls = vec!["calls".to_owned()];
+                    assert_eq!(
+                        marked,
+                        vec![
+                            (4, "f()".to_owned(), calls.clone()),
+                            (7, "f()".to_owned(), calls),
+                        ]
+                    );
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn marks_in_line_range() {
+        let path = std::env::temp_dir().join("c2rust-interact-mark-range.rs");
+        let file = path.to_str().unwrap().to_owned();
+        let src = "pub fn f() {}\n\npub fn g() {\n}\n\npub fn h() {}\n";
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            let mut ids = HashMap::new();
+            for name in &["f", "g", "h"] {
+                st.handle_one(ToServer::AddMarkByName {
+                    name: name.to_string(),
+                    kind: "item".to_owned(),
+                    label: "target".to_owned(),
+                })
+                .unwrap();
+                ids.insert(*name, marked_id(client_recv));
+            }
+
+            let mut marked_names = |start_line, end_line| {
+                st.handle_one(ToServer::GetMarksInRange {
+                    file: file.clone(),
+                    start_line,
+                    end_line,
+                })
+                .unwrap();
+                match reply(client_recv) {
+                    ToClient::MarkList { infos } => {
+                        let mut names = ids
+                            .iter()
+                            .filter(|&(_, id)| infos.iter().any(|i| i.id == *id))
+                            .map(|(&name, _)| name)
+                            .collect::<Vec<_>>();
+                        names.sort();
+                        names
+                    }
+                    other => panic!("unexpected message {:?}", other),
+                }
+            };
+
+            assert_eq!(marked_names(2, 3), vec!["g"]);
+            // A node counts if any of its lines are in range.
+            assert_eq!(marked_names(4, 6), vec!["g", "h"]);
+            assert_eq!(marked_names(1, 6), vec!["f", "g", "h"]);
+            assert!(marked_names(7, 9).is_empty());
+        });
+    }
+
+    #[test]
+    fn mark_context_lines() {
+        let path = std::env::temp_dir().join("c2rust-interact-mark-context.rs");
+        let file = path.to_str().unwrap().to_owned();
+        let src = "pub fn f() -> u8 {\n    let a = 1;\n    let b = 2;\n    let c = a + b;\n    \
+                   let d = c;\n    d * 2\n}\n";
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            st.handle_one(ToServer::AddMark {
+                file: file.clone(),
+                line: 4,
+                col: 12,
+                kind: "expr".to_owned(),
+                label: "target".to_owned(),
+                end_line: Some(4),
+                end_col: Some(17),
+            })
+            .unwrap();
+            let id = marked_id(client_recv);
+
+            st.handle_one(ToServer::GetMarkInfo {
+                id,
+                context_lines: 2,
+            })
+            .unwrap();
+            match reply(client_recv) {
+                ToClient::Mark { info } => {
+                    let context = info.context.unwrap();
+                    assert_eq!(context.before, vec!["    let a = 1;", "    let b = 2;"]);
+                    assert_eq!(context.after, vec!["    let d = c;", "    d * 2"]);
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+
+            // Asking for more lines than the file has stops at its first and last lines.
+            st.handle_one(ToServer::GetMarkList { context_lines: 5 })
+                .unwrap();
+            match reply(client_recv) {
+                ToClient::MarkList { infos } => {
+                    let context = infos[0].context.as_ref().unwrap();
+                    assert_eq!(context.before.len(), 3);
+                    assert_eq!(context.before[0], "pub fn f() -> u8 {");
+                    assert_eq!(context.after, vec!["    let d = c;", "    d * 2", "}"]);
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn inspect_node_leaves_marks_alone() {
+        let path = std::env::temp_dir().join("c2rust-interact-inspect.rs");
+        let file = path.to_str().unwrap().to_owned();
+        let src = "pub fn f() -> u8 {\n    1 + 2\n}\n";
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            st.handle_one(ToServer::InspectNode {
+                file: file.clone(),
+                line: 2,
+                col: 4,
+                kind: "stmt,expr".to_owned(),
+            })
+            .unwrap();
+            match reply(client_recv) {
+                ToClient::NodeInfo { info } => {
+                    assert_eq!(info.kind.as_ref().map(|s| s as &str), Some("stmt"));
+                    assert_eq!(info.excerpt.as_ref().map(|s| s as &str), Some("1 + 2"));
+                    assert_eq!((info.start_line, info.start_col), (2, 4));
+                    assert!(info.labels.is_empty());
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+            assert!(st.state.marks().is_empty());
+        });
+    }
+
+    #[test]
+    fn command_on_in_memory_crate() {
+        // Neither the crate root nor its directory exists, so the source can only come from the
+        // loader.
+        let path = std::env::temp_dir()
+            .join("c2rust-interact-in-memory")
+            .join("lib.rs");
+        let src = "pub fn f() {}\n";
+        let mut files = HashMap::new();
+        files.insert(path.clone(), src.to_owned());
+
+        let (to_client, client_recv) = mpsc::sync_channel(16);
+        let (to_worker, _worker_recv) = mpsc::sync_channel(1);
+        let file_io = InteractiveFileIO {
+            file_loader: Arc::new(VirtualFileLoader::new(files)),
+            buffers_available: Arc::new(Mutex::new(HashSet::new())),
+            ..file_io_with_worker(&path, to_worker, to_client.clone())
+        };
+        let mut registry = command::Registry::new();
+        crate::transform::register_commands(&mut registry);
+        run_interact_state(&path, registry, file_io, to_client, client_recv, |st, client_recv| {
+            st.handle_one(ToServer::RunCommand {
+                name: "rename_items_regex".to_owned(),
+                args: vec!["f".to_owned(), "g".to_owned()],
+            })
+            .unwrap();
+            let file = path.to_str().unwrap();
+            let rewritten = client_recv.try_iter().any(|msg| match msg {
+                ToClient::NewBufferText { file: f, content } => {
+                    f == file && content == "pub fn g() {}\n"
+                }
+                ToClient::NewBufferDiff { file: f, edits } => {
+                    f == file && edits.iter().any(|e| e.text == "g")
+                }
+                _ => false,
+            });
+            assert!(rewritten);
+        });
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn repeated_pure_command_is_cached() {
+        let path = std::env::temp_dir().join("c2rust-interact-pure-cache.rs");
+        let src = "pub fn f() {}\n\npub fn g() {\n    f();\n    f();\n}\n";
+        let mut registry = command::Registry::new();
+        crate::mark_adjust::register_commands(&mut registry);
+        with_interact_state(&path, src, registry, |st, client_recv| {
+            st.handle_one(ToServer::AddMarkByName {
+                name: "f".to_owned(),
+                kind: "item".to_owned(),
+                label: "target".to_owned(),
+            })
+            .unwrap();
+            marked_id(client_recv);
+            let marks = st.state.marks().clone();
+
+            // Run `mark_uses target`, reporting whether it compiled the crate.
+            let run = |st: &mut InteractState| {
+                st.handle_one(ToServer::RunCommand {
+                    name: "mark_uses".to_owned(),
+                    args: vec!["target".to_owned()],
+                })
+                .unwrap();
+                client_recv.try_iter().any(|msg| match msg {
+                    ToClient::Progress { ref phase, .. } => phase == "starting driver",
+                    _ => false,
+                })
+            };
+            assert!(run(st));
+            let uses = st.state.marks().clone();
+            assert_eq!(uses.len(), 2);
+
+            *st.state.marks_mut() = marks;
+            assert!(!run(st));
+            assert_eq!(*st.state.marks(), uses);
+        });
+    }
+
+    #[test]
+    fn command_progress_in_order() {
+        let path = std::env::temp_dir().join("c2rust-interact-progress.rs");
+        let src = "pub fn f() {}\n";
+        let mut registry = command::Registry::new();
+        crate::transform::register_commands(&mut registry);
+        with_interact_state(&path, src, registry, |st, client_recv| {
+            st.handle_one(ToServer::RunCommand {
+                name: "rename_items_regex".to_owned(),
+                args: vec!["f".to_owned(), "g".to_owned()],
+            })
+            .unwrap();
+            let phases = client_recv
+                .try_iter()
+                .filter_map(|msg| match msg {
+                    ToClient::Progress { phase, .. } => Some(phase),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(phases, vec!["starting driver", "running command", "rewriting files"]);
+        });
+    }
+
+    #[test]
+    fn command_with_cfg_args() {
+        let path = std::env::temp_dir().join("c2rust-interact-cfg-args.rs");
+        let src = "#[cfg(foo)]\npub fn f() {}\n";
+        let mut registry = command::Registry::new();
+        crate::transform::register_commands(&mut registry);
+        with_interact_state(&path, src, registry, |st, client_recv| {
+            let file = canonicalize_lossy(&path).to_str().unwrap().to_owned();
+            let rename = vec!["f".to_owned(), "g".to_owned()];
+            let rewrites = |client_recv: &Receiver<ToClient>| {
+                client_recv
+                    .try_iter()
+                    .filter_map(|msg| match msg {
+                        ToClient::NewBufferText { file: f, content } if f == file => Some(content),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            // Without `--cfg foo`, `f` isn't compiled, so there is nothing to rename
+            st.handle_one(ToServer::RunCommand {
+                name: "rename_items_regex".to_owned(),
+                args: rename.clone(),
+            })
+            .unwrap();
+            assert!(rewrites(client_recv).iter().all(|text| !text.contains("fn g")));
+
+            st.handle_one(ToServer::RunCommandWithArgs {
+                name: "rename_items_regex".to_owned(),
+                args: rename,
+                rustc_args: vec!["--cfg".to_owned(), "foo".to_owned()],
+            })
+            .unwrap();
+            assert_eq!(rewrites(client_recv), vec!["#[cfg(foo)]\npub fn g() {}\n"]);
+        });
+    }
+
+    #[test]
+    fn command_warnings_sent_as_diagnostics() {
+        let path = std::env::temp_dir().join("c2rust-interact-diagnostics.rs");
+        let src = "pub fn f() {}\n\npub fn g() {}\n";
+        let mut registry = command::Registry::new();
+        registry.register("warn_on_g", |_args| {
+            Box::new(command::DriverCommand::new(
+                driver::Phase::Phase2,
+                |st, _cx| {
+                    for item in &st.krate().module.items {
+                        if item.ident.name == Symbol::intern("g") {
+                            st.warn(item.span, "found g");
+                        }
+                    }
+                },
+            ))
+        });
+        with_interact_state(&path, src, registry, |st, client_recv| {
+            st.handle_one(ToServer::RunCommand {
+                name: "warn_on_g".to_owned(),
+                args: vec![],
+            })
+            .unwrap();
+            let diagnostics = client_recv
+                .try_iter()
+                .filter_map(|msg| match msg {
+                    ToClient::Diagnostics { items } => Some(items),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                diagnostics,
+                vec![vec![command::Diagnostic {
+                    severity: command::Severity::Warning,
+                    file: path.to_str().unwrap().to_owned(),
+                    line: 3,
+                    col: 0,
+                    message: "found g".to_owned(),
+                }]],
+            );
+        });
+    }
+
+    #[test]
+    fn run_commands_loads_and_saves_once() {
+        let path = std::env::temp_dir().join("c2rust-interact-run-commands.rs");
+        let src = "pub fn f() {}\n";
+        let mut registry = command::Registry::new();
+        crate::transform::register_commands(&mut registry);
+        with_interact_state(&path, src, registry, |st, client_recv| {
+            let file = canonicalize_lossy(&path).to_str().unwrap().to_owned();
+            let rename = |from: &str, to: &str| {
+                let args = vec![from.to_owned(), to.to_owned()];
+                ("rename_items_regex".to_owned(), args)
+            };
+            st.handle_one(ToServer::RunCommands {
+                commands: vec![rename("f", "g"), rename("g", "h")],
+            })
+            .unwrap();
+
+            let msgs = client_recv.try_iter().collect::<Vec<_>>();
+            let driver_starts = msgs
+                .iter()
+                .filter(|msg| match msg {
+                    ToClient::Progress { phase, .. } => phase == "starting driver",
+                    _ => false,
+                })
+                .count();
+            assert_eq!(driver_starts, 1);
+
+            // The second command sees the first one's rename, and the file is written once
+            let rewrites = msgs
+                .iter()
+                .filter_map(|msg| match msg {
+                    ToClient::NewBufferText { file: f, content } if *f == file => Some(content),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(rewrites, vec!["pub fn h() {}\n"]);
+        });
+    }
+
+    #[test]
+    fn repeated_mark_list_is_cached() {
+        let path = std::env::temp_dir().join("c2rust-interact-mark-list-cache.rs");
+        let src = "pub fn f() {}\n\npub fn g() {}\n";
+        with_interact_state(&path, src, command::Registry::new(), |st, client_recv| {
+            for name in &["f", "g"] {
+                st.handle_one(ToServer::AddMarkByName {
+                    name: name.to_string(),
+                    kind: "item".to_owned(),
+                    label: "target".to_owned(),
+                })
+                .unwrap();
+                marked_id(client_recv);
+            }
+            st.span_cache = None;
+
+            // Get the mark list, returning how many times that compiled the crate.
+            let run = |st: &mut InteractState| {
+                st.handle_one(ToServer::GetMarkList { context_lines: 0 })
+                    .unwrap();
+                let msgs = client_recv.try_iter().collect::<Vec<_>>();
+                match msgs.last() {
+                    Some(ToClient::MarkList { infos }) => assert_eq!(infos.len(), 2),
+                    other => panic!("unexpected message {:?}", other),
+                }
+                msgs.iter()
+                    .filter(|msg| match msg {
+                        ToClient::Progress { ref phase, .. } => phase == "starting driver",
+                        _ => false,
+                    })
+                    .count()
+            };
+            assert_eq!(run(st), 1);
+            assert_eq!(run(st), 0);
+        });
+    }
+
+    #[test]
+    fn undo_restores_buffers_and_marks() {
+        let path = std::env::temp_dir().join("c2rust-interact-undo.rs");
+        let src = "pub fn f() {}\n";
+        let mut registry = command::Registry::new();
+        crate::transform::register_commands(&mut registry);
+        with_interact_state(&path, src, registry, |st, client_recv| {
+            let file = canonicalize_lossy(&path).to_str().unwrap().to_owned();
+            st.handle_one(ToServer::AddMarkByName {
+                name: "f".to_owned(),
+                kind: "item".to_owned(),
+                label: "target".to_owned(),
+            })
+            .unwrap();
+            marked_id(client_recv);
+            let marks = st.state.marks().clone();
+
+            st.handle_one(ToServer::RunCommand {
+                name: "rename_items_regex".to_owned(),
+                args: vec!["f".to_owned(), "g".to_owned()],
+            })
+            .unwrap();
+            let rewritten = client_recv.try_iter().any(|msg| match msg {
+                ToClient::NewBufferText { file: ref f, .. }
+                | ToClient::NewBufferDiff { file: ref f, .. } => *f == file,
+                _ => false,
+            });
+            assert!(rewritten);
+
+            st.handle_one(ToServer::Undo).unwrap();
+            let msgs = client_recv.try_iter().collect::<Vec<_>>();
+            match msgs.first() {
+                Some(ToClient::NewBufferText { file: f, content }) => {
+                    assert_eq!(*f, file);
+                    assert_eq!(content, src);
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+            match msgs.last() {
+                Some(ToClient::MarkList { infos }) => assert_eq!(infos.len(), 1),
+                other => panic!("unexpected message {:?}", other),
+            }
+            assert_eq!(*st.state.marks(), marks);
+
+            let err = st.handle_one(ToServer::Undo).unwrap_err();
+            assert_eq!(err, "nothing to undo");
+        });
+    }
+
+    #[test]
+    fn preview_records_files_without_sending_text() {
+        let path = std::env::temp_dir().join("c2rust-interact-preview.rs");
+        let (to_client, client_recv) = mpsc::sync_channel(4);
+        let io = file_io_with_client(&path, "", to_client);
+
+        // A real run sends the new text
+        io.write_file(&path, "fn f() {}").unwrap();
+        let sent = match client_recv.try_recv() {
+            Ok(ToClient::NewBufferText { file, content }) => {
+                assert_eq!(content, "fn f() {}");
+                file
+            }
+            other => panic!("expected buffer text, got {:?}", other),
+        };
+
+        // A preview of the same change only records the file name
+        *io.preview_files.lock().unwrap() = Some(Vec::new());
+        io.write_file(&path, "fn f() {}").unwrap();
+        assert!(client_recv.try_recv().is_err());
+        assert_eq!(io.preview_files.lock().unwrap().take(), Some(vec![sent]));
+    }
+
+    #[test]
+    fn crlf_buffer_written_back_with_crlf() {
+        let path = std::env::temp_dir().join("c2rust-interact-crlf.rs");
+        let (to_client, client_recv) = mpsc::sync_channel(4);
+        let io = InteractiveFileIO {
+            buffer_diffs: true,
+            ..file_io_with_client(&path, "fn f() {}\r\nfn g() {}\r\n", to_client)
+        };
+        io.read_file(&path).unwrap();
+
+        io.write_file(&path, "fn f() {}\nfn h() {}\n").unwrap();
+        match client_recv.try_recv() {
+            Ok(ToClient::NewBufferText { content, .. }) => {
+                assert_eq!(content, "fn f() {}\r\nfn h() {}\r\n");
+            }
+            other => panic!("expected buffer text, got {:?}", other),
+        }
+
+        let edits = [FileEdit {
+            lo: (2, 3),
+            hi: (2, 4),
+            text: "h() {}\nfn i".to_owned(),
+        }];
+        io.write_file_edits(&path, "fn f() {}\nfn h() {}\nfn i() {}\n", &edits)
+            .unwrap();
+        match client_recv.try_recv() {
+            Ok(ToClient::NewBufferDiff { edits, .. }) => {
+                assert_eq!(edits[0].text, "h() {}\r\nfn i");
+                assert_eq!((edits[0].lo, edits[0].hi), ((2, 3), (2, 4)));
+            }
+            other => panic!("expected buffer diff, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn edits_sent_as_full_text_without_diffs() {
+        let path = std::env::temp_dir().join("c2rust-interact-no-diffs.rs");
+        let (to_client, client_recv) = mpsc::sync_channel(4);
+        let io = file_io_with_client(&path, "fn f() {\n    g(1)\n}\n", to_client);
+        io.read_file(&path).unwrap();
+
+        // Small enough to go as a diff, but the client didn't ask for them
+        let new_text = "fn f() {\n    g(3)\n}\n";
+        let edits = [edit((2, 4), (2, 8), "g(3)")];
+        io.write_file_edits(&path, new_text, &edits).unwrap();
+        match client_recv.try_recv() {
+            Ok(ToClient::NewBufferText { content, .. }) => assert_eq!(content, new_text),
+            other => panic!("expected buffer text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_buffer_after_worker_exits() {
+        let path = std::env::temp_dir().join("c2rust-interact-dead-worker.rs");
+        let (to_client, _client_recv) = mpsc::sync_channel(1);
+
+        // The worker is gone before the request is sent
+        let (to_worker, worker_recv) = mpsc::sync_channel(1);
+        drop(worker_recv);
+        let io = file_io_with_worker(&path, to_worker, to_client.clone());
+        let err = io.read_file(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+
+        // The worker exits while the request is pending
+        let (to_worker, worker_recv) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            let _ = worker_recv.recv();
+        });
+        let io = file_io_with_worker(&path, to_worker, to_client);
+        let err = io.read_file(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn read_buffer_missing_on_disk() {
+        let path = std::env::temp_dir().join("c2rust-interact-no-such-file.rs");
+        let io = buffer_file_io(&path, "fn generated() {}");
+        assert_eq!(io.read_file(&path).unwrap(), "fn generated() {}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_buffer_through_broken_symlink() {
+        let dir = std::env::temp_dir().join(format!("c2rust-interact-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let link = dir.join("link.rs");
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(dir.join("missing.rs"), &link).unwrap();
+
+        let io = buffer_file_io(&link, "fn linked() {}");
+        let text = io.read_file(&link).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(text, "fn linked() {}");
+    }
+
+    #[test]
+    fn static_str_panic_message() {
+        let payload = panic::catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "static message");
+    }
+
+    #[test]
+    fn formatted_panic_message() {
+        let payload = panic::catch_unwind(|| panic!("formatted {}", 42)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "formatted 42");
+    }
+}
@@ -1,33 +1,99 @@
 //! Interactive mode, for running `c2rust-refactor` as a backend for editor plugins.
+use std::cmp;
+use std::fs;
 use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{SendError, SyncSender};
 
+use crate::command::{CommandInfo, Diagnostic};
+use crate::driver::Phase;
+use crate::rewrite::files::FileEdit;
+
+mod json_backend;
 mod main_thread;
 mod plain_backend;
 mod vim8_backend;
 mod worker;
 
-pub use self::main_thread::interact_command;
+pub use self::main_thread::{interact_command, interact_command_with_loader};
 
 #[derive(Clone, Debug)]
 pub enum ToServer {
     /// Add a mark with label `label` to a node of the indicated `kind` at `file`, `line`, `col`.
+    /// If `end_line` and `end_col` are given, the smallest such node covering the selection from
+    /// `line`, `col` up to `end_line`, `end_col` is marked instead.  `kind` may be a
+    /// comma-separated list, such as `stmt,expr`, in which case each kind is tried in order and
+    /// the first that finds a node is used.
     AddMark {
         file: String,
         line: u32,
         col: u32,
         kind: String,
         label: String,
+        end_line: Option<u32>,
+        end_col: Option<u32>,
     },
 
+    /// Describe the node of the given `kind` at a location, as `AddMark` would find it, without
+    /// marking it.  The reply is a `NodeInfo`.
+    InspectNode {
+        file: String,
+        line: u32,
+        col: u32,
+        kind: String,
+    },
+
+    /// Add a mark with label `label` to the node of the indicated `kind` whose name is `name`.
+    /// It is an error if no node or more than one node has that name.
+    AddMarkByName {
+        name: String,
+        kind: String,
+        label: String,
+    },
+
+    /// Add a mark with label `label` to every call expression whose callee is a path resolving
+    /// to the function `name`, given either as the function's own name or as its full path.
+    /// Calls through function pointers are skipped.  The reply is a `CallsMarked`.
+    MarkCallsOf { name: String, label: String },
+
     /// Remove all marks from node `id`.
     RemoveMark { id: usize },
 
-    /// Get details about the marks on node `id`.
-    GetMarkInfo { id: usize },
+    /// Remove label `label` from every node that has it, and reply with the new list of marks.
+    RemoveMarksByLabel { label: String },
+
+    /// Remove all marks, and reply with the (empty) list of marks.
+    ClearMarks,
+
+    /// Get details about the marks on node `id`.  If `context_lines` is nonzero, the reply
+    /// includes up to that many lines of source above and below the node.
+    GetMarkInfo { id: usize, context_lines: u32 },
+
+    /// Get the type of the expression marked as node `id`, which requires typechecking the crate.
+    GetMarkType { id: usize },
+
+    /// Get a list of all marks, with `context_lines` lines of source around each as for
+    /// `GetMarkInfo`.
+    GetMarkList { context_lines: u32 },
+
+    /// Get a list of all marks, like `GetMarkList`, including the source text of each marked node
+    /// cut down to at most `max_len` characters.
+    GetMarkListWithText { max_len: usize },
 
-    /// Get a list of all marks.
-    GetMarkList,
+    /// Get a list of the marks on nodes in `file` that overlap lines `start_line` through
+    /// `end_line`, inclusive, such as those visible in an editor window.
+    GetMarksInRange {
+        file: String,
+        start_line: u32,
+        end_line: u32,
+    },
+
+    /// Save all marks to `path`, along with the location of each marked node.
+    SaveMarks { path: String },
+
+    /// Load marks saved by `SaveMarks` from `path`, finding each marked node again by its saved
+    /// location, and reply with the new list of marks.
+    LoadMarks { path: String },
 
     /// Provide the server with a list of available buffers.  If the compiler would load one of the
     /// named files, the server will request its contents from the client, instead of reading the
@@ -39,17 +105,123 @@ pub enum ToServer {
 
     /// Run a refactoring command.
     RunCommand { name: String, args: Vec<String> },
+
+    /// Run a refactoring command with extra rustc flags, which apply only until the crate is next
+    /// reloaded.  Only `--cfg` flags are supported.
+    RunCommandWithArgs {
+        name: String,
+        args: Vec<String>,
+        rustc_args: Vec<String>,
+    },
+
+    /// Run a sequence of refactoring commands, each given as a name and arguments.  The crate is
+    /// loaded once before the first command and the rewritten files are saved once after the last,
+    /// rather than once per command as with a series of `RunCommand`s.
+    RunCommands { commands: Vec<(String, Vec<String>)> },
+
+    /// Run a refactoring command without applying its changes, and reply with a `CommandPreview`
+    /// describing what it would have changed.
+    PreviewCommand { name: String, args: Vec<String> },
+
+    /// Undo the most recent `RunCommand`, `RunCommandWithArgs`, or `RunCommands`: send the
+    /// buffers it rewrote back to their earlier text, restore the marks it changed, and reply with
+    /// the restored list of marks.
+    Undo,
+
+    /// Get the minimum phase and documentation of a refactoring command, without running it.  The
+    /// phase may depend on the arguments, so `args` should be those the command will be run with.
+    /// `name` may be an alias, in which case the reply gives the name of the command it refers to.
+    QueryCommand { name: String, args: Vec<String> },
+
+    /// Get the names and documentation of all known refactoring commands.
+    ListCommands,
 }
 
 #[derive(Clone, Debug)]
 pub struct MarkInfo {
     id: usize,
+    /// An identifier for the node that, unlike `id`, stays the same when the crate is reloaded
+    /// after a rewrite.  See `pick_node::stable_node_ids`.
+    stable_id: String,
     file: String,
     start_line: u32,
     start_col: u32,
     end_line: u32,
     end_col: u32,
     labels: Vec<String>,
+    /// The node lies in a virtual file, such as a macro expansion, that clients can't open.
+    /// Such marks are left out of mark lists and refused when requested individually.
+    virtual_file: bool,
+    /// In replies to `AddMark` and `InspectNode`, which of the requested node kinds the node was
+    /// found as.
+    kind: Option<String>,
+    /// In replies to `GetMarkListWithText` and `InspectNode`, the source text of the node.
+    excerpt: Option<String>,
+    /// In replies to `GetMarkInfo` and `GetMarkList` asking for context lines, the source lines
+    /// around the node.
+    context: Option<MarkContext>,
+}
+
+/// Lines of source surrounding a marked node, cut short at the start and end of the file.
+#[derive(Clone, Debug)]
+pub struct MarkContext {
+    /// The lines just before the node's first line, in file order.
+    before: Vec<String>,
+    /// The lines just after the node's last line, in file order.
+    after: Vec<String>,
+}
+
+/// How columns within a line are counted in messages, chosen when interactive mode starts.  Lines
+/// are numbered from 1 and columns from 0 in every encoding; they differ only on lines containing
+/// non-ASCII text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnEncoding {
+    /// Bytes of UTF-8.
+    Utf8Bytes,
+    /// UTF-16 code units, as in the Language Server Protocol.
+    Utf16,
+    /// Unicode characters.  This is the default.
+    Chars,
+}
+
+impl ColumnEncoding {
+    pub fn from_name(name: &str) -> Option<ColumnEncoding> {
+        match name {
+            "utf8-bytes" => Some(ColumnEncoding::Utf8Bytes),
+            "utf16" => Some(ColumnEncoding::Utf16),
+            "chars" => Some(ColumnEncoding::Chars),
+            _ => None,
+        }
+    }
+
+    /// Get the column of the character at byte offset `byte` of `line`.
+    fn column(self, line: &str, byte: usize) -> u32 {
+        let before = &line[..cmp::min(byte, line.len())];
+        let col = match self {
+            ColumnEncoding::Utf8Bytes => byte,
+            ColumnEncoding::Utf16 => before.encode_utf16().count(),
+            ColumnEncoding::Chars => before.chars().count(),
+        };
+        col as u32
+    }
+
+    /// Get the byte offset within `line` of column `col`.  A column inside a character rounds up
+    /// to the start of the next one, and columns past the end of the line count single bytes.
+    fn byte_offset(self, line: &str, col: u32) -> usize {
+        let col = col as usize;
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= col {
+                return i;
+            }
+            units += match self {
+                ColumnEncoding::Utf8Bytes => c.len_utf8(),
+                ColumnEncoding::Utf16 => c.len_utf16(),
+                ColumnEncoding::Chars => 1,
+            };
+        }
+        line.len() + col.saturating_sub(units)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -59,11 +231,28 @@ pub enum ToClient {
         info: MarkInfo,
     },
 
+    /// Reply to `InspectNode`.  `info` has no labels, since the node isn't marked.
+    NodeInfo {
+        info: MarkInfo,
+    },
+
     /// List the IDs of all marked nodes.
     MarkList {
         infos: Vec<MarkInfo>,
     },
 
+    /// Reply to `GetMarkType`, with the expression's type as rustc prints it.
+    MarkType {
+        id: usize,
+        ty: String,
+    },
+
+    /// Reply to `MarkCallsOf`, with the number of calls to `name` that were marked.
+    CallsMarked {
+        name: String,
+        count: usize,
+    },
+
     /// Request buffer text from the client.
     GetBufferText {
         file: String,
@@ -75,11 +264,69 @@ pub enum ToClient {
         content: String,
     },
 
+    /// Rewritten buffer, as edits to be applied in order to the text the server last read.  Only
+    /// sent to clients started with the `diffs=on` option; others get `NewBufferText`.
+    NewBufferDiff {
+        file: String,
+        edits: Vec<FileEdit>,
+    },
+
+    /// The server has reached `phase` of a long-running request, such as `starting driver` or
+    /// `running command`.  Clients may ignore these.
+    Progress {
+        phase: String,
+        message: String,
+    },
+
+    /// Problems reported by the commands of the last request that didn't stop them from running.
+    Diagnostics {
+        items: Vec<Diagnostic>,
+    },
+
+    /// Summary of the changes a `PreviewCommand` would make.
+    CommandPreview {
+        /// Files whose text would be rewritten.
+        files: Vec<String>,
+        marks_changed: bool,
+        krate_changed: bool,
+    },
+
+    /// Reply to `QueryCommand`.  `info.phase` is `None` if the command's phase isn't known ahead
+    /// of time.
+    CommandDetails {
+        name: String,
+        info: CommandInfo,
+    },
+
+    /// Reply to `ListCommands`, with each command's name and documentation, sorted by name.
+    CommandList {
+        commands: Vec<(String, CommandInfo)>,
+    },
+
     Error {
         text: String,
+        /// Source location of the panic that produced this error, if any.
+        location: Option<String>,
     },
 }
 
+/// The name clients use for `phase`.
+fn phase_name(phase: Option<Phase>) -> &'static str {
+    match phase {
+        Some(Phase::Phase1) => "phase1",
+        Some(Phase::Phase2) => "phase2",
+        Some(Phase::Phase3) => "phase3",
+        None => "unknown",
+    }
+}
+
+/// Canonicalize `path` if possible, or return it unchanged otherwise.  Buffers provided by the
+/// client need not exist on disk (or may be reached through a broken symlink), so paths that can't
+/// be resolved are still valid buffer names.
+fn canonicalize_lossy(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
 /// Like `std::sync::mpsc::Sender`, but transforms sent data with a function before sending it to
 /// the receiving thread.
 #[derive(Clone, Debug)]
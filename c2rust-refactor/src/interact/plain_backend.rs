@@ -1,13 +1,26 @@
 //! Plain-text backend, for testing interactive mode.
-use std::io::{self, BufRead, Write};
-use std::str::FromStr;
+use std::io::{self, BufRead, Read, Write};
+use std::str::{self, FromStr};
 use std::sync::mpsc::{self, SyncSender};
 use std::thread;
 
-use crate::interact::WrapSender;
+use crate::command::CommandInfo;
+use crate::interact::{phase_name, WrapSender};
 use crate::interact::{MarkInfo, ToClient, ToServer};
 
-pub fn init<U, F>(to_server: WrapSender<ToServer, U, F>) -> SyncSender<ToClient>
+/// How messages are delimited on stdin and stdout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framing {
+    /// One message per line, except that buffer text is sent on the lines after the message and
+    /// ended by a line containing only `.`.
+    Lines,
+    /// Each message is preceded by its length in bytes, as a 4-byte big-endian integer, so buffer
+    /// text can contain anything.  The client sends `buffer-text` contents after the first
+    /// newline of the message, rather than on the same line.
+    LengthPrefixed,
+}
+
+pub fn init<U, F>(to_server: WrapSender<ToServer, U, F>, framing: Framing) -> SyncSender<ToClient>
 where
     U: Send + 'static,
     F: Fn(ToServer) -> U + Send + 'static,
@@ -19,8 +32,13 @@ where
         let mut out = out.lock();
 
         for msg in client_recv.iter() {
-            let line = encode_message(msg);
-            out.write_all(line.as_bytes()).unwrap();
+            let text = encode_message(msg);
+            match framing {
+                Framing::Lines => out.write_all(text.as_bytes()).unwrap(),
+                // Progress messages encode to nothing, so there's nothing to send.
+                Framing::LengthPrefixed if text.is_empty() => {}
+                Framing::LengthPrefixed => write_frame(&mut out, text.as_bytes()).unwrap(),
+            }
             out.flush().unwrap();
         }
     });
@@ -29,23 +47,72 @@ where
         let in_ = io::stdin();
         let mut in_ = in_.lock();
 
-        let mut line = String::new();
-        while let Ok(_) = in_.read_line(&mut line) {
-            // Drop trailing '\n'
-            let end = line.len() - 1;
-            let msg = decode_message(&line[..end]).unwrap();
-            line.clear();
-            to_server.send(msg).unwrap();
+        match framing {
+            Framing::Lines => {
+                let mut line = String::new();
+                while let Ok(_) = in_.read_line(&mut line) {
+                    // Drop trailing '\n'
+                    let end = line.len() - 1;
+                    let msg = decode_message(&line[..end]).unwrap();
+                    line.clear();
+                    to_server.send(msg).unwrap();
+                }
+            }
+            Framing::LengthPrefixed => {
+                while let Ok(Some(frame)) = read_frame(&mut in_) {
+                    let msg = decode_frame(&frame).unwrap();
+                    to_server.send(msg).unwrap();
+                }
+            }
         }
     });
 
     client_send
 }
 
+fn write_frame<W: Write>(out: &mut W, payload: &[u8]) -> io::Result<()> {
+    let len = payload.len() as u32;
+    out.write_all(&len.to_be_bytes())?;
+    out.write_all(payload)
+}
+
+/// Read one length-prefixed message, or `None` if the input ended before its length.
+fn read_frame<R: Read>(in_: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match in_.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut payload = vec![0; u32::from_be_bytes(len) as usize];
+    in_.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// Decode a length-prefixed message.  Anything after the first line is the contents of a
+/// `buffer-text` message.
+fn decode_frame(payload: &[u8]) -> Result<ToServer, String> {
+    let text = str::from_utf8(payload).map_err(|e| format!("message is not UTF-8: {}", e))?;
+    let (line, body) = match text.find('\n') {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => return decode_message(text),
+    };
+
+    let mut parts = line.split(' ');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("buffer-text"), Some(file), None) => Ok(ToServer::BufferText {
+            file: file.to_owned(),
+            content: body.to_owned(),
+        }),
+        _ => Err(format!("unexpected text after message `{}`", line)),
+    }
+}
+
 fn encode_mark_info(i: MarkInfo) -> String {
     let mut s = format!(
-        "info {} {} {} {} {} {} {}",
+        "info {} {} {} {} {} {} {} {}",
         i.id,
+        i.stable_id,
         i.file,
         i.start_line,
         i.start_col,
@@ -56,13 +123,45 @@ fn encode_mark_info(i: MarkInfo) -> String {
     for l in i.labels {
         s.push_str(&format!(" {}", l));
     }
+    if let Some(kind) = i.kind {
+        s.push_str(&format!(" kind {}", kind));
+    }
+    // Quoted, since the text may contain spaces and newlines
+    if let Some(excerpt) = i.excerpt {
+        s.push_str(&format!(" excerpt {:?}", excerpt));
+    }
+    if let Some(context) = i.context {
+        s.push_str(&format!(" context {}", context.before.len()));
+        for l in context.before {
+            s.push_str(&format!(" {:?}", l));
+        }
+        s.push_str(&format!(" {}", context.after.len()));
+        for l in context.after {
+            s.push_str(&format!(" {:?}", l));
+        }
+    }
     s
 }
 
+fn encode_command_info(name: String, info: CommandInfo) -> String {
+    format!(
+        "{} {} {} {}\n{}\n{}\n.\n",
+        name,
+        phase_name(info.phase),
+        info.min_args,
+        info.max_args
+            .map_or_else(|| "-".to_owned(), |max| max.to_string()),
+        info.usage,
+        info.description
+    )
+}
+
 fn encode_message(msg: ToClient) -> String {
     match msg {
         ToClient::Mark { info } => format!("mark {}\n", encode_mark_info(info)),
 
+        ToClient::NodeInfo { info } => format!("node-info {}\n", encode_mark_info(info)),
+
         ToClient::MarkList { infos } => {
             let mut s = String::new();
             s.push_str("mark-list");
@@ -73,13 +172,74 @@ fn encode_message(msg: ToClient) -> String {
             s
         }
 
+        ToClient::MarkType { id, ty } => format!("mark-type {} {}\n", id, ty),
+
+        ToClient::CallsMarked { name, count } => format!("calls-marked {} {}\n", name, count),
+
         ToClient::GetBufferText { file } => format!("get-buffer-text {}\n", file),
 
         ToClient::NewBufferText { file, content } => {
             format!("new-buffer-text {}\n{}\n.\n", file, content)
         }
 
-        ToClient::Error { text } => format!("error {}", text),
+        ToClient::NewBufferDiff { file, edits } => {
+            let mut s = format!("new-buffer-diff {} {}\n", file, edits.len());
+            for e in edits {
+                s.push_str(&format!(
+                    "edit {} {} {} {}\n{}\n.\n",
+                    e.lo.0, e.lo.1, e.hi.0, e.hi.1, e.text
+                ));
+            }
+            s
+        }
+
+        // Progress messages are only informational, so keep them out of the test output.
+        ToClient::Progress { .. } => String::new(),
+
+        ToClient::Diagnostics { items } => {
+            let mut s = String::new();
+            for d in items {
+                s.push_str(&format!(
+                    "diagnostic {} {} {} {} {}\n",
+                    d.severity.as_str(),
+                    d.file,
+                    d.line,
+                    d.col,
+                    d.message
+                ));
+            }
+            s
+        }
+
+        ToClient::CommandPreview {
+            files,
+            marks_changed,
+            krate_changed,
+        } => {
+            let mut s = format!("command-preview {} {}", marks_changed, krate_changed);
+            for f in files {
+                s.push_str(&format!(" {}", f));
+            }
+            s.push('\n');
+            s
+        }
+
+        ToClient::CommandDetails { name, info } => {
+            format!("command-details {}", encode_command_info(name, info))
+        }
+
+        ToClient::CommandList { commands } => {
+            let mut s = format!("command-list {}\n", commands.len());
+            for (name, info) in commands {
+                s.push_str(&format!("command {}", encode_command_info(name, info)));
+            }
+            s
+        }
+
+        ToClient::Error { text, location } => match location {
+            Some(loc) => format!("error {} (at {})\n", text, loc),
+            None => format!("error {}\n", text),
+        },
     }
 }
 
@@ -104,6 +264,18 @@ fn decode_message(line: &str) -> Result<ToServer, String> {
         }
     }
 
+    macro_rules! get_conv_opt {
+        ($t:ty) => {
+            match parts.next() {
+                Some(x) => match <$t>::from_str(x) {
+                    Ok(y) => Some(y),
+                    Err(e) => return Err(format!("error while parsing {}: {:?}", stringify!($t), e)),
+                },
+                None => None,
+            }
+        }
+    }
+
     Ok(match &kind as &str {
         "add-mark" => ToServer::AddMark {
             file: get_conv!(String),
@@ -111,17 +283,68 @@ fn decode_message(line: &str) -> Result<ToServer, String> {
             col: get_conv!(u32),
             kind: get_conv!(String),
             label: get_conv!(String),
+            end_line: get_conv_opt!(u32),
+            end_col: get_conv_opt!(u32),
+        },
+
+        "inspect-node" => ToServer::InspectNode {
+            file: get_conv!(String),
+            line: get_conv!(u32),
+            col: get_conv!(u32),
+            kind: get_conv!(String),
+        },
+
+        "add-mark-by-name" => ToServer::AddMarkByName {
+            name: get_conv!(String),
+            kind: get_conv!(String),
+            label: get_conv!(String),
+        },
+
+        "mark-calls-of" => ToServer::MarkCallsOf {
+            name: get_conv!(String),
+            label: get_conv!(String),
         },
 
         "remove-mark" => ToServer::RemoveMark {
             id: get_conv!(usize),
         },
 
+        "remove-marks-by-label" => ToServer::RemoveMarksByLabel {
+            label: get_conv!(String),
+        },
+
+        "clear-marks" => ToServer::ClearMarks,
+
         "get-mark-info" => ToServer::GetMarkInfo {
             id: get_conv!(usize),
+            context_lines: get_conv_opt!(u32).unwrap_or(0),
         },
 
-        "get-mark-list" => ToServer::GetMarkList,
+        "get-mark-type" => ToServer::GetMarkType {
+            id: get_conv!(usize),
+        },
+
+        "get-mark-list" => ToServer::GetMarkList {
+            context_lines: get_conv_opt!(u32).unwrap_or(0),
+        },
+
+        "get-mark-list-with-text" => ToServer::GetMarkListWithText {
+            max_len: get_conv!(usize),
+        },
+
+        "get-marks-in-range" => ToServer::GetMarksInRange {
+            file: get_conv!(String),
+            start_line: get_conv!(u32),
+            end_line: get_conv!(u32),
+        },
+
+        "save-marks" => ToServer::SaveMarks {
+            path: get_conv!(String),
+        },
+
+        "load-marks" => ToServer::LoadMarks {
+            path: get_conv!(String),
+        },
 
         "set-buffers-available" => ToServer::SetBuffersAvailable {
             files: parts.map(|s| s.to_owned()).collect(),
@@ -137,6 +360,127 @@ fn decode_message(line: &str) -> Result<ToServer, String> {
             args: parts.map(|s| s.to_owned()).collect(),
         },
 
+        // Rustc flags follow the command arguments, after `--`.
+        "run-command-with-args" => {
+            let name = get_conv!(String);
+            let rest = parts.map(|s| s.to_owned()).collect::<Vec<_>>();
+            let (args, rustc_args) = match rest.iter().position(|s| s == "--") {
+                Some(i) => (rest[..i].to_owned(), rest[i + 1..].to_owned()),
+                None => (rest, vec![]),
+            };
+            ToServer::RunCommandWithArgs {
+                name,
+                args,
+                rustc_args,
+            }
+        }
+
+        "preview-command" => ToServer::PreviewCommand {
+            name: get_conv!(String),
+            args: parts.map(|s| s.to_owned()).collect(),
+        },
+
+        "undo" => ToServer::Undo,
+
+        "query-command" => ToServer::QueryCommand {
+            name: get_conv!(String),
+            args: parts.map(|s| s.to_owned()).collect(),
+        },
+
+        "list-commands" => ToServer::ListCommands,
+
+        // Commands are separated by `;`, as on the `c2rust-refactor` command line.
+        "run-commands" => {
+            let mut commands = Vec::new();
+            let mut cur: Option<(String, Vec<String>)> = None;
+            for part in parts {
+                if part == ";" {
+                    commands.extend(cur.take());
+                } else if let Some((_, ref mut args)) = cur {
+                    args.push(part.to_owned());
+                } else {
+                    cur = Some((part.to_owned(), Vec::new()));
+                }
+            }
+            commands.extend(cur);
+            ToServer::RunCommands { commands }
+        }
+
         s => return Err(format!("unrecognized message kind `{}`", s)),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_not_printed() {
+        let msg = ToClient::Progress {
+            phase: "running command".to_owned(),
+            message: "rename".to_owned(),
+        };
+        assert_eq!(encode_message(msg), "");
+    }
+
+    #[test]
+    fn decode_run_commands() {
+        let msg =
+            decode_message("run-commands rename_items_regex foo bar ; reorganize_definitions")
+                .unwrap();
+        match msg {
+            ToServer::RunCommands { commands } => assert_eq!(
+                commands,
+                vec![
+                    (
+                        "rename_items_regex".to_owned(),
+                        vec!["foo".to_owned(), "bar".to_owned()]
+                    ),
+                    ("reorganize_definitions".to_owned(), vec![]),
+                ]
+            ),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn framed_buffer_text_round_trip() {
+        let content = "fn f() {}\n.\n\0 g\n";
+
+        let msg = ToClient::NewBufferText {
+            file: "a.rs".to_owned(),
+            content: content.to_owned(),
+        };
+        let mut out = Vec::new();
+        write_frame(&mut out, encode_message(msg).as_bytes()).unwrap();
+        let mut in_ = &out[..];
+        let frame = read_frame(&mut in_).unwrap().unwrap();
+        assert_eq!(&out[..4], &(frame.len() as u32).to_be_bytes());
+        assert_eq!(
+            str::from_utf8(&frame).unwrap(),
+            format!("new-buffer-text a.rs\n{}\n.\n", content)
+        );
+        assert!(read_frame(&mut in_).unwrap().is_none());
+
+        let mut out = Vec::new();
+        let text = format!("buffer-text a.rs\n{}", content);
+        write_frame(&mut out, text.as_bytes()).unwrap();
+        let frame = read_frame(&mut &out[..]).unwrap().unwrap();
+        match decode_frame(&frame).unwrap() {
+            ToServer::BufferText { file, content: c } => {
+                assert_eq!(file, "a.rs");
+                assert_eq!(c, content);
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        match decode_frame(b"get-mark-info 3").unwrap() {
+            ToServer::GetMarkInfo {
+                id: 3,
+                context_lines: 0,
+            } => {}
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert!(decode_frame(b"get-mark-info 3\nextra").is_err());
+    }
+}
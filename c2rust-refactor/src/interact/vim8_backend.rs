@@ -1,11 +1,13 @@
 //! JSON backend, for communication with Vim 8.
 use json::{self, JsonValue};
-use std::io::{self, BufRead, Write};
+use std::io::{self, Read, Write};
 use std::sync::mpsc::{self, SyncSender};
 use std::thread;
 
-use crate::interact::WrapSender;
+use crate::command::{CommandInfo, Diagnostic};
+use crate::interact::{phase_name, WrapSender};
 use crate::interact::{MarkInfo, ToClient, ToServer};
+use crate::rewrite::files::FileEdit;
 
 pub fn init<U, F>(to_server: WrapSender<ToServer, U, F>) -> SyncSender<ToClient>
 where
@@ -31,32 +33,152 @@ where
         let in_ = io::stdin();
         let mut in_ = in_.lock();
 
-        let mut line = String::new();
-        while let Ok(_) = in_.read_line(&mut line) {
-            let json = json::parse(&line).unwrap();
-            let msg = decode_message(json).unwrap();
-            info!("received: {:?}", msg);
-            line.clear();
-            to_server.send(msg).unwrap();
+        let mut input = InputBuffer::default();
+        let mut chunk = [0; 4096];
+        loop {
+            let len = match in_.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => len,
+            };
+            input.push(&chunk[..len]);
+            while let Some(text) = input.next_message() {
+                let msg = decode_input(&text).unwrap();
+                info!("received: {:?}", msg);
+                to_server.send(msg).unwrap();
+            }
         }
     });
 
     client_send
 }
 
+/// Bytes read from Vim that have not yet been decoded.  A large message, such as a `buffer-text`,
+/// may arrive split across several reads, and one read may hold several messages, so messages are
+/// split at the end of each top-level JSON value rather than wherever a read happens to stop.
+#[derive(Default)]
+struct InputBuffer {
+    bytes: Vec<u8>,
+}
+
+impl InputBuffer {
+    fn push(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Remove the next complete message from the buffer and return its text, or return `None` if
+    /// the rest of the message hasn't been read yet.
+    fn next_message(&mut self) -> Option<String> {
+        let start = match self.bytes.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(start) => start,
+            None => {
+                self.bytes.clear();
+                return None;
+            }
+        };
+
+        let mut depth = 0_usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut end = None;
+        for (i, &b) in self.bytes.iter().enumerate().skip(start) {
+            if in_string {
+                match b {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match b {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            // Anything other than an object or array ends at the end of its line, and fails to
+            // decode.
+            if depth == 0 && (b == b'}' || b == b']' || b == b'\n') {
+                end = Some(i + 1);
+                break;
+            }
+        }
+
+        let end = end?;
+        let text = String::from_utf8_lossy(&self.bytes[start..end]).into_owned();
+        self.bytes.drain(..end);
+        Some(text)
+    }
+}
+
+/// Decode one message from Vim.  On a channel in JSON mode, Vim wraps each message as
+/// `[seq, payload]`; in raw mode the payload is sent alone.
+fn decode_input(text: &str) -> Result<ToServer, String> {
+    let json = json::parse(text).map_err(|e| format!("invalid JSON: {}", e))?;
+    match json {
+        JsonValue::Array(mut parts) if parts.len() == 2 => decode_message(parts.pop().unwrap()),
+        json => decode_message(json),
+    }
+}
+
 fn encode_mark_info(i: MarkInfo) -> JsonValue {
-    object! {
+    let mut obj = object! {
         "id" => i.id,
+        "stable_id" => i.stable_id,
         "file" => i.file,
         "start_line" => i.start_line,
         "start_col" => i.start_col,
         "end_line" => i.end_line,
         "end_col" => i.end_col,
         "labels" => i.labels
+    };
+    if let Some(kind) = i.kind {
+        obj["kind"] = kind.into();
+    }
+    if let Some(excerpt) = i.excerpt {
+        obj["excerpt"] = excerpt.into();
+    }
+    if let Some(context) = i.context {
+        obj["context"] = object! {
+            "before" => context.before,
+            "after" => context.after
+        };
+    }
+    obj
+}
+
+fn encode_file_edit(e: FileEdit) -> JsonValue {
+    object! {
+        "start_line" => e.lo.0,
+        "start_col" => e.lo.1,
+        "end_line" => e.hi.0,
+        "end_col" => e.hi.1,
+        "text" => e.text
     }
 }
 
-fn encode_message(msg: ToClient) -> JsonValue {
+fn encode_diagnostic(d: Diagnostic) -> JsonValue {
+    object! {
+        "severity" => d.severity.as_str(),
+        "file" => d.file,
+        "line" => d.line,
+        "col" => d.col,
+        "message" => d.message
+    }
+}
+
+fn encode_command_info(name: String, info: CommandInfo) -> JsonValue {
+    object! {
+        "name" => name,
+        "phase" => phase_name(info.phase),
+        "usage" => info.usage,
+        "description" => info.description,
+        "min_args" => info.min_args,
+        "max_args" => info.max_args
+    }
+}
+
+pub(super) fn encode_message(msg: ToClient) -> JsonValue {
     match msg {
         ToClient::Mark { info } => {
             object! {
@@ -65,6 +187,13 @@ fn encode_message(msg: ToClient) -> JsonValue {
             }
         }
 
+        ToClient::NodeInfo { info } => {
+            object! {
+                "msg" => "node-info",
+                "info" => encode_mark_info(info)
+            }
+        }
+
         ToClient::MarkList { infos } => {
             object! {
                 "msg" => "mark-list",
@@ -72,6 +201,22 @@ fn encode_message(msg: ToClient) -> JsonValue {
             }
         }
 
+        ToClient::MarkType { id, ty } => {
+            object! {
+                "msg" => "mark-type",
+                "id" => id,
+                "type" => ty
+            }
+        }
+
+        ToClient::CallsMarked { name, count } => {
+            object! {
+                "msg" => "calls-marked",
+                "name" => name,
+                "count" => count
+            }
+        }
+
         ToClient::GetBufferText { file } => {
             object! {
                 "msg" => "get-buffer-text",
@@ -87,16 +232,69 @@ fn encode_message(msg: ToClient) -> JsonValue {
             }
         }
 
-        ToClient::Error { text } => {
+        ToClient::NewBufferDiff { file, edits } => {
+            object! {
+                "msg" => "new-buffer-diff",
+                "file" => file,
+                "edits" => edits.into_iter().map(encode_file_edit).collect::<Vec<_>>()
+            }
+        }
+
+        ToClient::Progress { phase, message } => {
+            object! {
+                "msg" => "progress",
+                "phase" => phase,
+                "message" => message
+            }
+        }
+
+        ToClient::Diagnostics { items } => {
+            object! {
+                "msg" => "diagnostics",
+                "items" => items.into_iter().map(encode_diagnostic).collect::<Vec<_>>()
+            }
+        }
+
+        ToClient::CommandPreview {
+            files,
+            marks_changed,
+            krate_changed,
+        } => {
+            object! {
+                "msg" => "command-preview",
+                "files" => files,
+                "marks_changed" => marks_changed,
+                "krate_changed" => krate_changed
+            }
+        }
+
+        ToClient::CommandDetails { name, info } => {
+            let mut obj = encode_command_info(name, info);
+            obj["msg"] = "command-details".into();
+            obj
+        }
+
+        ToClient::CommandList { commands } => {
+            object! {
+                "msg" => "command-list",
+                "commands" => commands
+                    .into_iter()
+                    .map(|(name, info)| encode_command_info(name, info))
+                    .collect::<Vec<_>>()
+            }
+        }
+
+        ToClient::Error { text, location } => {
             object! {
                 "msg" => "error",
-                "text" => text
+                "text" => text,
+                "location" => location
             }
         }
     }
 }
 
-fn decode_message(json: JsonValue) -> Result<ToServer, String> {
+pub(super) fn decode_message(json: JsonValue) -> Result<ToServer, String> {
     let mut obj = match json {
         JsonValue::Object(obj) => obj,
         _ => return Err("expected object".to_owned()),
@@ -150,6 +348,24 @@ fn decode_message(json: JsonValue) -> Result<ToServer, String> {
         }};
     };
 
+    macro_rules! get_conv_opt {
+        ($json:expr, $key:expr, $conv:ident) => {
+            match $json.get_mut($key) {
+                Some(x) => match x.$conv() {
+                    Some(y) => Some(y),
+                    None => {
+                        return Err(format!(
+                            "conversion `{}` failed on key `{}`",
+                            stringify!($conv),
+                            $key
+                        ))
+                    }
+                },
+                None => None,
+            }
+        };
+    };
+
     let kind = get_conv!(obj, "msg", take_string);
 
     Ok(match &kind as &str {
@@ -159,17 +375,68 @@ fn decode_message(json: JsonValue) -> Result<ToServer, String> {
             col: get_conv!(obj, "col", as_u32),
             kind: get_conv!(obj, "kind", take_string),
             label: get_conv!(obj, "label", take_string),
+            end_line: get_conv_opt!(obj, "end_line", as_u32),
+            end_col: get_conv_opt!(obj, "end_col", as_u32),
+        },
+
+        "inspect-node" => ToServer::InspectNode {
+            file: get_conv!(obj, "file", take_string),
+            line: get_conv!(obj, "line", as_u32),
+            col: get_conv!(obj, "col", as_u32),
+            kind: get_conv!(obj, "kind", take_string),
+        },
+
+        "add-mark-by-name" => ToServer::AddMarkByName {
+            name: get_conv!(obj, "name", take_string),
+            kind: get_conv!(obj, "kind", take_string),
+            label: get_conv!(obj, "label", take_string),
+        },
+
+        "mark-calls-of" => ToServer::MarkCallsOf {
+            name: get_conv!(obj, "name", take_string),
+            label: get_conv!(obj, "label", take_string),
         },
 
         "remove-mark" => ToServer::RemoveMark {
             id: get_conv!(obj, "id", as_usize),
         },
 
+        "remove-marks-by-label" => ToServer::RemoveMarksByLabel {
+            label: get_conv!(obj, "label", take_string),
+        },
+
+        "clear-marks" => ToServer::ClearMarks,
+
         "get-mark-info" => ToServer::GetMarkInfo {
             id: get_conv!(obj, "id", as_usize),
+            context_lines: get_conv_opt!(obj, "context_lines", as_u32).unwrap_or(0),
+        },
+
+        "get-mark-type" => ToServer::GetMarkType {
+            id: get_conv!(obj, "id", as_usize),
+        },
+
+        "get-mark-list" => ToServer::GetMarkList {
+            context_lines: get_conv_opt!(obj, "context_lines", as_u32).unwrap_or(0),
+        },
+
+        "get-mark-list-with-text" => ToServer::GetMarkListWithText {
+            max_len: get_conv!(obj, "max_len", as_usize),
+        },
+
+        "get-marks-in-range" => ToServer::GetMarksInRange {
+            file: get_conv!(obj, "file", take_string),
+            start_line: get_conv!(obj, "start_line", as_u32),
+            end_line: get_conv!(obj, "end_line", as_u32),
         },
 
-        "get-mark-list" => ToServer::GetMarkList,
+        "save-marks" => ToServer::SaveMarks {
+            path: get_conv!(obj, "path", take_string),
+        },
+
+        "load-marks" => ToServer::LoadMarks {
+            path: get_conv!(obj, "path", take_string),
+        },
 
         "set-buffers-available" => ToServer::SetBuffersAvailable {
             files: get_conv_array!(obj, "files", take_string),
@@ -185,6 +452,102 @@ fn decode_message(json: JsonValue) -> Result<ToServer, String> {
             args: get_conv_array!(obj, "args", take_string),
         },
 
+        "run-command-with-args" => ToServer::RunCommandWithArgs {
+            name: get_conv!(obj, "name", take_string),
+            args: get_conv_array!(obj, "args", take_string),
+            rustc_args: get_conv_array!(obj, "rustc_args", take_string),
+        },
+
+        "preview-command" => ToServer::PreviewCommand {
+            name: get_conv!(obj, "name", take_string),
+            args: get_conv_array!(obj, "args", take_string),
+        },
+
+        "undo" => ToServer::Undo,
+
+        "query-command" => ToServer::QueryCommand {
+            name: get_conv!(obj, "name", take_string),
+            args: get_conv_array!(obj, "args", take_string),
+        },
+
+        "list-commands" => ToServer::ListCommands,
+
+        "run-commands" => {
+            let mut commands = Vec::new();
+            let mut cmds = match obj.get_mut("commands") {
+                Some(JsonValue::Array(x)) => x.split_off(0),
+                Some(_) => return Err("expected key `commands` to contain an array".to_owned()),
+                None => return Err("missing key `commands`".to_owned()),
+            };
+            for (i, cmd) in cmds.iter_mut().enumerate() {
+                let cmd = match *cmd {
+                    JsonValue::Object(ref mut x) => x,
+                    _ => {
+                        return Err(format!(
+                            "expected element {} of `commands` to be an object",
+                            i
+                        ))
+                    }
+                };
+                let name = get_conv!(cmd, "name", take_string);
+                let args = get_conv_array!(cmd, "args", take_string);
+                commands.push((name, args));
+            }
+            ToServer::RunCommands { commands }
+        }
+
         s => return Err(format!("unrecognized message kind `{}`", s)),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoded(input: &mut InputBuffer) -> Vec<ToServer> {
+        let mut msgs = vec![];
+        while let Some(text) = input.next_message() {
+            msgs.push(decode_input(&text).unwrap());
+        }
+        msgs
+    }
+
+    #[test]
+    fn message_split_across_reads() {
+        let msg = r#"{"msg": "buffer-text", "file": "a.rs", "content": "fn f() {\n    \"}\"\n}\n"}"#;
+        let (first, rest) = msg.split_at(msg.find("{\\n").unwrap() + 1);
+
+        let mut input = InputBuffer::default();
+        input.push(first.as_bytes());
+        assert!(decoded(&mut input).is_empty());
+        input.push(rest.as_bytes());
+        input.push(b"\n");
+        match decoded(&mut input).as_slice() {
+            [ToServer::BufferText { file, content }] => {
+                assert_eq!(file, "a.rs");
+                assert_eq!(content, "fn f() {\n    \"}\"\n}\n");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(input.bytes.iter().all(|b| b.is_ascii_whitespace()));
+    }
+
+    #[test]
+    fn several_messages_in_one_read() {
+        let mut input = InputBuffer::default();
+        input.push(
+            b"{\"msg\": \"remove-mark\", \"id\": 3}\n[1, {\"msg\": \"clear-marks\"}]\n{\"msg\": ",
+        );
+        match decoded(&mut input).as_slice() {
+            [ToServer::RemoveMark { id: 3 }, ToServer::ClearMarks] => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // The partial message at the end is kept until the rest of it arrives.
+        input.push(b"\"undo\"}\n");
+        match decoded(&mut input).as_slice() {
+            [ToServer::Undo] => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -5,11 +5,10 @@
 //! actually, obtaining file contents requires sending a request to the client and processing
 //! messages until we get a response.  That loop happens in the worker thread.
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{Receiver, Sender, SyncSender};
 
-use crate::interact::{ToClient, ToServer};
+use crate::interact::{canonicalize_lossy, ToClient, ToServer};
 
 pub enum ToWorker {
     InputMessage(ToServer),
@@ -47,7 +46,7 @@ impl WorkerState {
         match msg {
             InputMessage(BufferText { file, content }) => {
                 info!("got text for file {:?}", file);
-                let path = fs::canonicalize(&file).unwrap();
+                let path = canonicalize_lossy(Path::new(&file));
                 let send = match self.pending_files.remove(&path) {
                     Some(x) => x,
                     None => {
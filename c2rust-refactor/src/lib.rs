@@ -499,6 +499,16 @@ fn main_impl(opts: Options) -> interface::Result<()> {
                                 std::process::exit(1);
                             }
                         }
+                        for d in state.drain_diagnostics() {
+                            warn!(
+                                "{}: {}:{}:{}: {}",
+                                d.severity.as_str(),
+                                d.file,
+                                d.line,
+                                d.col,
+                                d.message
+                            );
+                        }
                     }
                 }
 
@@ -444,7 +444,7 @@ pub fn register_commands(reg: &mut Registry) {
         let arg = args[0].clone();
         Box::new(DriverCommand::new(Phase::Phase2, move |st, cx| {
             find_mark_uses_command(st, cx, &arg);
-        }))
+        }).pure())
     });
 
     reg.register("mark_field_uses", |args| {
@@ -452,7 +452,7 @@ pub fn register_commands(reg: &mut Registry) {
         let label = args[1].clone();
         Box::new(DriverCommand::new(Phase::Phase3, move |st, cx| {
             find_field_uses_command(st, cx, &field, &label);
-        }))
+        }).pure())
     });
 
     reg.register("mark_arg_uses", |args| {
@@ -460,14 +460,14 @@ pub fn register_commands(reg: &mut Registry) {
         let label = args[1].clone();
         Box::new(DriverCommand::new(Phase::Phase3, move |st, cx| {
             find_arg_uses_command(st, cx, arg_idx, &label);
-        }))
+        }).pure())
     });
 
     reg.register("mark_callers", |args| {
         let label = args[0].clone();
         Box::new(DriverCommand::new(Phase::Phase3, move |st, cx| {
             find_callers_command(st, cx, &label);
-        }))
+        }).pure())
     });
 
     reg.register("copy_marks", |args| {
@@ -497,7 +497,7 @@ pub fn register_commands(reg: &mut Registry) {
         let label = args[0].clone();
         Box::new(DriverCommand::new(Phase::Phase2, move |st, _cx| {
             mark_pub_in_mod(st, &label);
-        }))
+        }).pure())
     });
 
     register_clear_marks(reg);
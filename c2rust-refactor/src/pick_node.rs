@@ -2,6 +2,8 @@
 //!
 //! This is used in various parts of the frontend to set marks at specific locations.
 use rustc::session::Session;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::PathBuf;
 use std::str::FromStr;
 use syntax::ast::*;
@@ -10,7 +12,7 @@ use syntax::source_map::{BytePos, Span};
 use syntax::visit::{self, FnKind, Visitor};
 use syntax_pos::FileName;
 
-use crate::ast_manip::Visit;
+use crate::ast_manip::{visit_nodes, Visit};
 use crate::command::{DriverCommand, Registry};
 use crate::driver::Phase;
 use crate::RefactorCtxt;
@@ -187,6 +189,162 @@ impl<'a> Visitor<'a> for PickVisitor {
     }
 }
 
+struct NameVisitor<'a> {
+    nodes: Vec<NodeInfo>,
+    kind: NodeKind,
+    name: &'a str,
+}
+
+impl<'a> NameVisitor<'a> {
+    fn record(&mut self, kind: NodeKind, ident: Ident, id: NodeId, span: Span) {
+        if self.kind.contains(kind) && &*ident.as_str() == self.name {
+            self.nodes.push(NodeInfo { id, span });
+        }
+    }
+}
+
+impl<'a, 'ast> Visitor<'ast> for NameVisitor<'a> {
+    fn visit_item(&mut self, x: &'ast Item) {
+        self.record(NodeKind::Item, x.ident, x.id, x.span);
+        visit::walk_item(self, x);
+    }
+
+    fn visit_trait_item(&mut self, x: &'ast TraitItem) {
+        self.record(NodeKind::TraitItem, x.ident, x.id, x.span);
+        visit::walk_trait_item(self, x);
+    }
+
+    fn visit_impl_item(&mut self, x: &'ast ImplItem) {
+        self.record(NodeKind::ImplItem, x.ident, x.id, x.span);
+        visit::walk_impl_item(self, x);
+    }
+
+    fn visit_foreign_item(&mut self, x: &'ast ForeignItem) {
+        self.record(NodeKind::ForeignItem, x.ident, x.id, x.span);
+        visit::walk_foreign_item(self, x);
+    }
+
+    fn visit_struct_field(&mut self, x: &'ast StructField) {
+        if let Some(ident) = x.ident {
+            self.record(NodeKind::Field, ident, x.id, x.span);
+        }
+        visit::walk_struct_field(self, x);
+    }
+
+    fn visit_mac(&mut self, mac: &'ast Mac) {
+        visit::walk_mac(self, mac);
+    }
+}
+
+/// Records the path of child indices from the crate root to each of the `targets`.  Only the node
+/// types that can be picked are counted as children.
+struct PathVisitor {
+    targets: HashSet<NodeId>,
+    paths: HashMap<NodeId, Vec<usize>>,
+    path: Vec<usize>,
+    /// The number of children seen so far by each of the nodes on `path`, and by the crate.
+    counts: Vec<usize>,
+}
+
+impl PathVisitor {
+    fn enter<F: FnOnce(&mut Self)>(&mut self, id: NodeId, walk: F) {
+        let index = {
+            let count = self.counts.last_mut().unwrap();
+            *count += 1;
+            *count - 1
+        };
+        self.path.push(index);
+        if self.targets.contains(&id) {
+            self.paths.insert(id, self.path.clone());
+        }
+
+        self.counts.push(0);
+        walk(self);
+        self.counts.pop();
+        self.path.pop();
+    }
+}
+
+impl<'ast> Visitor<'ast> for PathVisitor {
+    fn visit_item(&mut self, x: &'ast Item) {
+        self.enter(x.id, |v| visit::walk_item(v, x));
+    }
+
+    fn visit_trait_item(&mut self, x: &'ast TraitItem) {
+        self.enter(x.id, |v| visit::walk_trait_item(v, x));
+    }
+
+    fn visit_impl_item(&mut self, x: &'ast ImplItem) {
+        self.enter(x.id, |v| visit::walk_impl_item(v, x));
+    }
+
+    fn visit_foreign_item(&mut self, x: &'ast ForeignItem) {
+        self.enter(x.id, |v| visit::walk_foreign_item(v, x));
+    }
+
+    fn visit_stmt(&mut self, x: &'ast Stmt) {
+        self.enter(x.id, |v| visit::walk_stmt(v, x));
+    }
+
+    fn visit_expr(&mut self, x: &'ast Expr) {
+        self.enter(x.id, |v| visit::walk_expr(v, x));
+    }
+
+    fn visit_pat(&mut self, x: &'ast Pat) {
+        self.enter(x.id, |v| visit::walk_pat(v, x));
+    }
+
+    fn visit_ty(&mut self, x: &'ast Ty) {
+        self.enter(x.id, |v| visit::walk_ty(v, x));
+    }
+
+    fn visit_fn(&mut self, fk: FnKind<'ast>, fd: &'ast FnDecl, s: Span, _id: NodeId) {
+        for arg in &fd.inputs {
+            self.enter(arg.id, |_| {});
+        }
+        visit::walk_fn(self, fk, fd, s);
+    }
+
+    fn visit_struct_field(&mut self, x: &'ast StructField) {
+        self.enter(x.id, |v| visit::walk_struct_field(v, x));
+    }
+
+    fn visit_mac(&mut self, mac: &'ast Mac) {
+        visit::walk_mac(self, mac);
+    }
+}
+
+/// Compute an identifier for each of the nodes `ids` that depends only on the node's position in
+/// the structure of `krate`, not on its `NodeId`.  Unlike `NodeId`s, these identifiers are the
+/// same when the crate is parsed again, as long as the nodes before and above the target are
+/// unchanged.  Nodes not found in `krate` are left out of the result.
+pub fn stable_node_ids(krate: &Crate, ids: &HashSet<NodeId>) -> HashMap<NodeId, String> {
+    let mut v = PathVisitor {
+        targets: ids.clone(),
+        paths: HashMap::new(),
+        path: Vec::new(),
+        counts: vec![0],
+    };
+    krate.visit(&mut v);
+    if ids.contains(&CRATE_NODE_ID) {
+        v.paths.insert(CRATE_NODE_ID, vec![]);
+    }
+
+    v.paths
+        .into_iter()
+        .map(|(id, path)| {
+            let mut s = String::new();
+            for index in path {
+                s.push_str(&format!("/{}", index));
+            }
+            if s.is_empty() {
+                s.push('/');
+            }
+            (id, s)
+        })
+        .collect()
+}
+
 /// Enum of node kinds.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum NodeKind {
@@ -209,6 +367,22 @@ pub enum NodeKind {
 }
 
 impl NodeKind {
+    /// Every node kind, in the order they are listed in error messages.
+    pub const ALL: [NodeKind; 12] = [
+        NodeKind::Any,
+        NodeKind::ItemLike,
+        NodeKind::Item,
+        NodeKind::TraitItem,
+        NodeKind::ImplItem,
+        NodeKind::ForeignItem,
+        NodeKind::Stmt,
+        NodeKind::Expr,
+        NodeKind::Pat,
+        NodeKind::Ty,
+        NodeKind::Param,
+        NodeKind::Field,
+    ];
+
     /// Check if `self` contains kind `other`.  `other` is expected to be a specific node kind, not
     /// a category like `Any`.
     pub fn contains(self, other: NodeKind) -> bool {
@@ -243,9 +417,26 @@ impl NodeKind {
     }
 }
 
+/// Error returned when parsing a string that doesn't name a `NodeKind`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownNodeKind(pub String);
+
+impl fmt::Display for UnknownNodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown node kind `{}`; expected one of ", self.0)?;
+        for (i, kind) in NodeKind::ALL.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", kind.as_str())?;
+        }
+        Ok(())
+    }
+}
+
 impl FromStr for NodeKind {
-    type Err = ();
-    fn from_str(s: &str) -> Result<NodeKind, ()> {
+    type Err = UnknownNodeKind;
+    fn from_str(s: &str) -> Result<NodeKind, UnknownNodeKind> {
         let kind = match s {
             "any" => NodeKind::Any,
             "itemlike" => NodeKind::ItemLike,
@@ -262,19 +453,47 @@ impl FromStr for NodeKind {
             "arg" => NodeKind::Param,  // arg is an alias for param
             "field" => NodeKind::Field,
 
-            _ => return Err(()),
+            _ => return Err(UnknownNodeKind(s.to_owned())),
         };
         Ok(kind)
     }
 }
 
+/// Parse a comma-separated list of node kinds, such as `stmt,expr`.
+pub fn parse_node_kinds(s: &str) -> Result<Vec<NodeKind>, UnknownNodeKind> {
+    s.split(',').map(|k| NodeKind::from_str(k.trim())).collect()
+}
+
+/// Try `pick` with each of `kinds` in turn, and return the first node found along with the kind
+/// that found it.  Picking falls back to the crate itself for every kind, so the crate only counts
+/// as a match for the last kind.
+pub fn pick_first_kind<F>(kinds: &[NodeKind], mut pick: F) -> Option<(NodeKind, NodeInfo)>
+where
+    F: FnMut(NodeKind) -> Option<NodeInfo>,
+{
+    let mut last = None;
+    for &kind in kinds {
+        last = pick(kind).map(|info| (kind, info));
+        match last {
+            Some((_, ref info)) if info.id != CRATE_NODE_ID => break,
+            _ => {}
+        }
+    }
+    last
+}
+
 /// Select an AST node by its `BytePos` in the `SourceMap`.  Only nodes of the specified `kind` will
 /// be selected.
 pub fn pick_node(krate: &Crate, kind: NodeKind, pos: BytePos) -> Option<NodeInfo> {
+    pick_node_covering_span(krate, kind, Span::new(pos, pos, SyntaxContext::root()))
+}
+
+/// Select the smallest AST node of the specified `kind` whose span covers all of `target`.
+pub fn pick_node_covering_span(krate: &Crate, kind: NodeKind, target: Span) -> Option<NodeInfo> {
     let mut v = PickVisitor {
         node_info: None,
         kind,
-        target: Span::new(pos, pos, SyntaxContext::root()),
+        target,
     };
     krate.visit(&mut v);
 
@@ -291,15 +510,9 @@ pub fn pick_node(krate: &Crate, kind: NodeKind, pos: BytePos) -> Option<NodeInfo
     v.node_info
 }
 
-/// Select an AST node by its file, line, and column numbers.
-pub fn pick_node_at_loc(
-    krate: &Crate,
-    session: &Session,
-    kind: NodeKind,
-    file: &str,
-    line: u32,
-    col: u32,
-) -> Option<NodeInfo> {
+/// Convert a file, line, and column number into a `BytePos` in the `SourceMap`.  Lines are
+/// numbered from 1 and columns from 0.
+pub fn loc_to_pos(session: &Session, file: &str, line: u32, col: u32) -> BytePos {
     let fm = match session
         .source_map()
         .get_source_file(&FileName::Real(PathBuf::from(file)))
@@ -325,18 +538,103 @@ pub fn pick_node_at_loc(
 
     // TODO: This math is probably off when the line contains multibyte characters.  The
     // information to properly handle multibyte chars should be accessible through the `SourceFile`.
-    let pos = lo + BytePos(col);
+    lo + BytePos(col)
+}
 
+/// Select an AST node by its file, line, and column numbers.
+pub fn pick_node_at_loc(
+    krate: &Crate,
+    session: &Session,
+    kind: NodeKind,
+    file: &str,
+    line: u32,
+    col: u32,
+) -> Option<NodeInfo> {
+    let pos = loc_to_pos(session, file, line, col);
     pick_node(krate, kind, pos)
 }
 
+/// Select the smallest AST node covering the selection from `start` up to (but not including)
+/// `end`, both given as line and column numbers in `file`.
+pub fn pick_node_covering_loc_range(
+    krate: &Crate,
+    session: &Session,
+    kind: NodeKind,
+    file: &str,
+    start: (u32, u32),
+    end: (u32, u32),
+) -> Option<NodeInfo> {
+    let lo = loc_to_pos(session, file, start.0, start.1);
+    let hi = loc_to_pos(session, file, end.0, end.1);
+    if hi < lo {
+        panic!(
+            "selection end {}:{} comes before its start {}:{}",
+            end.0, end.1, start.0, start.1
+        );
+    }
+    pick_node_covering_span(krate, kind, Span::new(lo, hi, SyntaxContext::root()))
+}
+
+/// Find all named nodes of the specified `kind` whose identifier is `name`.  Only items, trait,
+/// impl, and foreign items, and struct fields have names, so other kinds never match.
+pub fn pick_nodes_by_name(krate: &Crate, kind: NodeKind, name: &str) -> Vec<NodeInfo> {
+    let mut v = NameVisitor {
+        nodes: Vec::new(),
+        kind,
+        name,
+    };
+    krate.visit(&mut v);
+    v.nodes
+}
+
+/// Select the single named node of the specified `kind` whose identifier is `name`.  Returns an
+/// error if there is no such node, or if there are several.
+pub fn pick_unique_node_by_name(
+    krate: &Crate,
+    kind: NodeKind,
+    name: &str,
+) -> Result<NodeInfo, String> {
+    let mut nodes = pick_nodes_by_name(krate, kind, name);
+    match nodes.len() {
+        0 => Err(format!("no {:?} node named `{}`", kind, name)),
+        1 => Ok(nodes.pop().unwrap()),
+        n => Err(format!(
+            "name `{}` is ambiguous: {} {:?} nodes match",
+            name, n, kind
+        )),
+    }
+}
+
+/// Find all call expressions whose callee resolves to a function named `name`, given either as
+/// the function's own name (`printf`) or as its full path (`libc::printf`).  Calls through
+/// function pointers have no callee to resolve, so they are skipped.  Requires the typeck
+/// phase.
+pub fn pick_calls_of(krate: &Crate, cx: &RefactorCtxt, name: &str) -> Vec<NodeInfo> {
+    let tcx = cx.ty_ctxt();
+    let mut nodes = Vec::new();
+    visit_nodes(krate, |e: &Expr| {
+        if let ExprKind::Call(..) = e.kind {
+            let def_id = match_or!([cx.opt_callee(e)] Some(x) => x; return);
+            let path = tcx.def_path_str(def_id);
+            if path == name || path.rsplit("::").next() == Some(name) {
+                nodes.push(NodeInfo {
+                    id: e.id,
+                    span: e.span,
+                });
+            }
+        }
+    });
+    nodes
+}
+
 /// # `pick_node` Command
 ///
 /// Test command - not intended for general use.
 ///
-/// Usage: `pick_node KIND FILE LINE COL`
+/// Usage: `pick_node KIND FILE LINE COL [END_LINE END_COL]`
 ///
-/// Find a node of kind `KIND` at location `FILE:LINE:COL`.
+/// Find a node of kind `KIND` at location `FILE:LINE:COL`, or the smallest one covering the
+/// range up to `END_LINE:END_COL` if an end is given.
 /// If successful, logs the node's ID and span at level `info`.
 pub fn pick_node_command(krate: &Crate, cx: &RefactorCtxt, args: &[String]) {
     let kind = NodeKind::from_str(&args[0]).unwrap();
@@ -344,7 +642,20 @@ pub fn pick_node_command(krate: &Crate, cx: &RefactorCtxt, args: &[String]) {
     let line = u32::from_str(&args[2]).unwrap();
     let col = u32::from_str(&args[3]).unwrap();
 
-    let result = pick_node_at_loc(krate, cx.session(), kind, file, line, col);
+    let result = if args.len() >= 6 {
+        let end_line = u32::from_str(&args[4]).unwrap();
+        let end_col = u32::from_str(&args[5]).unwrap();
+        pick_node_covering_loc_range(
+            krate,
+            cx.session(),
+            kind,
+            file,
+            (line, col),
+            (end_line, end_col),
+        )
+    } else {
+        pick_node_at_loc(krate, cx.session(), kind, file, line, col)
+    };
 
     if let Some(ref result) = result {
         let lo_loc = cx.session().source_map().lookup_char_pos(result.span.lo());
@@ -378,3 +689,213 @@ pub fn register_commands(reg: &mut Registry) {
         }))
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_manip::number_nodes::{number_nodes_with, NodeIdCounter};
+    use syntax::sess::ParseSess;
+    use syntax::source_map::FilePathMapping;
+
+    const SRC: &str = "fn f(a: i32, b: i32) -> i32 {
+    let x = (a +
+        b) * 2;
+    x
+}
+";
+
+    /// Parse `src` and pass the resulting crate to `f`.
+    fn with_crate<F, R>(src: &str, f: F) -> R
+    where
+        F: FnOnce(&ParseSess, &Crate) -> R,
+    {
+        syntax::with_default_globals(|| {
+            let sess = ParseSess::new(FilePathMapping::empty());
+            let krate = rustc_parse::parse_crate_from_source_str(
+                FileName::anon_source_code(src),
+                src.to_owned(),
+                &sess,
+            )
+            .unwrap_or_else(|mut e| {
+                e.emit();
+                panic!("failed to parse test source")
+            });
+            f(&sess, &krate)
+        })
+    }
+
+    /// Pick the node of `kind` covering `SRC[lo..hi]` and return its source text.
+    fn pick_text(kind: NodeKind, lo: usize, hi: usize) -> Option<String> {
+        with_crate(SRC, |sess, krate| {
+            let start = sess.source_map().files()[0].start_pos;
+            let target = Span::new(
+                start + BytePos(lo as u32),
+                start + BytePos(hi as u32),
+                SyntaxContext::root(),
+            );
+            pick_node_covering_span(krate, kind, target)
+                .map(|info| sess.source_map().span_to_snippet(info.span).unwrap())
+        })
+    }
+
+    /// Pick the node of `kind` named `name` in `src` and return its source text.
+    fn pick_named_text(src: &str, kind: NodeKind, name: &str) -> Result<String, String> {
+        with_crate(src, |sess, krate| {
+            pick_unique_node_by_name(krate, kind, name)
+                .map(|info| sess.source_map().span_to_snippet(info.span).unwrap())
+        })
+    }
+
+    #[test]
+    fn covering_multi_line_selection() {
+        // From `a` on the second line through `b` on the third
+        let lo = SRC.find("a +").unwrap();
+        let hi = SRC.find("b)").unwrap() + 1;
+        assert_eq!(
+            pick_text(NodeKind::Expr, lo, hi)
+                .as_ref()
+                .map(|s| s as &str),
+            Some("a +\n        b")
+        );
+
+        // Extending the selection past the parenthesis picks the enclosing expression
+        let hi = SRC.find("2;").unwrap() + 1;
+        assert_eq!(
+            pick_text(NodeKind::Expr, lo, hi)
+                .as_ref()
+                .map(|s| s as &str),
+            Some("(a +\n        b) * 2")
+        );
+        assert_eq!(
+            pick_text(NodeKind::Stmt, lo, hi)
+                .as_ref()
+                .map(|s| s as &str),
+            Some("let x = (a +\n        b) * 2;")
+        );
+    }
+
+    #[test]
+    fn empty_selection_picks_node_at_point() {
+        let pos = SRC.find("b)").unwrap();
+        assert_eq!(
+            pick_text(NodeKind::Expr, pos, pos)
+                .as_ref()
+                .map(|s| s as &str),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn crlf_coordinates_round_trip() {
+        // rustc normalizes CRLF to LF when loading a file, so columns never count the `\r` and
+        // line/column positions match what an editor reports for the original buffer.
+        let src = SRC.replace('\n', "\r\n");
+        with_crate(&src, |sess, krate| {
+            let fm = sess.source_map().files()[0].clone();
+            // `b` on the third line, as `loc_to_pos` would compute it
+            let (lo, _) = fm.line_bounds(2);
+            let pos = lo + BytePos(8);
+            let info = pick_node_covering_span(
+                krate,
+                NodeKind::Expr,
+                Span::new(pos, pos, SyntaxContext::root()),
+            )
+            .unwrap();
+            assert_eq!(sess.source_map().span_to_snippet(info.span).unwrap(), "b");
+
+            let loc = sess.source_map().lookup_char_pos(info.span.lo());
+            assert_eq!((loc.line, loc.col.0), (3, 8));
+        })
+    }
+
+    #[test]
+    fn first_matching_kind() {
+        let kinds = parse_node_kinds("expr, stmt").unwrap();
+        assert_eq!(kinds, vec![NodeKind::Expr, NodeKind::Stmt]);
+        assert!(parse_node_kinds("expr,bogus").is_err());
+
+        let pick_at = |pos: usize| {
+            with_crate(SRC, |sess, krate| {
+                let start = sess.source_map().files()[0].start_pos;
+                let pos = start + BytePos(pos as u32);
+                pick_first_kind(&kinds, |kind| pick_node(krate, kind, pos)).map(|(kind, info)| {
+                    (kind, sess.source_map().span_to_snippet(info.span).unwrap())
+                })
+            })
+        };
+
+        // No expression covers the `let` keyword, so the statement is picked instead
+        assert_eq!(
+            pick_at(SRC.find("let").unwrap()),
+            Some((NodeKind::Stmt, "let x = (a +\n        b) * 2;".to_owned()))
+        );
+        assert_eq!(
+            pick_at(SRC.find("b)").unwrap()),
+            Some((NodeKind::Expr, "b".to_owned()))
+        );
+    }
+
+    #[test]
+    fn stable_ids_survive_reparsing() {
+        let pos = SRC.find("b)").unwrap();
+        // Parse twice, numbering the nodes differently each time, as happens when the crate is
+        // reloaded after a rewrite
+        let ids = [0, 1000]
+            .iter()
+            .map(|&first_id| {
+                with_crate(SRC, |sess, krate| {
+                    let mut krate = krate.clone();
+                    number_nodes_with(&mut krate, &NodeIdCounter::new(first_id));
+                    let start = sess.source_map().files()[0].start_pos;
+                    let info =
+                        pick_node(&krate, NodeKind::Expr, start + BytePos(pos as u32)).unwrap();
+
+                    let mut targets = HashSet::new();
+                    targets.insert(info.id);
+                    let stable = stable_node_ids(&krate, &targets).remove(&info.id).unwrap();
+                    (info.id, stable)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        assert_ne!(ids[0].0, ids[1].0);
+        assert_eq!(ids[0].1, ids[1].1);
+    }
+
+    #[test]
+    fn pick_by_name() {
+        let src = "struct Point { x: i32 }\nfn origin() -> Point { Point { x: 0 } }\n";
+        assert_eq!(
+            pick_named_text(src, NodeKind::Item, "origin"),
+            Ok("fn origin() -> Point { Point { x: 0 } }".to_owned())
+        );
+        assert_eq!(
+            pick_named_text(src, NodeKind::Any, "Point"),
+            Ok("struct Point { x: i32 }".to_owned())
+        );
+        assert_eq!(
+            pick_named_text(src, NodeKind::Field, "x"),
+            Ok("x: i32".to_owned())
+        );
+        assert!(pick_named_text(src, NodeKind::Item, "x").is_err());
+    }
+
+    #[test]
+    fn pick_by_ambiguous_name() {
+        let src = "mod a { fn f() {} }\nmod b { fn f() {} }\n";
+        let err = pick_named_text(src, NodeKind::Item, "f").unwrap_err();
+        assert!(err.contains("ambiguous"), "{}", err);
+    }
+
+    #[test]
+    fn node_kind_names() {
+        for &kind in &NodeKind::ALL {
+            assert_eq!(NodeKind::from_str(kind.as_str()), Ok(kind));
+        }
+
+        let err = NodeKind::from_str("widget").unwrap_err().to_string();
+        assert!(err.contains("`widget`"), "{}", err);
+        assert!(err.contains("item, trait_item"), "{}", err);
+        assert!(err.contains("expr"), "{}", err);
+    }
+}
@@ -2,6 +2,8 @@
 use diff;
 use std::collections::{HashMap, VecDeque};
 use std::io;
+use std::path::Path;
+use std::slice;
 use syntax::source_map::{SourceFile, SourceMap};
 use syntax_pos::{BytePos, FileName};
 
@@ -9,6 +11,18 @@ use crate::file_io::FileIO;
 use crate::rewrite::cleanup::cleanup_rewrites;
 use crate::rewrite::{TextAdjust, TextRewrite};
 
+/// The Unicode byte order mark, which rustc strips from the start of files it loads.
+const BOM: char = '\u{feff}';
+
+/// A replacement of part of a file's original text.  Positions are line and column numbers in the
+/// original text, with lines numbered from 1 and columns (in characters) from 0.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FileEdit {
+    pub lo: (u32, u32),
+    pub hi: (u32, u32),
+    pub text: String,
+}
+
 /// Apply a sequence of rewrites to the source code, handling the results by passing the new text
 /// to `callback` along with the `SourceFile` describing the original source file.
 pub fn rewrite_files_with(cm: &SourceMap, rw: &TextRewrite, io: &dyn FileIO) -> io::Result<()> {
@@ -35,9 +49,9 @@ pub fn rewrite_files_with(cm: &SourceMap, rw: &TextRewrite, io: &dyn FileIO) ->
     }
 
     for (_, (rewrites, nodes, sf)) in by_file {
-        let path = match sf.name {
-            FileName::Real(ref path) => path,
-            _ => {
+        let path = match real_file_path(&sf.name) {
+            Some(path) => path,
+            None => {
                 warn!("can't rewrite virtual file {:?}", sf.name);
                 continue;
             }
@@ -50,7 +64,12 @@ pub fn rewrite_files_with(cm: &SourceMap, rw: &TextRewrite, io: &dyn FileIO) ->
         rewrite_range(cm, sf.start_pos, sf.end_pos, &rewrites, &mut |s| {
             buf.push_str(s)
         });
-        io.write_file(path, &buf)?;
+        // The rewritten text comes from the `SourceFile`, which lacks the original's byte order
+        // mark, if any.
+        if io.read_file(path).map_or(false, |s| s.starts_with(BOM)) {
+            buf.insert(0, BOM);
+        }
+        io.write_file_edits(path, &buf, &file_edits(cm, &rewrites))?;
     }
 
     io.end_rewrite(cm)?;
@@ -58,6 +77,45 @@ pub fn rewrite_files_with(cm: &SourceMap, rw: &TextRewrite, io: &dyn FileIO) ->
     Ok(())
 }
 
+/// The path of the file named `name`, or `None` if it is a virtual file, such as a macro
+/// expansion, with no counterpart on disk.
+pub fn real_file_path(name: &FileName) -> Option<&Path> {
+    match *name {
+        FileName::Real(ref path) => Some(path),
+        _ => None,
+    }
+}
+
+/// Check whether `name` refers to a real file, which can be rewritten and shown to the user.
+pub fn is_real_file(name: &FileName) -> bool {
+    real_file_path(name).is_some()
+}
+
+/// Describe each of `rewrites`, which must already be cleaned up, as a `FileEdit` of the text it
+/// replaces.
+fn file_edits(cm: &SourceMap, rewrites: &[TextRewrite]) -> Vec<FileEdit> {
+    rewrites
+        .iter()
+        .map(|rw| {
+            let mut text = String::new();
+            rewrite_range(
+                cm,
+                rw.old_span.lo(),
+                rw.old_span.hi(),
+                slice::from_ref(rw),
+                &mut |s| text.push_str(s),
+            );
+            let lo = cm.lookup_char_pos(rw.old_span.lo());
+            let hi = cm.lookup_char_pos(rw.old_span.hi());
+            FileEdit {
+                lo: (lo.line as u32, lo.col.0 as u32),
+                hi: (hi.line as u32, hi.col.0 as u32),
+                text,
+            }
+        })
+        .collect()
+}
+
 #[allow(dead_code)] // Helper function for debugging
 fn print_rewrite(rw: &TextRewrite, depth: usize) {
     for _ in 0..depth {
@@ -309,3 +367,94 @@ fn print_hunk(buf: &VecDeque<diff::Result<&str>>, l_start: usize, r_start: usize
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rewrite::TextAdjust;
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use syntax::source_map::{FilePathMapping, Span};
+    use syntax_pos::hygiene::SyntaxContext;
+
+    /// A `FileIO` serving a single file's original text and recording what is written.
+    struct OneFileIO {
+        text: String,
+        written: RefCell<Vec<(PathBuf, String)>>,
+    }
+
+    impl FileIO for OneFileIO {
+        fn read_file(&self, _path: &Path) -> io::Result<String> {
+            Ok(self.text.clone())
+        }
+
+        fn write_file(&self, path: &Path, s: &str) -> io::Result<()> {
+            self.written
+                .borrow_mut()
+                .push((path.to_owned(), s.to_owned()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn edits_cover_only_rewritten_text() {
+        syntax::with_default_globals(|| {
+            let cm = SourceMap::new(FilePathMapping::empty());
+            let old = cm.new_source_file(
+                FileName::Custom("old".to_owned()),
+                "fn f() {\n    1 + 2\n}\n".to_owned(),
+            );
+            let new = cm.new_source_file(FileName::Custom("new".to_owned()), "3".to_owned());
+
+            let lo = old.start_pos + BytePos(13);
+            let old_span = Span::new(lo, lo + BytePos(5), SyntaxContext::root());
+            let new_span = Span::new(new.start_pos, new.end_pos, SyntaxContext::root());
+            let rewrites = vec![TextRewrite::adjusted(
+                old_span,
+                new_span,
+                TextAdjust::Parenthesize,
+            )];
+
+            assert_eq!(
+                file_edits(&cm, &rewrites),
+                vec![FileEdit {
+                    lo: (2, 4),
+                    hi: (2, 9),
+                    text: "(3)".to_owned(),
+                }]
+            );
+        })
+    }
+
+    #[test]
+    fn rewrite_keeps_byte_order_mark() {
+        syntax::with_default_globals(|| {
+            let text = "\u{feff}fn f() {}\n";
+            let cm = SourceMap::new(FilePathMapping::empty());
+            let old = cm.new_source_file(
+                FileName::Real(PathBuf::from("/src/lib.rs")),
+                text.to_owned(),
+            );
+            let new = cm.new_source_file(FileName::Custom("new".to_owned()), "g".to_owned());
+
+            let lo = old.start_pos + BytePos(3);
+            let old_span = Span::new(lo, lo + BytePos(1), SyntaxContext::root());
+            let new_span = Span::new(new.start_pos, new.end_pos, SyntaxContext::root());
+            let mut rw = TextRewrite::new(old_span, old_span);
+            rw.rewrites.push(TextRewrite::new(old_span, new_span));
+
+            let io = OneFileIO {
+                text: text.to_owned(),
+                written: RefCell::new(Vec::new()),
+            };
+            rewrite_files_with(&cm, &rw, &io).unwrap();
+            assert_eq!(
+                io.written.into_inner(),
+                vec![(
+                    PathBuf::from("/src/lib.rs"),
+                    "\u{feff}fn g() {}\n".to_owned()
+                )]
+            );
+        })
+    }
+}
@@ -31,6 +31,10 @@ impl<T: Transform> Command for TransformCommand<T> {
             })
             .expect("Failed to run compiler");
     }
+
+    fn min_phase(&self) -> Option<Phase> {
+        Some(self.0.min_phase())
+    }
 }
 
 /// Wrap a `Transform` to produce a `Box<Command>`.
@@ -693,6 +693,10 @@ impl Command for TypeFixRules {
             }
         }).expect("Could not retype crate!");
     }
+
+    fn min_phase(&self) -> Option<Phase> {
+        Some(Phase::Phase3)
+    }
 }
 
 struct TypeFixRulesFolder<'a, 'tcx: 'a> {
@@ -804,6 +808,10 @@ impl Command for AutoRetype {
             });
         }).expect("Failed to run compiler");
     }
+
+    fn min_phase(&self) -> Option<Phase> {
+        Some(Phase::Phase3)
+    }
 }
 
 /// This folder changes type annotations according to the given mapping and
@@ -145,6 +145,10 @@ impl Command for TestTypeckLoop {
             }
         }).unwrap();
     }
+
+    fn min_phase(&self) -> Option<Phase> {
+        Some(Phase::Phase3)
+    }
 }
 
 
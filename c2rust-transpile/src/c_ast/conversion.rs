@@ -168,6 +168,7 @@ fn parse_attributes(attributes: Vec<Value>) -> IndexSet<Attribute> {
     let mut expect_section_value = false;
     let mut expect_alias_value = false;
     let mut expect_visibility_value = false;
+    let mut expect_cleanup_value = false;
 
     for attr in attributes.into_iter() {
         let attr_str = from_value::<String>(attr)
@@ -175,6 +176,7 @@ fn parse_attributes(attributes: Vec<Value>) -> IndexSet<Attribute> {
 
         match attr_str.as_str() {
             "alias" => expect_alias_value = true,
+            "cleanup" => expect_cleanup_value = true,
             "always_inline" => {
                 attrs.insert(Attribute::AlwaysInline);
             }
@@ -207,6 +209,11 @@ fn parse_attributes(attributes: Vec<Value>) -> IndexSet<Attribute> {
 
                 expect_visibility_value = false;
             }
+            s if expect_cleanup_value => {
+                attrs.insert(Attribute::Cleanup(s.into()));
+
+                expect_cleanup_value = false;
+            }
             _ => {}
         }
     }
@@ -738,6 +745,8 @@ impl ConversionContext {
                     let element_id = from_value(ty_node.extras[0].clone()).expect("element id");
                     let element = self.visit_type(element_id);
 
+                    // Clang has already evaluated the size expression, so sizes given by enum
+                    // constants or other constant expressions arrive here as a plain count
                     let count: usize = from_value(ty_node.extras[1].clone()).expect("count");
 
                     let element_ty = CTypeKind::ConstantArray(element, count);
@@ -1095,6 +1104,7 @@ impl ConversionContext {
                         .expect("Expected integer base value");
 
                     let base = match base {
+                        2 => IntBase::Bin,
                         8 => IntBase::Oct,
                         10 => IntBase::Dec,
                         16 => IntBase::Hex,
@@ -1123,11 +1133,18 @@ impl ConversionContext {
                 ASTEntryTag::TagCharacterLiteral if expected_ty & (EXPR | STMT) != 0 => {
                     let value = from_value(node.extras[0].clone())
                         .expect("Expected character literal value");
+                    let spelling = from_value::<String>(node.extras[1].clone())
+                        .expect("Expected character literal spelling");
 
                     let ty_old = node.type_id.expect("Expected expression to have type");
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let character_literal = CExprKind::Literal(ty, CLiteral::Character(value));
+                    let literal = if is_multi_character_literal(&spelling) {
+                        CLiteral::MultiCharacter(value, spelling)
+                    } else {
+                        CLiteral::Character(value)
+                    };
+                    let character_literal = CExprKind::Literal(ty, literal);
 
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, character_literal);
                 }
@@ -1145,6 +1162,17 @@ impl ConversionContext {
                     self.expr_possibly_as_stmt(expected_ty, new_id, node, floating_literal);
                 }
 
+                ASTEntryTag::TagImaginaryLiteral if expected_ty & (EXPR | STMT) != 0 => {
+                    let value = from_value(node.extras[0].clone())
+                        .expect("Expected imaginary literal value");
+                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty = self.visit_qualified_type(ty_old);
+
+                    let imaginary_literal = CExprKind::Literal(ty, CLiteral::Imaginary(value));
+
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, imaginary_literal);
+                }
+
                 ASTEntryTag::TagUnaryOperator if expected_ty & (EXPR | STMT) != 0 => {
                     let prefix = from_value(node.extras[1].clone())
                         .expect("Expected prefix information");
@@ -1929,7 +1957,28 @@ impl ConversionContext {
                         None
                     };
 
-                    let record = CDeclKind::Union { name, fields };
+                    let attrs = from_value::<Vec<Value>>(node.extras[2].clone())
+                        .expect("Expected attribute array on record");
+                    let is_packed = attrs.iter().any(|attr| {
+                        from_value::<String>(attr.clone())
+                            .expect("Records attributes should be strings")
+                            == "packed"
+                    });
+                    let manual_alignment =
+                        expect_opt_u64(&node.extras[3]).expect("Expected union alignment");
+                    let max_field_alignment =
+                        expect_opt_u64(&node.extras[4]).expect("Expected union field align");
+                    let platform_byte_size =
+                        from_value(node.extras[5].clone()).expect("Expected union size");
+
+                    let record = CDeclKind::Union {
+                        name,
+                        fields,
+                        is_packed,
+                        manual_alignment,
+                        max_field_alignment,
+                        platform_byte_size,
+                    };
 
                     self.add_decl(new_id, located(node, record));
                     self.processed_nodes.insert(new_id, RECORD_DECL);
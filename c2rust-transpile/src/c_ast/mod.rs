@@ -243,6 +243,10 @@ impl TypedAstContext {
         self.c_decls.iter_mut()
     }
 
+    pub fn iter_exprs(&self) -> std::collections::hash_map::Iter<CExprId, CExpr> {
+        self.c_exprs.iter()
+    }
+
     pub fn get_decl(&self, key: &CDeclId) -> Option<&CDecl> {
         self.c_decls.get(key)
     }
@@ -443,6 +447,39 @@ impl TypedAstContext {
         self.index(resolved_typ_id)
     }
 
+    /// Id for the next node added to a context built by hand. As with the ids clang hands out,
+    /// types, expressions and declarations never share one.
+    #[cfg(test)]
+    fn next_id(&self) -> u64 {
+        (self.c_types.len() + self.c_exprs.len() + self.c_decls.len()) as u64
+    }
+
+    /// Add a type without a source location, for tests that build a context by hand
+    #[cfg(test)]
+    pub fn add_type(&mut self, kind: CTypeKind) -> CTypeId {
+        let id = CTypeId(self.next_id());
+        self.c_types.insert(id, CType { loc: None, kind });
+        id
+    }
+
+    /// Add an expression without a source location, for tests that build a context by hand
+    #[cfg(test)]
+    pub fn add_expr(&mut self, kind: CExprKind) -> CExprId {
+        let id = CExprId(self.next_id());
+        self.c_exprs.insert(id, CExpr { loc: None, kind });
+        id
+    }
+
+    /// Add a top-level declaration without a source location, for tests that build a context
+    /// by hand
+    #[cfg(test)]
+    pub fn add_top_decl(&mut self, kind: CDeclKind) -> CDeclId {
+        let id = CDeclId(self.next_id());
+        self.c_decls.insert(id, CDecl { loc: None, kind });
+        self.c_decls_top.push(id);
+        id
+    }
+
     /// Pessimistically try to check if an expression has side effects. If it does, or we can't tell
     /// that it doesn't, return `false`.
     pub fn is_expr_pure(&self, expr: CExprId) -> bool {
@@ -607,6 +644,28 @@ impl TypedAstContext {
                                     to_walk.push(parent_id);
                                 }
                             }
+                            CDeclKind::Variable { ref attrs, .. } => {
+                                // A `cleanup` attribute names its function rather than referring
+                                // to it through a `DeclRef`, so keep that function as well.
+                                for attr in attrs {
+                                    let cleanup_fn = match attr {
+                                        Attribute::Cleanup(name) => name,
+                                        _ => continue,
+                                    };
+                                    for (&fn_id, decl) in &self.c_decls {
+                                        match decl.kind {
+                                            CDeclKind::Function { ref name, .. }
+                                                if name == cleanup_fn =>
+                                            {
+                                                if used.insert(fn_id) {
+                                                    to_walk.push(fn_id);
+                                                }
+                                            }
+                                            _ => {}
+                                        }
+                                    }
+                                }
+                            }
                             _ => {}
                         }
                     }
@@ -910,6 +969,10 @@ pub enum CDeclKind {
     Union {
         name: Option<String>,
         fields: Option<Vec<CFieldId>>,
+        is_packed: bool,
+        manual_alignment: Option<u64>,
+        max_field_alignment: Option<u64>,
+        platform_byte_size: u64,
     },
 
     // Field
@@ -1286,13 +1349,16 @@ pub enum IntBase {
     Dec,
     Hex,
     Oct,
+    Bin,
 }
 
 #[derive(Debug, Clone)]
 pub enum CLiteral {
     Integer(u64, IntBase), // value and base
     Character(u64),
+    MultiCharacter(u64, String), // value and source spelling, e.g. `'ABCD'`
     Floating(f64, String),
+    Imaginary(f64), // imaginary part of a `_Complex` value, e.g. `2.0i`
     String(Vec<u8>, u8), // Literal bytes and unit byte width
 }
 
@@ -1302,12 +1368,44 @@ impl CLiteral {
         match *self {
             CLiteral::Integer(x, _) => x != 0u64,
             CLiteral::Character(x) => x != 0u64,
+            CLiteral::MultiCharacter(x, _) => x != 0u64,
             CLiteral::Floating(x, _) => x != 0f64,
+            CLiteral::Imaginary(x) => x != 0f64,
             _ => true,
         }
     }
 }
 
+/// Whether the spelling of a character literal names more than one character, as in `'ABCD'`.
+/// Only plain (narrow) literals are considered, since those are the ones that clang packs into
+/// an implementation-defined `int`.
+pub fn is_multi_character_literal(spelling: &str) -> bool {
+    if !spelling.starts_with('\'') || !spelling.ends_with('\'') || spelling.len() < 2 {
+        return false;
+    }
+    let is_octal = |b: &u8| b'0' <= *b && *b <= b'7';
+
+    let mut units = 0;
+    let mut rest = spelling[1..spelling.len() - 1].as_bytes();
+    while let Some(&c) = rest.first() {
+        let len = match (c, rest.get(1)) {
+            (b'\\', Some(b'x')) => {
+                2 + rest[2..].iter().take_while(|b| b.is_ascii_hexdigit()).count()
+            }
+            (b'\\', Some(b)) if is_octal(b) => {
+                1 + rest[1..].iter().take(3).take_while(|b| is_octal(b)).count()
+            }
+            (b'\\', Some(b'u')) => 6,
+            (b'\\', Some(b'U')) => 10,
+            (b'\\', _) => 2,
+            _ => 1,
+        };
+        rest = &rest[len.min(rest.len())..];
+        units += 1;
+    }
+    units > 1
+}
+
 /// Represents a constant integer expression as used in a case expression
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ConstIntExpr {
@@ -1553,6 +1651,8 @@ pub enum Attribute {
     Alias(String),
     /// __attribute__((always_inline, __always_inline__))
     AlwaysInline,
+    /// __attribute__((cleanup(foo), __cleanup__(foo)))
+    Cleanup(String),
     /// __attribute__((cold, __cold__))
     Cold,
     /// __attribute__((gnu_inline, __gnu_inline__))
@@ -1657,6 +1757,13 @@ impl CTypeKind {
         }
     }
 
+    pub fn is_complex(&self) -> bool {
+        match *self {
+            CTypeKind::Complex(..) => true,
+            _ => false,
+        }
+    }
+
     /// Choose the smaller, simpler of the two types if they are cast-compatible.
     pub fn smaller_compatible_type(ty1: CTypeKind, ty2: CTypeKind) -> Option<CTypeKind> {
         match (&ty1, &ty2) {
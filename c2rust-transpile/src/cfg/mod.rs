@@ -993,6 +993,16 @@ impl DeclStmtInfo {
         }
     }
 
+    /// Information for a declaration that can't be moved to a larger scope, such as one whose
+    /// cleanup has to run at the end of its own scope.
+    pub fn unmovable(decl_and_assign: Vec<Stmt>) -> Self {
+        DeclStmtInfo {
+            decl: None,
+            assign: None,
+            decl_and_assign: Some(decl_and_assign),
+        }
+    }
+
     pub fn empty() -> Self {
         DeclStmtInfo {
             decl: Some(Vec::new()),
@@ -1022,7 +1032,7 @@ impl DeclStmtStore {
         ))?;
 
         let decl: Vec<Stmt> = decl.ok_or(format_err!(
-            "Declaration for {:?} has already been extracted",
+            "Declaration for {:?} has already been extracted or can't be moved",
             decl_id
         ))?;
 
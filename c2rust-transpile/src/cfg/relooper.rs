@@ -11,7 +11,7 @@ pub fn reloop(
     use_c_loop_info: bool,       // use the loop information in the CFG (slower, but better)
     use_c_multiple_info: bool,   // use the multiple information in the CFG (slower, but better)
     live_in: IndexSet<CDeclId>,  // declarations we assume are live going into this graph
-) -> (Vec<Stmt>, Vec<Structure<Stmt>>) {
+) -> Result<(Vec<Stmt>, Vec<Structure<Stmt>>), TranslationError> {
     let entries: IndexSet<Label> = vec![cfg.entries].into_iter().collect();
     let blocks = cfg
         .nodes
@@ -49,10 +49,10 @@ pub fn reloop(
     let lift_me = state.lifted;
 
     // These are the statements that emerge from these lifts
-    let lifted_stmts: Vec<Stmt> = lift_me
-        .iter()
-        .flat_map(|&decl: &CDeclId| store.extract_decl(decl).unwrap())
-        .collect();
+    let mut lifted_stmts: Vec<Stmt> = vec![];
+    for &decl in &lift_me {
+        lifted_stmts.append(&mut store.extract_decl(decl)?);
+    }
 
     // We map over the existing structure and flatten everything to `Stmt`
     let mut relooped: Vec<Structure<Stmt>> = relooped_with_decls
@@ -64,7 +64,7 @@ pub fn reloop(
         relooped = simplify_structure(relooped)
     }
 
-    (lifted_stmts, relooped)
+    Ok((lifted_stmts, relooped))
 }
 
 /// This is the state we close over while relooping. It accumulates information about which
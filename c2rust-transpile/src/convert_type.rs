@@ -2,7 +2,9 @@ use crate::c_ast::CDeclId;
 use crate::c_ast::*;
 use crate::renamer::*;
 use crate::diagnostics::TranslationError;
+use crate::ExternCrate;
 use c2rust_ast_builder::mk;
+use indexmap::IndexSet;
 use std::collections::{HashMap, HashSet};
 use std::ops::Index;
 use syntax::ast::*;
@@ -20,7 +22,10 @@ pub struct TypeConverter {
     fields: HashMap<CDeclId, Renamer<FieldKey>>,
     suffix_names: HashMap<(CDeclId, &'static str), String>,
     features: HashSet<&'static str>,
+    extern_crates: IndexSet<ExternCrate>,
     emit_no_std: bool,
+    /// Names of the constants that array types of each of these lengths use as their length.
+    array_lengths: HashMap<usize, String>,
 }
 
 pub const RESERVED_NAMES: [&str; 103] = [
@@ -140,7 +145,23 @@ impl TypeConverter {
             fields: HashMap::new(),
             suffix_names: HashMap::new(),
             features: HashSet::new(),
+            extern_crates: IndexSet::new(),
             emit_no_std,
+            array_lengths: HashMap::new(),
+        }
+    }
+
+    /// Use the constant `name` as the length of arrays of `len` elements.
+    pub fn name_array_length(&mut self, len: usize, name: String) {
+        self.array_lengths.insert(len, name);
+    }
+
+    /// Build the length of an array of `len` elements, which is a named constant if one was
+    /// given for this length and a literal otherwise.
+    pub fn array_length_expr(&self, len: usize) -> P<Expr> {
+        match self.array_lengths.get(&len) {
+            Some(name) => mk().path_expr(vec![name]),
+            None => mk().lit_expr(mk().int_lit(len as u128, LitIntType::Unsuffixed)),
         }
     }
 
@@ -148,6 +169,11 @@ impl TypeConverter {
         &self.features
     }
 
+    /// Crates that the converted types refer to
+    pub fn crates_used(&self) -> &IndexSet<ExternCrate> {
+        &self.extern_crates
+    }
+
     pub fn declare_decl_name(&mut self, decl_id: CDeclId, name: &str) -> String {
         self.renamer
             .insert(decl_id, name)
@@ -337,6 +363,16 @@ impl TypeConverter {
 
             CTypeKind::Pointer(qtype) => self.convert_pointer(ctxt, qtype),
 
+            CTypeKind::Complex(element) => {
+                self.extern_crates.insert(ExternCrate::NumComplex);
+                let element = self.convert(ctxt, element)?;
+                let args = mk().angle_bracketed_args(vec![element]);
+                Ok(mk().path_ty(vec![
+                    mk().path_segment("num_complex"),
+                    mk().path_segment_with_args("Complex", args),
+                ]))
+            }
+
             CTypeKind::Elaborated(ref ctype) => self.convert(ctxt, *ctype),
             CTypeKind::Decayed(ref ctype) => self.convert(ctxt, *ctype),
             CTypeKind::Paren(ref ctype) => self.convert(ctxt, *ctype),
@@ -365,10 +401,7 @@ impl TypeConverter {
 
             CTypeKind::ConstantArray(element, count) => {
                 let ty = self.convert(ctxt, element)?;
-                Ok(mk().array_ty(
-                    ty,
-                    mk().lit_expr(mk().int_lit(count as u128, LitIntType::Unsuffixed)),
-                ))
+                Ok(mk().array_ty(ty, self.array_length_expr(count)))
             }
 
             CTypeKind::IncompleteArray(element) => {
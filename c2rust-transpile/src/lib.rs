@@ -1,9 +1,9 @@
 #![feature(rustc_private)]
 #![feature(label_break_value)]
 #![feature(box_patterns)]
+#![cfg_attr(test, feature(test))]
 
 extern crate colored;
-extern crate dtoa;
 extern crate syntax;
 extern crate syntax_pos;
 #[macro_use]
@@ -58,7 +58,7 @@ use c2rust_ast_exporter as ast_exporter;
 use crate::build_files::{emit_build_files, get_build_dir, CrateConfig};
 use crate::compile_cmds::get_compile_commands;
 use crate::convert_type::RESERVED_NAMES;
-pub use crate::translator::ReplaceMode;
+pub use crate::translator::{LiteralBaseMode, LiteralProvenance, NarrowingLiteralMode, ReplaceMode};
 use std::prelude::v1::Vec;
 
 type PragmaVec = Vec<(&'static str, Vec<&'static str>)>;
@@ -96,6 +96,7 @@ pub struct TranspilerConfig {
     pub panic_on_translator_failure: bool,
     pub emit_modules: bool,
     pub fail_on_error: bool,
+    pub stub_failed_decls: bool,
     pub replace_unsupported_decls: ReplaceMode,
     pub translate_valist: bool,
     pub overwrite_existing: bool,
@@ -105,6 +106,17 @@ pub struct TranspilerConfig {
     pub emit_no_std: bool,
     pub output_dir: Option<PathBuf>,
     pub translate_const_macros: bool,
+    pub emit_char_casts: bool,
+    pub literal_base: LiteralBaseMode,
+    pub group_digits: bool,
+    pub wrapping_literals: bool,
+    pub narrowing_literals: NarrowingLiteralMode,
+    pub source_location_docs: bool,
+    pub prefer_byte_strings: bool,
+    pub named_array_lengths: bool,
+    pub emit_provenance: bool,
+    pub safe_string_literals: bool,
+    pub checked_enum_values: bool,
     pub disable_refactoring: bool,
     pub log_level: log::LevelFilter,
 
@@ -130,12 +142,72 @@ impl TranspilerConfig {
     }
 }
 
+#[cfg(test)]
+impl TranspilerConfig {
+    /// Configuration with every optional behaviour turned off, for tests that drive the
+    /// translator directly
+    pub(crate) fn for_tests() -> Self {
+        TranspilerConfig {
+            dump_untyped_context: false,
+            dump_typed_context: false,
+            pretty_typed_context: false,
+            dump_function_cfgs: false,
+            json_function_cfgs: false,
+            dump_cfg_liveness: false,
+            dump_structures: false,
+            verbose: false,
+            debug_ast_exporter: false,
+            incremental_relooper: true,
+            fail_on_multiple: false,
+            filter: None,
+            debug_relooper_labels: false,
+            cross_checks: false,
+            cross_check_backend: String::new(),
+            cross_check_configs: vec![],
+            prefix_function_names: None,
+            translate_asm: false,
+            use_c_loop_info: true,
+            use_c_multiple_info: true,
+            simplify_structures: true,
+            panic_on_translator_failure: false,
+            emit_modules: false,
+            fail_on_error: false,
+            stub_failed_decls: false,
+            replace_unsupported_decls: ReplaceMode::None,
+            translate_valist: false,
+            overwrite_existing: false,
+            reduce_type_annotations: false,
+            reorganize_definitions: false,
+            enabled_warnings: HashSet::new(),
+            emit_no_std: false,
+            output_dir: None,
+            translate_const_macros: false,
+            emit_char_casts: false,
+            literal_base: LiteralBaseMode::PreserveSource,
+            group_digits: false,
+            wrapping_literals: false,
+            narrowing_literals: NarrowingLiteralMode::Truncate,
+            source_location_docs: false,
+            prefer_byte_strings: false,
+            named_array_lengths: false,
+            emit_provenance: false,
+            safe_string_literals: false,
+            checked_enum_values: false,
+            disable_refactoring: false,
+            log_level: log::LevelFilter::Warn,
+            emit_build_files: false,
+            binaries: vec![],
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum ExternCrate {
     C2RustBitfields,
     C2RustAsmCasts,
     F128,
     NumTraits,
+    NumComplex,
     Memoffset,
     Libc,
 }
@@ -166,6 +238,7 @@ impl From<ExternCrate> for ExternCrateDetails {
             ExternCrate::C2RustAsmCasts => Self::new("c2rust-asm-casts", "0.1", true),
             ExternCrate::F128 => Self::new("f128", "0.2", false),
             ExternCrate::NumTraits => Self::new("num-traits", "0.2", true),
+            ExternCrate::NumComplex => Self::new("num-complex", "0.2", false),
             ExternCrate::Memoffset => Self::new("memoffset", "0.5", true),
             ExternCrate::Libc => Self::new("libc", "0.2", false),
         }
@@ -508,7 +581,7 @@ fn transpile_single(
     }
 
     // Perform the translation
-    let (translated_string, pragmas, crates) =
+    let (translated_string, pragmas, crates, provenance) =
         translator::translate(typed_context, &tcfg, input_path);
 
     let mut file = match File::create(&output_path) {
@@ -521,6 +594,13 @@ fn transpile_single(
         Err(e) => panic!("Unable to write translation to file {}: {}", output_path.display(), e),
     };
 
+    if let Some(provenance) = provenance {
+        let provenance_path = output_path.with_extension("provenance.json");
+        if let Err(e) = fs::write(&provenance_path, translator::provenance_json(&provenance)) {
+            panic!("Unable to write provenance to file {}: {}", provenance_path.display(), e);
+        }
+    }
+
     Ok((output_path, pragmas, crates))
 }
 
@@ -27,6 +27,7 @@
 //! (see pprust.rs for current details):
 //!
 //! Before the following AST elements:
+//! - Expr
 //! - Lit
 //! - Attribute
 //! - Ty
@@ -103,12 +104,15 @@ impl CommentStore {
         // node to which they are _not_ related.
         self.current_position += 1;
 
-        // The position of isolated comments have to be LESS than the span of
-        // the AST node it annotates.
+        // The position of isolated and mixed comments have to be LESS than the
+        // span of the AST node it annotates.
         for cmmt in &mut new_comments {
-            if let comments::CommentStyle::Isolated = cmmt.style {
-                cmmt.pos = BytePos(self.current_position);
-                self.current_position += 1;
+            match cmmt.style {
+                comments::CommentStyle::Isolated | comments::CommentStyle::Mixed => {
+                    cmmt.pos = BytePos(self.current_position);
+                    self.current_position += 1;
+                }
+                _ => {}
             }
         }
 
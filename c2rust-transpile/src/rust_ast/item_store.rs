@@ -100,6 +100,19 @@ impl ItemStore {
         self.uses.get_mut(path).insert_with_attr(ident, attrs)
     }
 
+    /// Move the items and imports of `other` into this store, after the ones it already has.
+    pub fn append(&mut self, mut other: ItemStore) {
+        self.items.append(&mut other.items);
+        self.foreign_items.append(&mut other.foreign_items);
+        for (path, imports) in other.uses.0 {
+            let existing = self.uses.get_mut(path);
+            existing.leaves.extend(imports.leaves);
+            if imports.attrs.is_some() {
+                existing.attrs = imports.attrs;
+            }
+        }
+    }
+
     pub fn drain(&mut self) -> (Vec<P<Item>>, Vec<ForeignItem>, PathedMultiImports) {
         let mut items = Vec::new();
         let mut foreign_items = Vec::new();
@@ -73,7 +73,11 @@ impl<'c> Translation<'c> {
                     tokens.push(TokenTree::token(token::Comma, DUMMY_SP))
                 }
 
-                let mut result = self.convert_expr(ctx.used(), expression)?;
+                // Strings passed to the assembly may only be referenced from it
+                let escape = self.string_literals_escape.replace(true);
+                let result = self.convert_expr(ctx.used(), expression);
+                self.string_literals_escape.set(escape);
+                let mut result = result?;
                 stmts.append(result.stmts_mut());
 
                 let mut result = result.into_value();
@@ -195,3 +199,60 @@ impl<'c> Translation<'c> {
         Ok(stmts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asm_only_string_literals_are_used() {
+        syntax::with_default_globals(|| {
+            let mut ast = TypedAstContext::new(&[]);
+            let char_ty = ast.add_type(CTypeKind::Char);
+            let const_char = CQualTypeId {
+                qualifiers: Qualifiers { is_const: true, ..Qualifiers::default() },
+                ctype: char_ty,
+            };
+            let string = CQualTypeId::new(ast.add_type(CTypeKind::ConstantArray(char_ty, 6)));
+            let pointer = CQualTypeId::new(ast.add_type(CTypeKind::Pointer(const_char)));
+            let hello = CLiteral::String(b"hello".to_vec(), 1);
+            let hello = ast.add_expr(CExprKind::Literal(string, hello));
+            let decayed = ast.add_expr(CExprKind::ImplicitCast(
+                pointer,
+                hello,
+                CastKind::ArrayToPointerDecay,
+                None,
+                LRValue::RValue,
+            ));
+
+            let mut tcfg = crate::TranspilerConfig::for_tests();
+            tcfg.translate_asm = true;
+            let t = Translation::new(ast, &tcfg, std::path::Path::new("test.c"));
+            let ctx = ExprContext {
+                used: true,
+                is_static: false,
+                is_const: false,
+                decay_ref: DecayRef::Default,
+                is_bitfield_write: false,
+                needs_address: false,
+                expecting_valistimpl: false,
+                ternary_needs_parens: false,
+                expanding_macro: None,
+            };
+            let inputs = [AsmOperand { constraints: "r".to_string(), expression: decayed }];
+            let stmts = t.convert_asm(ctx, DUMMY_SP, true, "", &inputs, &[], &[]).unwrap();
+
+            // The literal is only referenced from the assembly, so its static must be kept
+            let (items, _, _) = t.items.borrow_mut()[&t.main_file].drain();
+            let items: Vec<String> = items.iter().map(|item| pprust::item_to_string(item)).collect();
+            assert_eq!(items, vec!["#[used]\nstatic STRING_LITERAL: [u8; 6] = *b\"hello\\x00\";"]);
+            let stmts: Vec<String> = stmts.iter().map(pprust::stmt_to_string).collect();
+            assert!(stmts.concat().contains("STRING_LITERAL.as_ptr()"), "{:?}", stmts);
+
+            // Ordinary uses of the same literal don't get the `#[used]` static
+            t.convert_expr(ctx, decayed).unwrap();
+            let (items, _, _) = t.items.borrow_mut()[&t.main_file].drain();
+            assert!(items.is_empty());
+        });
+    }
+}
@@ -3,90 +3,405 @@
 //! These include integer, floating, array, struct, union, enum literals.
 
 use super::*;
+use std::fmt;
 use std::iter;
+use std::str;
 
 impl<'c> Translation<'c> {
-    /// Generate an integer literal corresponding to the given type, value, and base.
-    pub fn mk_int_lit(&self, ty: CQualTypeId, val: u64, base: IntBase) -> Result<P<Expr>, TranslationError> {
-        let lit = match base {
-            IntBase::Dec => mk().int_lit(val.into(), LitIntType::Unsuffixed),
-            IntBase::Hex => mk().float_unsuffixed_lit(format!("0x{:x}", val)),
-            IntBase::Oct => mk().float_unsuffixed_lit(format!("0o{:o}", val)),
+    /// Generate an integer literal corresponding to the given type, value, and base. The value
+    /// may use all 128 bits when the type is `__int128` or `unsigned __int128`.
+    pub fn mk_int_lit(&self, ty: CQualTypeId, val: u128, base: IntBase) -> Result<P<Expr>, TranslationError> {
+        // When the target type has a fixed-width Rust equivalent we can suffix the literal
+        // directly rather than wrapping it in a cast.
+        let suffix = self.int_lit_suffix(ty.ctype);
+        let lit_ty = suffix.unwrap_or(LitIntType::Unsuffixed);
+
+        let resolved_ty = &self.ast_context.resolve_type(ty.ctype).kind;
+        let bits = if *resolved_ty == CTypeKind::Int128 { 128 } else { 64 };
+        let wrapped = if resolved_ty.is_signed_integral_type() {
+            negative_wrapped_magnitude(val, bits)
+        } else {
+            None
+        };
+        let expr = if let Some(magnitude) = wrapped {
+            // The value doesn't fit in the signed target type, so emit the two's-complement
+            // value C ends up with rather than a literal that overflows in Rust.
+            let comment = format!("// {:#x} wrapped to a signed value", val);
+            let span = self
+                .comment_store
+                .borrow_mut()
+                .add_comments(&[comment])
+                .map(pos_to_span)
+                .unwrap_or(DUMMY_SP);
+            mk().span(span).unary_expr(
+                ast::UnOp::Neg,
+                mk().lit_expr(mk().int_lit(magnitude, lit_ty)),
+            )
+        } else {
+            self.int_lit_expr(val, base, lit_ty)
         };
 
+        if suffix.is_some() {
+            return Ok(expr);
+        }
+
         let target_ty = self.convert_type(ty.ctype)?;
-        Ok(mk().cast_expr(mk().lit_expr(lit), target_ty))
+        Ok(mk().cast_expr(expr, target_ty))
+    }
+
+    /// Build the integer literal `val` of type `lit_ty`, spelled in `base` as `--literal-base`
+    /// adjusts it and with its digits grouped if `--group-digits` is set.
+    fn int_lit_expr(&self, val: u128, base: IntBase, lit_ty: LitIntType) -> P<Expr> {
+        let base = self.tcfg.literal_base.apply(base);
+        let lit = match int_lit_text(val, base, self.tcfg.group_digits) {
+            Some(text) => mk().int_lit_with_text(val, text, lit_ty),
+            None => mk().int_lit(val, lit_ty),
+        };
+        mk().lit_expr(lit)
+    }
+
+    /// Generate an integer literal like `mk_int_lit`, wrapped in `Wrapping` so that arithmetic
+    /// on it with the usual operators wraps on overflow, e.g. `::std::num::Wrapping(1u32)`.
+    pub fn mk_wrapping_int_lit(
+        &self,
+        ty: CQualTypeId,
+        val: u128,
+        base: IntBase,
+    ) -> Result<P<Expr>, TranslationError> {
+        Ok(self.wrapping_expr(self.mk_int_lit(ty, val, base)?))
+    }
+
+    /// Wrap an integer expression in `Wrapping`.
+    pub fn wrapping_expr(&self, expr: P<Expr>) -> P<Expr> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let wrapping = mk().path_expr(vec!["", std_or_core, "num", "Wrapping"]);
+        mk().call_expr(wrapping, vec![expr])
+    }
+
+    /// If `expr` is an integer literal, possibly in parentheses or behind implicit integral
+    /// casts, get its value and base.
+    pub fn int_literal_operand(&self, expr: CExprId) -> Option<(u128, IntBase)> {
+        match self.ast_context[expr].kind {
+            CExprKind::Literal(_, CLiteral::Integer(val, base)) => Some((val.into(), base)),
+            CExprKind::Paren(_, e)
+            | CExprKind::ImplicitCast(_, e, CastKind::IntegralCast, _, _) => {
+                self.int_literal_operand(e)
+            }
+            _ => None,
+        }
+    }
+
+    /// If `expr` is an integer constant that doesn't fit in the integral type `target`, get its
+    /// value along with the value a cast to `target` truncates it to. Platform-dependent types
+    /// like `long` are never reported, since their width isn't known when translating, and
+    /// neither is `char`, whose signedness isn't.
+    pub fn narrowed_constant(&self, expr: CExprId, target: CTypeId) -> Option<(i128, i128)> {
+        let suffix = self.int_lit_suffix(self.ast_context.resolve_type_id(target))?;
+        let (bits, signed) = int_lit_layout(suffix);
+        let val = self.integer_constant(expr)?;
+        truncated_constant(val, bits, signed).map(|wrapped| (val, wrapped))
+    }
+
+    /// Convert a cast of the integer constant `val` to `target_ty`, which truncates it to
+    /// `wrapped`, as `--narrowing-literals` asks: as the cast `expr` with a comment noting the
+    /// truncation, or in strict mode as a `compile_error!`.
+    pub fn narrowing_literal_cast(
+        &self,
+        val: i128,
+        wrapped: i128,
+        expr: P<Expr>,
+        target_ty: P<Ty>,
+    ) -> P<Expr> {
+        let ty_name = pprust::ty_to_string(&target_ty);
+        if self.tcfg.narrowing_literals == NarrowingLiteralMode::Strict {
+            return narrowing_literal_error(val, &ty_name);
+        }
+        let comment = format!("// {} truncated to {} in {}", val, wrapped, ty_name);
+        let span = self
+            .comment_store
+            .borrow_mut()
+            .add_comments(&[comment])
+            .map(pos_to_span)
+            .unwrap_or(DUMMY_SP);
+        mk().span(span).cast_expr(expr, target_ty)
+    }
+
+    /// If `expr` is the integer literal 0 or 1, possibly in parentheses, return its value as a
+    /// `bool`. Other integers stay as explicit `!= 0` comparisons when converted to `bool`.
+    pub fn bool_literal_value(&self, expr: CExprId) -> Option<bool> {
+        match self.ast_context[expr].kind {
+            CExprKind::Literal(_, CLiteral::Integer(0, _)) => Some(false),
+            CExprKind::Literal(_, CLiteral::Integer(1, _)) => Some(true),
+            CExprKind::Paren(_, e) => self.bool_literal_value(e),
+            _ => None,
+        }
+    }
+
+    /// Find the literal suffix for an integer type whose width is the same on every target we
+    /// support, e.g. `i32` for `int`. Typedefs, enums, and platform-dependent types like `long`
+    /// and `char` have no suffix and must be reached through a cast.
+    fn int_lit_suffix(&self, ty: CTypeId) -> Option<LitIntType> {
+        match self.ast_context[ty].kind {
+            CTypeKind::SChar => Some(LitIntType::Signed(IntTy::I8)),
+            CTypeKind::Short => Some(LitIntType::Signed(IntTy::I16)),
+            CTypeKind::Int => Some(LitIntType::Signed(IntTy::I32)),
+            CTypeKind::LongLong => Some(LitIntType::Signed(IntTy::I64)),
+            CTypeKind::Int128 => Some(LitIntType::Signed(IntTy::I128)),
+            CTypeKind::UChar => Some(LitIntType::Unsigned(UintTy::U8)),
+            CTypeKind::UShort => Some(LitIntType::Unsigned(UintTy::U16)),
+            CTypeKind::UInt => Some(LitIntType::Unsigned(UintTy::U32)),
+            CTypeKind::ULongLong => Some(LitIntType::Unsigned(UintTy::U64)),
+            CTypeKind::UInt128 => Some(LitIntType::Unsigned(UintTy::U128)),
+            _ => None,
+        }
     }
 
     /// Given an integer value this attempts to either generate the corresponding enum
-    /// variant directly, otherwise it transmutes a number to the enum type.
-    pub fn enum_for_i64(&self, enum_type_id: CTypeId, value: i64) -> P<Expr> {
+    /// variant directly, otherwise it casts a number to the enum type.
+    pub fn enum_for_i64(
+        &self,
+        enum_type_id: CTypeId,
+        value: i64,
+    ) -> Result<P<Expr>, TranslationError> {
         let def_id = match self.ast_context.resolve_type(enum_type_id).kind {
             CTypeKind::Enum(def_id) => def_id,
-            _ => panic!("{:?} does not point to an `enum` type"),
+            ref t => return Err(format_err!("{:?} does not point to an `enum` type", t).into()),
         };
 
-        let (variants, underlying_type_id) = match self.ast_context[def_id].kind {
-            CDeclKind::Enum {
-                ref variants,
-                integral_type,
-                ..
-            } => (variants, integral_type),
-            _ => panic!("{:?} does not point to an `enum` declaration"),
+        let underlying_type_id = match self.ast_context[def_id].kind {
+            CDeclKind::Enum { integral_type, .. } => integral_type,
+            _ => {
+                return Err(format_err!("{:?} does not point to an `enum` declaration", def_id).into())
+            }
         };
 
-        for &variant_id in variants {
-            match self.ast_context[variant_id].kind {
-                CDeclKind::EnumConstant { value: v, .. } => {
-                    if v == ConstIntExpr::I(value) || v == ConstIntExpr::U(value as u64) {
-                        let name = self.renamer.borrow().get(&variant_id).unwrap();
+        if let Some(expr) = self.enum_variants_for_i64(def_id, value)? {
+            return Ok(expr);
+        }
 
-                        // Import the enum variant if needed
-                        if let Some(cur_file) = *self.cur_file.borrow() {
-                            self.add_import(cur_file, variant_id, &name);
-                        }
-                        return mk().path_expr(vec![name]);
-                    }
-                }
-                _ => panic!("{:?} does not point to an enum variant", variant_id),
+        let underlying_type_id = underlying_type_id.ok_or_else(|| {
+            format_err!(
+                "Attempt to construct value {} of forward declared enum {:?}",
+                value,
+                def_id
+            )
+        })?;
+        let underlying = &self.ast_context.resolve_type(underlying_type_id.ctype).kind;
+        let value = enum_fallback_value(underlying, value);
+
+        let target_ty = self.convert_type(enum_type_id)?;
+
+        Ok(mk().cast_expr(value, target_ty))
+    }
+
+    /// Like `enum_for_i64`, but for `--checked-enum-values`: a value the variants of the enum
+    /// `def_id` can't make up goes through its generated conversion function, which panics on
+    /// it, rather than being cast.
+    pub fn checked_enum_for_i64(
+        &self,
+        def_id: CEnumId,
+        value: i64,
+    ) -> Result<P<Expr>, TranslationError> {
+        if let Some(expr) = self.enum_variants_for_i64(def_id, value)? {
+            return Ok(expr);
+        }
+        let from_i64 = self.enum_from_i64_fn(def_id)?;
+        Ok(mk().call_expr(mk().path_expr(vec![from_i64]), vec![signed_int_expr(value)]))
+    }
+
+    /// Build `value` out of the variants of the enum `def_id`, if it can be: as the variant with
+    /// that value, or as several variants combined like bit flags.
+    fn enum_variants_for_i64(
+        &self,
+        def_id: CEnumId,
+        value: i64,
+    ) -> Result<Option<P<Expr>>, TranslationError> {
+        self.index_enum_variants(def_id)?;
+        let enum_variants = self.enum_variants.borrow();
+        let variants = &enum_variants[&def_id];
+
+        if let Some(variant_id) = variants.get(value) {
+            return self.enum_variant_expr(variant_id).map(Some);
+        }
+
+        // Enums are often used as bit flags, so build a value which combines several variants,
+        // e.g. `FLAG_A | FLAG_B`, out of those variants rather than from a bare number. Enum
+        // types are translated to integer types, so the variants can be or-ed directly.
+        if let Some(flags) = enum_flag_variants(&variants.in_order, value) {
+            let mut expr = self.enum_variant_expr(flags[0])?;
+            for &flag in &flags[1..] {
+                let flag = self.enum_variant_expr(flag)?;
+                expr = mk().binary_expr(BinOpKind::BitOr, expr, flag);
             }
+            return Ok(Some(expr));
+        }
+
+        Ok(None)
+    }
+
+    /// Name of the function `--checked-enum-values` converts integers to the enum `def_id` with,
+    /// declaring it in the current file on first use. It matches its `i64` argument against the
+    /// values of the enum's variants and panics on anything else.
+    pub fn enum_from_i64_fn(&self, def_id: CEnumId) -> Result<String, TranslationError> {
+        let key = (self.cur_file(), def_id);
+        if let Some(name) = self.enum_from_i64_fns.borrow().get(&key) {
+            return Ok(name.clone());
         }
 
-        let underlying_type_id =
-            underlying_type_id.expect("Attempt to construct value of forward declared enum");
-        let value = match self.ast_context.resolve_type(underlying_type_id.ctype).kind {
-            CTypeKind::UInt => {
-                mk().lit_expr(mk().int_lit((value as u32) as u128, LitIntType::Unsuffixed))
+        let enum_name = self
+            .type_converter
+            .borrow()
+            .resolve_decl_name(def_id)
+            .ok_or_else(|| format_err!("Enum {:?} has no name", def_id))?;
+        if let Some(cur_file) = *self.cur_file.borrow() {
+            self.add_import(cur_file, def_id, &enum_name);
+        }
+
+        self.index_enum_variants(def_id)?;
+        let variants = self.enum_variants.borrow()[&def_id].in_order.clone();
+        let mut values = HashSet::new();
+        let mut arms = vec![];
+        for (variant_id, value) in variants {
+            // Later variants sharing a value would be unreachable patterns
+            if values.insert(value) {
+                let variant = self.enum_variant_expr(variant_id)?;
+                arms.push(mk().arm(mk().lit_pat(signed_int_expr(value)), None, variant));
             }
-            CTypeKind::ULong => {
-                mk().lit_expr(mk().int_lit((value as u64) as u128, LitIntType::Unsuffixed))
+        }
+        let msg = format!("{{}} is not a value of enum {}", enum_name);
+        arms.push(mk().arm(mk().wild_pat(), None, mk().mac_expr(panic_mac(&msg, "value"))));
+
+        let name = self
+            .renamer
+            .borrow_mut()
+            .pick_name_root(&format!("{}_from_i64", enum_name));
+        let decl = mk().fn_decl(
+            vec![mk().arg(mk().path_ty(vec!["i64"]), mk().ident_pat("value"))],
+            FunctionRetTy::Ty(mk().path_ty(vec![enum_name])),
+        );
+        let body = mk().block(vec![mk().expr_stmt(mk().match_expr(mk().ident_expr("value"), arms))]);
+        let item = mk().pub_().fn_item(&name, decl, body);
+        self.with_cur_file_item_store(|item_store| item_store.add_item(item));
+
+        self.enum_from_i64_fns.borrow_mut().insert(key, name.clone());
+        Ok(name)
+    }
+
+    /// Index the variants of the enum `def_id` by their values, unless that's already done.
+    fn index_enum_variants(&self, def_id: CEnumId) -> Result<(), TranslationError> {
+        if !self.enum_variants.borrow().contains_key(&def_id) {
+            let variants = self.collect_enum_variants(def_id)?;
+            self.enum_variants.borrow_mut().insert(def_id, variants);
+        }
+        Ok(())
+    }
+
+    /// Index the variants of an enum by their values.
+    fn collect_enum_variants(&self, def_id: CEnumId) -> Result<EnumVariants, TranslationError> {
+        let variant_ids = match self.ast_context[def_id].kind {
+            CDeclKind::Enum { ref variants, .. } => variants,
+            _ => {
+                return Err(format_err!("{:?} does not point to an `enum` declaration", def_id).into())
             }
-            _ => signed_int_expr(value),
         };
 
-        let target_ty = self.convert_type(enum_type_id).unwrap();
+        let mut variants = EnumVariants {
+            by_value: HashMap::with_capacity(variant_ids.len()),
+            in_order: Vec::with_capacity(variant_ids.len()),
+        };
+        for &variant_id in variant_ids {
+            match self.ast_context[variant_id].kind {
+                CDeclKind::EnumConstant { value, .. } => variants.insert(variant_id, value),
+                _ => {
+                    return Err(
+                        format_err!("{:?} does not point to an enum variant", variant_id).into(),
+                    )
+                }
+            }
+        }
+        Ok(variants)
+    }
+
+    /// Refer to an enum variant by name, importing it if needed.
+    fn enum_variant_expr(&self, variant_id: CEnumConstantId) -> Result<P<Expr>, TranslationError> {
+        let name = self
+            .renamer
+            .borrow()
+            .get(&variant_id)
+            .ok_or_else(|| format_err!("Enum variant {:?} has no name", variant_id))?;
 
-        mk().cast_expr(value, target_ty)
+        // Import the enum variant if needed
+        if let Some(cur_file) = *self.cur_file.borrow() {
+            self.add_import(cur_file, variant_id, &name);
+        }
+        Ok(mk().path_expr(vec![name]))
     }
 
-    /// Convert a C literal expression to a Rust expression
+    /// Get the text of a narrow string literal (without its null terminator) if it is valid
+    /// UTF-8 and contains no interior nulls, so that it can be emitted as a Rust `str` literal.
+    pub fn string_literal_text<'a>(&self, bytes: &'a [u8]) -> Option<&'a str> {
+        if bytes.contains(&0) {
+            return None;
+        }
+        str::from_utf8(bytes).ok()
+    }
+
+    /// Convert a C literal expression to a Rust expression, passing the result through the
+    /// literal hook if one is set.
     pub fn convert_literal(
         &self,
         ctx: ExprContext,
         ty: CQualTypeId,
         kind: &CLiteral,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let val = self.convert_literal_default(ctx, ty, kind)?;
+        let resolved_ty = &self.ast_context.resolve_type(ty.ctype).kind;
+        Ok(apply_literal_hook(self.literal_hook.as_ref(), kind, resolved_ty, val))
+    }
+
+    /// Convert a C literal expression to a Rust expression the way the transpiler does
+    /// without a literal hook.
+    fn convert_literal_default(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        kind: &CLiteral,
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
         match *kind {
-            CLiteral::Integer(val, base) => Ok(WithStmts::new_val(self.mk_int_lit(ty, val, base)?)),
+            CLiteral::Integer(val, _) if self.ast_context.resolve_type(ty.ctype).kind.is_bool() => {
+                Ok(WithStmts::new_val(mk().lit_expr(mk().bool_lit(val != 0))))
+            }
+
+            CLiteral::Integer(val, base) => {
+                Ok(WithStmts::new_val(self.mk_int_lit(ty, val.into(), base)?))
+            }
 
             CLiteral::Character(val) => {
                 let val = val as u32;
                 let expr = match char::from_u32(val) {
-                    Some(c) => {
+                    Some(c) if self.tcfg.emit_char_casts => {
                         let expr = mk().lit_expr(c);
                         let i32_type = mk().path_ty(vec!["i32"]);
                         mk().cast_expr(expr, i32_type)
                     }
+                    Some(c) => {
+                        // Printable ASCII characters are annotated with the character they
+                        // stand for, e.g. `/* 'A' */ 65i32`.
+                        let span = if c.is_ascii_graphic() || c == ' ' {
+                            self.comment_store
+                                .borrow_mut()
+                                .extend_existing_comments(
+                                    &[format!("/* {:?} */ ", c)],
+                                    None,
+                                    CommentStyle::Mixed,
+                                )
+                                .map(pos_to_span)
+                                .unwrap_or(DUMMY_SP)
+                        } else {
+                            DUMMY_SP
+                        };
+                        mk().span(span).lit_expr(mk().int_lit(val as u128, "i32"))
+                    }
                     None => {
                         // Fallback for characters outside of the valid Unicode range
                         if (val as i32) < 0 {
@@ -103,43 +418,115 @@ impl<'c> Translation<'c> {
                 Ok(WithStmts::new_val(expr))
             }
 
-            CLiteral::Floating(val, ref c_str) => {
-                let mut bytes: Vec<u8> = vec![];
-                let str = if c_str.is_empty() {
-                    dtoa::write(&mut bytes, val).unwrap();
-                    String::from_utf8(bytes).unwrap()
+            CLiteral::MultiCharacter(val, ref spelling) => {
+                // The value of a multi-character constant is implementation-defined, so use the
+                // one clang computed and keep the original spelling alongside it.
+                let span = self
+                    .comment_store
+                    .borrow_mut()
+                    .extend_existing_comments(
+                        &[multi_character_comment(spelling)],
+                        None,
+                        CommentStyle::Mixed,
+                    )
+                    .map(pos_to_span)
+                    .unwrap_or(DUMMY_SP);
+                let val = val as i32;
+                let lit = mk().int_lit(val.wrapping_abs() as u32 as u128, "i32");
+                let expr = if val < 0 {
+                    mk().span(span).unary_expr("-", mk().lit_expr(lit))
                 } else {
-                    c_str.to_owned()
+                    mk().span(span).lit_expr(lit)
                 };
-                let val = match self.ast_context.resolve_type(ty.ctype).kind {
-                    CTypeKind::LongDouble => {
-                        self.use_crate(ExternCrate::F128);
+                Ok(WithStmts::new_val(expr))
+            }
 
-                        let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
-                        let args = vec![mk().ident_expr(str)];
+            CLiteral::Floating(val, _) if !val.is_finite() => self.convert_non_finite_float(ctx, ty, val),
 
-                        mk().call_expr(fn_path, args)
+            CLiteral::Floating(val, ref c_str) => {
+                let is_hex = c_str.starts_with("0x") || c_str.starts_with("0X");
+                let from_source = !c_str.is_empty() && !is_hex;
+                let val = match self.ast_context.resolve_type(ty.ctype).kind {
+                    // Rust has no hex float literals, so we keep their exact bit pattern
+                    CTypeKind::Double if is_hex => {
+                        return Ok(self.float_from_bits(ctx, "f64", "u64", val.to_bits().into(), 16));
+                    }
+                    CTypeKind::Float if is_hex => {
+                        let bits = (val as f32).to_bits();
+                        return Ok(self.float_from_bits(ctx, "f32", "u32", bits.into(), 8));
+                    }
+                    CTypeKind::LongDouble => {
+                        let str = if from_source {
+                            c_str.to_owned()
+                        } else {
+                            float_literal_text(val)
+                        };
+                        return Ok(self.mk_long_double_lit(ctx, val, str));
+                    }
+                    CTypeKind::Double if from_source => {
+                        mk().lit_expr(mk().float_lit(c_str.as_str(), FloatTy::F64))
+                    }
+                    CTypeKind::Float if from_source => {
+                        mk().lit_expr(mk().float_lit(c_str.as_str(), FloatTy::F32))
+                    }
+                    CTypeKind::Double => float_lit_expr(val, FloatTy::F64),
+                    CTypeKind::Float => float_lit_expr(val, FloatTy::F32),
+                    ref k => {
+                        return Err(format_err!("Unsupported floating point literal type {:?}", k).into())
                     }
-                    CTypeKind::Double => mk().lit_expr(mk().float_lit(str, FloatTy::F64)),
-                    CTypeKind::Float => mk().lit_expr(mk().float_lit(str, FloatTy::F32)),
-                    ref k => panic!("Unsupported floating point literal type {:?}", k),
                 };
                 Ok(WithStmts::new_val(val))
             }
 
+            CLiteral::Imaginary(val) => {
+                let element = match self.ast_context.resolve_type(ty.ctype).kind {
+                    CTypeKind::Complex(element) => element,
+                    ref k => {
+                        return Err(format_err!("Unsupported imaginary literal type {:?}", k).into())
+                    }
+                };
+                let im = match self.ast_context.resolve_type(element).kind {
+                    CTypeKind::Double => mk().float_lit(float_literal_text(val), FloatTy::F64),
+                    CTypeKind::Float => {
+                        mk().float_lit(float_literal_text(val as f32), FloatTy::F32)
+                    }
+                    ref k => return Err(format_err!("Unsupported imaginary literal {:?}", k).into()),
+                };
+                let re = self.implicit_default_expr(element, ctx.is_static)?;
+                Ok(re.map(|re| self.mk_complex(re, mk().lit_expr(im))))
+            }
+
             CLiteral::String(ref val, width) => {
                 let mut val = val.to_owned();
 
                 let mut expects_uchars = false;
+                let mut elem_kind = None;
                 match self.ast_context.resolve_type(ty.ctype).kind {
 
                     CTypeKind::ConstantArray(elem_ty, size) => {
                         // Is the element type is unsigned char?
-                        if &CTypeKind::UChar == &self.ast_context.resolve_type(elem_ty).kind {
+                        let kind = &self.ast_context.resolve_type(elem_ty).kind;
+                        if &CTypeKind::UChar == kind {
                             expects_uchars = true;
                         }
-                        // Match the literal size to the expected size padding with zeros as needed
-                        val.resize(size * (width as usize), 0)
+                        elem_kind = Some(kind);
+                        // Match the literal size to the expected size padding with zeros as
+                        // needed. As in C, a literal that fills the whole array (or more) keeps
+                        // no null terminator and any excess characters are dropped.
+                        let array_len = size * (width as usize);
+                        if val.len() > array_len {
+                            warn!(
+                                "String literal of {} characters truncated to fit an array of {} elements",
+                                val.len() / (width as usize),
+                                size
+                            );
+                        } else if val.len() == array_len {
+                            warn!(
+                                "String literal exactly fills an array of {} elements; omitting the null terminator",
+                                size
+                            );
+                        }
+                        val.resize(array_len, 0)
                     },
 
                     // Add zero terminator
@@ -150,28 +537,51 @@ impl<'c> Translation<'c> {
                         }
                     }
                 };
-                if ctx.is_static {
+                if self.tcfg.prefer_byte_strings {
+                    if let Some(array) = elem_kind.and_then(|k| byte_string_array(k, width, &val)) {
+                        return Ok(WithStmts::new_val(array));
+                    }
+                }
+                // Static and const initializers spell the literal out as an array of integers,
+                // which can be evaluated at compile time without a `transmute`. Safe string
+                // literals do the same everywhere, and get their storage when they decay.
+                if ctx.is_static || ctx.is_const || self.tcfg.safe_string_literals {
                     let mut vals: Vec<P<Expr>> = vec![];
-                    for c in val {
-                        // Emit negative literals if the expected type is not unsigned char. This
-                        // provides a fallback for characters outside of the normal ASCII range.
-                        // Python 2 doc strings, for example, contain non-ASCII chars (https://git.io/fjAxu).
-                        if !expects_uchars && (c as i8) < 0 {
-                            // NOTE: the conversion to i32 avoids overflow when calling abs on -128.
-                            vals.push(mk().unary_expr("-", mk().lit_expr(
-                                mk().int_lit(((c as i8) as i32).abs() as u128, LitIntType::Unsuffixed))
-                            ));
-                        } else {
-                            vals.push(mk().lit_expr(mk().int_lit(c as u128, LitIntType::Unsuffixed)));
+                    if width == 1 {
+                        for c in val {
+                            vals.push(string_byte_expr(c, expects_uchars));
+                        }
+                    } else {
+                        for unit in wide_string_units(&val, width) {
+                            vals.push(mk().lit_expr(mk().int_lit(unit as u128, LitIntType::Unsuffixed)));
                         }
                     }
                     let array = mk().array_expr(vals);
                     Ok(WithStmts::new_val(array))
                 } else {
-                    let u8_ty = mk().path_ty(vec!["u8"]);
+                    // Wide literals are built from code units of the matching width so that the
+                    // source array has the same size and alignment as the C one.
+                    let len = val.len() / (width as usize);
+                    let (unit_ty, literal) = match width {
+                        1 => ("u8", mk().lit_expr(val)),
+                        2 | 4 => {
+                            let units: Vec<P<Expr>> = wide_string_units(&val, width)
+                                .into_iter()
+                                .map(|unit| mk().lit_expr(mk().int_lit(unit as u128, LitIntType::Unsuffixed)))
+                                .collect();
+                            let unit_ty = if width == 2 { "u16" } else { "u32" };
+                            (unit_ty, mk().addr_of_expr(mk().array_expr(units)))
+                        }
+                        _ => {
+                            return Err(format_err!(
+                                "Unsupported string literal element width {}", width
+                            ).into())
+                        }
+                    };
+                    let unit_ty = mk().path_ty(vec![unit_ty]);
                     let width_lit =
-                        mk().lit_expr(mk().int_lit(val.len() as u128, LitIntType::Unsuffixed));
-                    let array_ty = mk().array_ty(u8_ty, width_lit);
+                        mk().lit_expr(mk().int_lit(len as u128, LitIntType::Unsuffixed));
+                    let array_ty = mk().array_ty(unit_ty, width_lit);
                     let source_ty = mk().ref_ty(array_ty);
                     let mutbl = if ty.qualifiers.is_const {
                         Mutability::Immutable
@@ -179,10 +589,8 @@ impl<'c> Translation<'c> {
                         Mutability::Mutable
                     };
                     let target_ty = mk().set_mutbl(mutbl).ref_ty(self.convert_type(ty.ctype)?);
-                    let byte_literal = mk().lit_expr(val);
-                    if ctx.is_const { self.use_feature("const_transmute"); }
                     let pointer =
-                        transmute_expr(source_ty, target_ty, byte_literal, self.tcfg.emit_no_std);
+                        transmute_expr(source_ty, target_ty, literal, self.tcfg.emit_no_std);
                     let array = mk().unary_expr(ast::UnOp::Deref, pointer);
                     Ok(WithStmts::new_unsafe_val(array))
                 }
@@ -190,6 +598,232 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Build a `num_complex::Complex` from its real and imaginary parts.
+    pub fn mk_complex(&self, re: P<Expr>, im: P<Expr>) -> P<Expr> {
+        self.use_crate(ExternCrate::NumComplex);
+        mk().struct_expr(
+            vec!["num_complex", "Complex"],
+            vec![mk().field("re", re), mk().field("im", im)],
+        )
+    }
+
+    /// Translate a single C literal of type `ty` to the source text of the
+    /// equivalent Rust expression.
+    ///
+    /// This is meant for integer, floating point, character, and simple string
+    /// literals. Literals whose translation needs surrounding statements only
+    /// have their final value printed, so their output may be partial. Like the
+    /// rest of translation, this must run within `syntax::with_globals`.
+    pub fn literal_to_rust(&self, ty: CQualTypeId, lit: &CLiteral) -> Result<String, TranslationError> {
+        let ctx = ExprContext {
+            used: true,
+            is_static: false,
+            is_const: false,
+            decay_ref: DecayRef::Default,
+            is_bitfield_write: false,
+            needs_address: false,
+            expecting_valistimpl: false,
+            ternary_needs_parens: false,
+            expanding_macro: None,
+        };
+
+        let val = self.convert_literal(ctx, ty, lit)?;
+        let expr = if val.is_pure() {
+            val.to_unsafe_pure_expr().unwrap()
+        } else {
+            val.into_value()
+        };
+        Ok(pprust::expr_to_string(&expr))
+    }
+
+    /// Pair the C spelling of a literal of type `ty` with the Rust text `literal_to_rust` gives
+    /// for it, so the two can be compared side by side when checking a translation.
+    pub fn literal_spellings(
+        &self,
+        ty: CQualTypeId,
+        kind: &CLiteral,
+    ) -> Result<(String, String), TranslationError> {
+        let rust = self.literal_to_rust(ty, kind)?;
+        let resolved_ty = &self.ast_context.resolve_type(ty.ctype).kind;
+        Ok((c_literal_spelling(kind, resolved_ty), rust))
+    }
+
+    /// Name of a new `static mut` holding `init`, the array of type `ty` a safe string literal
+    /// is spelled out as, declared in the current file. Every literal gets its own, so that
+    /// writes through one can't show up in another.
+    pub fn safe_string_literal_static(&self, ty: P<Ty>, init: P<Expr>) -> String {
+        let name = self.renamer.borrow_mut().pick_name_root("STRING_LITERAL");
+        let item = mk().mutbl().static_item(&name, ty, init);
+        self.with_cur_file_item_store(|item_store| item_store.add_item(item));
+        name
+    }
+
+    /// Name of the static holding the null-terminated contents of a string literal
+    /// used read-only in several places, declaring it in the current file on first use.
+    /// Literals referenced from outside of Rust get their own static marked `#[used]`, and
+    /// ones in constant contexts (`is_const`) a constant, since constants can't name statics.
+    pub fn string_literal_static(&self, bytes: &[u8], is_const: bool) -> String {
+        let storage = if is_const {
+            StringLiteralStorage::Const
+        } else if self.string_literals_escape.get() {
+            StringLiteralStorage::UsedStatic
+        } else {
+            StringLiteralStorage::Static
+        };
+        let key = (self.cur_file(), bytes.to_vec(), storage);
+        if let Some(name) = self.string_statics.borrow().get(&key) {
+            return name.clone();
+        }
+
+        let name = self.renamer.borrow_mut().pick_name_root("STRING_LITERAL");
+        let item = string_literal_static_item(&name, bytes, storage);
+        self.with_cur_file_item_store(|item_store| item_store.add_item(item));
+
+        self.string_statics.borrow_mut().insert(key, name.clone());
+        name
+    }
+
+    /// Convert an infinite or NaN floating point literal. These have no literal syntax in Rust,
+    /// so we refer to the standard constants instead, or build non-canonical NaNs from their bits.
+    fn convert_non_finite_float(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        val: f64,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let std_or_core = if self.tcfg.emit_no_std { "core" } else { "std" };
+        let name = if val.is_nan() {
+            "NAN"
+        } else if val.is_sign_positive() {
+            "INFINITY"
+        } else {
+            "NEG_INFINITY"
+        };
+
+        let val = match self.ast_context.resolve_type(ty.ctype).kind {
+            CTypeKind::LongDouble => {
+                self.use_crate(ExternCrate::F128);
+                mk().path_expr(vec!["f128", "f128", name])
+            }
+            CTypeKind::Double => {
+                if val.is_nan() && val.to_bits() != f64::NAN.to_bits() {
+                    return Ok(self.float_from_bits(ctx, "f64", "u64", val.to_bits().into(), 16));
+                }
+                mk().path_expr(vec!["", std_or_core, "f64", name])
+            }
+            CTypeKind::Float => {
+                let val = val as f32;
+                if val.is_nan() && val.to_bits() != f32::NAN.to_bits() {
+                    return Ok(self.float_from_bits(ctx, "f32", "u32", val.to_bits().into(), 8));
+                }
+                mk().path_expr(vec!["", std_or_core, "f32", name])
+            }
+            ref k => {
+                return Err(format_err!("Unsupported floating point literal type {:?}", k).into())
+            }
+        };
+        Ok(WithStmts::new_val(val))
+    }
+
+    /// Build a `long double` with the value of `val`, spelled as `text`. `f128::new` is not a
+    /// `const fn`, so in const contexts we build the value from its binary128 bits instead. Both
+    /// forms produce the same value.
+    pub fn mk_long_double_lit(&self, ctx: ExprContext, val: f64, text: String) -> WithStmts<P<Expr>> {
+        self.use_crate(ExternCrate::F128);
+
+        if ctx.is_const || ctx.is_static {
+            self.use_feature("const_transmute");
+            let bits = f64_to_f128_bits(val);
+            let text = format!("0x{:032x}", bits);
+            let bits = mk().lit_expr(mk().int_lit_with_text(bits, text, "u128"));
+            let source_ty = mk().path_ty(vec!["u128"]);
+            let target_ty = mk().path_ty(vec!["f128", "f128"]);
+            let val = transmute_expr(source_ty, target_ty, bits, self.tcfg.emit_no_std);
+            WithStmts::new_unsafe_val(val)
+        } else {
+            let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
+            let args = vec![mk().ident_expr(text)];
+            WithStmts::new_val(mk().call_expr(fn_path, args))
+        }
+    }
+
+    /// Get the value of `expr` if it is a numeric literal, possibly behind an implicit
+    /// conversion, that `mk_long_double_lit` can build.
+    pub fn long_double_literal_value(&self, expr: CExprId) -> Option<f64> {
+        match self.ast_context[expr].kind {
+            CExprKind::Literal(_, CLiteral::Floating(val, _)) if val.is_finite() => Some(val),
+            // Larger integers would be rounded when going through an `f64`
+            CExprKind::Literal(_, CLiteral::Integer(val, _)) if val < (1 << 53) => Some(val as f64),
+            CExprKind::ImplicitCast(_, subexpr, CastKind::FloatingCast, _, _)
+            | CExprKind::ImplicitCast(_, subexpr, CastKind::IntegralToFloating, _, _) => {
+                self.long_double_literal_value(subexpr)
+            }
+            _ => None,
+        }
+    }
+
+    /// Build a float of type `float_ty` from the bits of an integer of type `bits_ty`, printed
+    /// as `digits` hex digits.
+    fn float_from_bits(
+        &self,
+        ctx: ExprContext,
+        float_ty: &str,
+        bits_ty: &str,
+        bits: u128,
+        digits: usize,
+    ) -> WithStmts<P<Expr>> {
+        let text = format!("0x{:0width$x}", bits, width = digits);
+        let bits = mk().lit_expr(mk().int_lit_with_text(bits, text, bits_ty));
+        if ctx.is_const || ctx.is_static {
+            // `from_bits` is not a `const fn`, but a transmute does the same thing
+            self.use_feature("const_transmute");
+            let source_ty = mk().path_ty(vec![bits_ty]);
+            let target_ty = mk().path_ty(vec![float_ty]);
+            let val = transmute_expr(source_ty, target_ty, bits, self.tcfg.emit_no_std);
+            WithStmts::new_unsafe_val(val)
+        } else {
+            let from_bits = mk().path_expr(vec![float_ty, "from_bits"]);
+            WithStmts::new_val(mk().call_expr(from_bits, vec![bits]))
+        }
+    }
+
+    /// Whether the initializer list `ids` for an array, struct, or union of type `ty` zeroes
+    /// all of it: every element is a literal `0`, possibly cast, an implicit value, or a
+    /// nested list that is itself all zeros. A union only counts when the initialized field
+    /// is the first one, which is the field its zero initializer sets.
+    fn is_zero_init_list(
+        &self,
+        ty: CTypeId,
+        ids: &[CExprId],
+        opt_union_field_id: Option<CFieldId>,
+    ) -> bool {
+        let is_aggregate = match self.ast_context.resolve_type(ty).kind {
+            CTypeKind::ConstantArray(..) | CTypeKind::Struct(_) => true,
+            CTypeKind::Union(union_id) => match self.ast_context[union_id].kind {
+                CDeclKind::Union { fields: Some(ref fields), .. } => {
+                    opt_union_field_id.map_or(true, |id| fields.first() == Some(&id))
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        is_aggregate && ids.iter().all(|&id| self.is_zero_initializer(id))
+    }
+
+    /// Whether the initializer `id` is a zero value on its own, as described for
+    /// `is_zero_init_list`.
+    fn is_zero_initializer(&self, id: CExprId) -> bool {
+        match *self.ast_context.resolve_expr_value(id) {
+            CExprKind::ImplicitValueInit(..) => true,
+            CExprKind::Literal(_, CLiteral::Integer(0, _))
+            | CExprKind::Literal(_, CLiteral::Character(0)) => true,
+            CExprKind::InitList(ty, ref ids, opt_union_field_id, _) => {
+                self.is_zero_init_list(ty.ctype, ids, opt_union_field_id)
+            }
+            _ => false,
+        }
+    }
+
     /// Convert an initialization list into an expresion. These initialization lists can be
     /// used as array literals, struct literals, and union literals in code.
     pub fn convert_init_list(
@@ -199,6 +833,13 @@ impl<'c> Translation<'c> {
         ids: &[CExprId],
         opt_union_field_id: Option<CFieldId>,
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        // `{}` and `{0}` both zero the whole aggregate, so translate them to the same zero
+        // initializer an aggregate gets when it isn't initialized at all, rather than going
+        // through the arms below and ending up with different results for each spelling.
+        if self.is_zero_init_list(ty.ctype, ids, opt_union_field_id) {
+            return self.implicit_default_expr(ty.ctype, ctx.is_static);
+        }
+
         match self.ast_context.resolve_type(ty.ctype).kind {
             CTypeKind::ConstantArray(ty, n) => {
                 // Convert all of the provided initializer values
@@ -221,39 +862,87 @@ impl<'c> Translation<'c> {
                     let v = ids.first().unwrap();
                     self.convert_expr(ctx.used(), *v)
                 } else {
-                    Ok(ids
-                        .iter()
-                        .map(|id| {
-                            self.convert_expr(ctx.used(), *id)?
-                                .result_map(|x| {
-                                    // Array literals require all of their elements to be
-                                    // the correct type; they will not use implicit casts to
-                                    // change mut to const. This becomes a problem when an
-                                    // array literal is used in a position where there is no
-                                    // type information available to force its type to the
-                                    // correct const or mut variation. To avoid this issue
-                                    // we manually insert the otherwise elided casts in this
-                                    // particular context.
-                                    if let CExprKind::ImplicitCast(ty, _, CastKind::ConstCast, _, _) =
-                                        self.ast_context[*id].kind
-                                    {
-                                        let t = self.convert_type(ty.ctype)?;
-                                        Ok(mk().cast_expr(x, t))
-                                    } else {
-                                        Ok(x)
-                                    }
-                                })
-                        })
-                        .chain(
+                    let excess = ids.get(n).and_then(|&id| {
+                        self.ast_context.display_loc(&self.ast_context[id].loc)
+                    });
+                    check_array_init_len(ids.len(), n).map_err(|e| e.add_loc(excess))?;
+
+                    let elements = self.array_init_elements(ids, n);
+                    let default = self.implicit_default_expr(ty, ctx.is_static);
+
+                    // A range designator (`[lo ... hi] = value`) shares a single
+                    // initializer expression between all of the elements it covers.
+                    // When it covers the whole array, evaluate it once by emitting
+                    // `[value; n]`.
+                    if let Some(&Some(id)) = elements.first() {
+                        if n > 1
+                            && elements.iter().all(|&e| e == Some(id))
+                            && self.is_repeatable_element(ty)
+                        {
+                            let len = self.array_length_expr(n);
+                            return Ok(self
+                                .convert_array_element(ctx, id)?
+                                .map(|val| mk().repeat_expr(val, len)));
+                        }
+                    }
+
+                    // Tables of integer constants become literals of the element type
+                    // directly, rather than one cast per element.
+                    if let Some(table) = self.convert_integer_table(ty, &elements) {
+                        return Ok(WithStmts::new_val(table));
+                    }
+
+                    let mut uses = HashMap::new();
+                    for &id in elements.iter().flatten() {
+                        *uses.entry(id).or_insert(0) += 1;
+                    }
+                    let is_side_effect_free = uses
+                        .keys()
+                        .all(|&id| self.ast_context.is_expr_pure(id));
+
+                    // Otherwise convert each shared initializer once, and let the
+                    // other elements it covers reuse the value. The side effects of
+                    // an impure initializer must only happen once, so outside of
+                    // static initializers its value is bound to a temporary first.
+                    let mut shared: HashMap<CExprId, WithStmts<P<Expr>>> = HashMap::new();
+                    let mut vals = Vec::with_capacity(n);
+                    for element in elements {
+                        let val = match element {
                             // Pad out the array literal with default values to the desired size
-                            iter::repeat(
-                                self.implicit_default_expr(ty, ctx.is_static)
-                            ).take(n - ids.len())
-                        )
-                        .collect::<Result<WithStmts<Vec<P<Expr>>>, TranslationError>>()?
-                        .map(|vals| {
+                            None => default.clone()?,
+                            Some(id) if uses[&id] == 1 => self.convert_array_element(ctx, id)?,
+                            Some(id) => match shared.get(&id) {
+                                Some(val) => val.clone(),
+                                None => {
+                                    let mut val = self.convert_array_element(ctx, id)?;
+                                    if !ctx.is_static && !self.ast_context.is_expr_pure(id) {
+                                        val = self.bind_to_temporary("init", Mutability::Immutable, val);
+                                    }
+                                    let mut reuse = WithStmts::new_val(val.clone().into_value());
+                                    reuse.merge_unsafe(val.is_unsafe());
+                                    shared.insert(id, reuse);
+                                    val
+                                }
+                            },
+                        };
+                        vals.push(val);
+                    }
+
+                    // Arrays whose elements all come out the same, such as `{0}`,
+                    // become `[value; n]` rather than spelling out every element.
+                    let vals = vals.into_iter().collect::<WithStmts<Vec<P<Expr>>>>();
+                    let repeatable = n > 1
+                        && vals.is_pure()
+                        && is_side_effect_free
+                        && self.is_repeatable_element(ty);
+                    Ok(vals.map(|vals| {
+                        if repeatable && all_exprs_equal(&vals) {
+                            let len = self.array_length_expr(n);
+                            mk().repeat_expr(vals[0].clone(), len)
+                        } else {
                             mk().array_expr(vals)
-                        }))
+                        }
+                    }))
                 }
             }
             CTypeKind::Struct(struct_id) => {
@@ -278,33 +967,302 @@ impl<'c> Translation<'c> {
             CTypeKind::Union(union_id) => {
                 self.convert_union_literal(ctx, union_id, ids.as_ref(), ty, opt_union_field_id)
             }
-            CTypeKind::Pointer(_) => {
-                let id = ids.first().unwrap();
-                self.convert_expr(ctx.used(), *id)
-            }
+            CTypeKind::IncompleteArray(_) if !ids.is_empty() => Err(TranslationError::generic(
+                "Initializing a flexible array member with elements is not supported",
+            )),
             CTypeKind::Vector(CQualTypeId { ctype, .. }, len) => {
                 self.vector_list_initializer(ctx, ids, ctype, len)
             }
-            CTypeKind::Char => {
-                let id = ids.first().unwrap();
-                self.convert_expr(ctx.used(), *id)
+            CTypeKind::Complex(element) => {
+                // `{re, im}`, with missing parts defaulting to zero
+                let parts = (0..2)
+                    .map(|i| match ids.get(i) {
+                        Some(&id) => self.convert_expr(ctx.used(), id),
+                        None => self.implicit_default_expr(element, ctx.is_static),
+                    })
+                    .collect::<Result<WithStmts<Vec<_>>, TranslationError>>()?;
+                Ok(parts.map(|mut parts| {
+                    let im = parts.pop().unwrap();
+                    let re = parts.pop().unwrap();
+                    self.mk_complex(re, im)
+                }))
+            }
+            CTypeKind::Pointer(_) | CTypeKind::Char => match ids.first() {
+                Some(&id) => self.convert_expr(ctx.used(), id),
+                // An empty `{}` initializer gives the same value as leaving it out
+                None => self.implicit_default_expr(ty.ctype, ctx.is_static),
+            },
+            ref t => {
+                let type_name = self
+                    .convert_type(ty.ctype)
+                    .map(|ty| pprust::ty_to_string(&ty))
+                    .unwrap_or_else(|_| format!("{:?}", t));
+                let mut msg = format!("Init list not implemented for {} ({:?})", type_name, t);
+                let first_loc = ids
+                    .first()
+                    .and_then(|&id| self.ast_context.display_loc(&self.ast_context[id].loc));
+                if let Some(loc) = first_loc {
+                    msg.push_str(&format!(", starting with the initializer at {}", loc));
+                }
+                Err(format_err!("{}", msg).into())
+            }
+        }
+    }
+
+    /// Lays the elements of a semantic array initializer list out over an
+    /// array of `n` elements. Clang has already applied any `[index] = value`
+    /// and `[lo ... hi] = value` designators, including letting the last
+    /// designator for an index win, so entry `i` of `ids` initializes element
+    /// `i`. Slots which the initializer left out, either by skipping them or
+    /// by ending early, are `None` and take the element type's default value.
+    fn array_init_elements(&self, ids: &[CExprId], n: usize) -> Vec<Option<CExprId>> {
+        let mut elements = vec![None; n];
+        for (slot, &id) in elements.iter_mut().zip(ids) {
+            if let CExprKind::ImplicitValueInit(..) = self.ast_context[id].kind {
+                continue;
+            }
+            *slot = Some(id);
+        }
+        elements
+    }
+
+    /// Build an array of `ty` elements from integer literals, without a cast for each element.
+    /// Only the first element carries a type suffix, which fixes the type of the others. Each
+    /// element is spelled like a scalar literal, in its source base, and goes through the literal
+    /// hook. Returns `None` if some element isn't an integer literal whose value fits in the
+    /// element type, leaving values `--narrowing-literals` has to report to the usual path, or if
+    /// the element type has no fixed-width suffix.
+    fn convert_integer_table(&self, ty: CTypeId, elements: &[Option<CExprId>]) -> Option<P<Expr>> {
+        let suffix = self.int_lit_suffix(self.ast_context.resolve_type_id(ty))?;
+        let (bits, signed) = int_lit_layout(suffix);
+        let literals = elements
+            .iter()
+            .map(|element| match *element {
+                Some(id) => self
+                    .table_literal(id)
+                    .filter(|lit| truncated_constant(lit.value(), bits, signed).is_none()),
+                None => Some(TableLiteral {
+                    expr_id: None,
+                    val: 0,
+                    base: IntBase::Dec,
+                    negated: false,
+                }),
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let resolved_ty = &self.ast_context.resolve_type(ty).kind;
+        let lit = |lit: &TableLiteral, lit_ty: LitIntType| {
+            let expr = self.int_lit_expr(lit.val.into(), lit.base, lit_ty);
+            let expr = match lit.expr_id {
+                Some(expr_id) => {
+                    let kind = CLiteral::Integer(lit.val, lit.base);
+                    let expr = match self.literal_hook {
+                        Some(ref hook) => hook(&kind, resolved_ty, expr),
+                        None => expr,
+                    };
+                    self.record_literal_provenance(expr_id, &expr);
+                    expr
+                }
+                None => expr,
+            };
+            if lit.negated {
+                mk().unary_expr(ast::UnOp::Neg, expr)
+            } else {
+                expr
             }
-            ref t => Err(format_err!("Init list not implemented for {:?}", t).into()),
+        };
+
+        let (first, rest) = literals.split_first()?;
+        if rest.iter().all(|lit| lit.value() == first.value()) {
+            let len = self.array_length_expr(literals.len());
+            return Some(mk().repeat_expr(lit(first, suffix), len));
         }
+
+        let exprs = iter::once(lit(first, suffix))
+            .chain(rest.iter().map(|l| lit(l, LitIntType::Unsuffixed)))
+            .collect::<Vec<_>>();
+        Some(mk().array_expr(exprs))
     }
 
+    /// If `id` is an integer literal, possibly negated, in parentheses, or converted between
+    /// fixed-width integer types, and its value fits in the type of every step on the way, get
+    /// that literal. Such an expression has the value of the literal as written.
+    fn table_literal(&self, id: CExprId) -> Option<TableLiteral> {
+        let ty = self.ast_context[id].kind.get_type()?;
+        let suffix = self.int_lit_suffix(self.ast_context.resolve_type_id(ty))?;
+        let (bits, signed) = int_lit_layout(suffix);
+
+        let lit = match self.ast_context[id].kind {
+            CExprKind::Literal(_, CLiteral::Integer(val, base)) => TableLiteral {
+                expr_id: Some(id),
+                val,
+                base,
+                negated: false,
+            },
+            CExprKind::Paren(_, e) | CExprKind::ImplicitCast(_, e, CastKind::IntegralCast, _, _) => {
+                self.table_literal(e)?
+            }
+            // Negating an unsigned value wraps it, so only signed negations are kept
+            CExprKind::Unary(_, c_ast::UnOp::Negate, e, _) if signed => {
+                let lit = self.table_literal(e)?;
+                TableLiteral {
+                    negated: !lit.negated,
+                    ..lit
+                }
+            }
+            _ => return None,
+        };
+        if truncated_constant(lit.value(), bits, signed).is_some() {
+            return None;
+        }
+        Some(lit)
+    }
+
+    /// Evaluate an integer constant made up of literals, negations, and casts between
+    /// fixed-width integer types.
+    fn integer_constant(&self, id: CExprId) -> Option<i128> {
+        let ty = self.ast_context[id].kind.get_type()?;
+        let suffix = self.int_lit_suffix(self.ast_context.resolve_type_id(ty))?;
+        let (bits, signed) = int_lit_layout(suffix);
+
+        let val = match self.ast_context[id].kind {
+            CExprKind::Literal(_, CLiteral::Integer(val, _)) => val.into(),
+            CExprKind::Literal(_, CLiteral::Character(val))
+            | CExprKind::Literal(_, CLiteral::MultiCharacter(val, _)) => val.into(),
+            CExprKind::Paren(_, e)
+            | CExprKind::ImplicitCast(_, e, CastKind::IntegralCast, _, _)
+            | CExprKind::ExplicitCast(_, e, CastKind::IntegralCast, _, _) => {
+                self.integer_constant(e)?
+            }
+            CExprKind::Unary(_, c_ast::UnOp::Negate, e, _) => self.integer_constant(e)?.wrapping_neg(),
+            _ => return None,
+        };
+        Some(wrap_int(val, bits, signed))
+    }
+
+    /// Build the length of an array of `n` elements, for use in `[value; n]`. With
+    /// `named_array_lengths`, this may be a constant shared by the arrays of that length.
+    pub fn array_length_expr(&self, n: usize) -> P<Expr> {
+        self.type_converter.borrow().array_length_expr(n)
+    }
+
+    /// Whether an array of `ty` elements can be built with a `[value; n]`
+    /// repeat expression, which requires the element type to be `Copy`.
+    /// Translated records derive `Copy` so long as all of their fields are,
+    /// but `va_list` is not `Copy`.
+    pub fn is_repeatable_element(&self, ty: CTypeId) -> bool {
+        if self.ast_context.is_va_list(ty) {
+            return false;
+        }
+
+        match self.ast_context.resolve_type(ty).kind {
+            CTypeKind::ConstantArray(elt, _) | CTypeKind::IncompleteArray(elt) => {
+                self.is_repeatable_element(elt)
+            }
+            // SIMD vector types are all `Copy`, and so is `num_complex::Complex`
+            CTypeKind::Vector(..) | CTypeKind::Complex(..) => true,
+            CTypeKind::Struct(decl_id) | CTypeKind::Union(decl_id) => {
+                match self.ast_context[decl_id].kind {
+                    CDeclKind::Struct {
+                        fields: Some(ref fields),
+                        ..
+                    }
+                    | CDeclKind::Union {
+                        fields: Some(ref fields),
+                        ..
+                    } => fields.iter().all(|&field_id| match self.ast_context[field_id].kind {
+                        CDeclKind::Field { typ, .. } => self.is_repeatable_element(typ.ctype),
+                        _ => false,
+                    }),
+                    _ => false,
+                }
+            }
+            ref kind => {
+                kind.is_integral_type()
+                    || kind.is_floating_type()
+                    || kind.is_pointer()
+                    || kind.is_enum()
+            }
+        }
+    }
+
+    /// Bind the value of `val` to a fresh local named after `prefix`, so that it
+    /// can be used several times while only being evaluated once.
+    pub fn bind_to_temporary(
+        &self,
+        prefix: &str,
+        mutbl: Mutability,
+        val: WithStmts<P<Expr>>,
+    ) -> WithStmts<P<Expr>> {
+        let name = self.renamer.borrow_mut().pick_name(prefix);
+        let is_unsafe = val.is_unsafe();
+        let (mut stmts, val) = val.discard_unsafe();
+        let pat = mk().set_mutbl(mutbl).ident_pat(&name);
+        let local = mk().local(pat, None as Option<P<Ty>>, Some(val));
+        stmts.push(mk().local_stmt(P(local)));
+
+        let mut bound = WithStmts::new(stmts, mk().ident_expr(name));
+        bound.merge_unsafe(is_unsafe);
+        bound
+    }
+
+    fn convert_array_element(
+        &self,
+        ctx: ExprContext,
+        id: CExprId,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        self.convert_expr(ctx.used(), id)?.result_map(|x| {
+            // Array literals require all of their elements to be
+            // the correct type; they will not use implicit casts to
+            // change mut to const. This becomes a problem when an
+            // array literal is used in a position where there is no
+            // type information available to force its type to the
+            // correct const or mut variation. To avoid this issue
+            // we manually insert the otherwise elided casts in this
+            // particular context.
+            if let CExprKind::ImplicitCast(ty, _, CastKind::ConstCast, _, _) =
+                self.ast_context[id].kind
+            {
+                let t = self.convert_type(ty.ctype)?;
+                Ok(mk().cast_expr(x, t))
+            } else {
+                Ok(x)
+            }
+        })
+    }
+
+    /// Convert an initializer list for a union to a literal of the member it initializes. The
+    /// union is `#[repr(C)]`, so every member starts at offset 0 as in C whichever one is
+    /// chosen, and its declaration checks that its size matches C's.
     fn convert_union_literal(
         &self,
         ctx: ExprContext,
         union_id: CRecordId,
         ids: &[CExprId],
-        _ty: CQualTypeId,
+        ty: CQualTypeId,
         opt_union_field_id: Option<CFieldId>,
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
-        let union_field_id = opt_union_field_id.expect("union field ID");
-
         match self.ast_context.index(union_id).kind {
-            CDeclKind::Union { .. } => {
+            CDeclKind::Union { ref fields, .. } => {
+                // Clang names the initialized member, which a designator may pick.
+                // An initializer such as `{}` doesn't select one, so zero-initialize
+                // the union, which is also what initializing its first member with
+                // no value would do.
+                let union_field_id = match opt_union_field_id {
+                    Some(field_id) => field_id,
+                    None if ids.is_empty() => {
+                        return self.implicit_default_expr(ty.ctype, ctx.is_static)
+                    }
+                    None => match fields.as_ref().and_then(|fields| fields.first()) {
+                        Some(&field_id) => field_id,
+                        None => {
+                            return Err(TranslationError::generic(
+                                "Initializer list for a union without fields",
+                            ))
+                        }
+                    },
+                };
+
                 let union_name = self
                     .type_converter
                     .borrow()
@@ -336,3 +1294,1026 @@ impl<'c> Translation<'c> {
         }
     }
 }
+
+/// The variants of an enum, indexed for building enum values in `enum_for_i64`.
+pub struct EnumVariants {
+    /// Variants by value, widened so that signed and unsigned discriminants never compare equal
+    /// unless they denote the same number.
+    by_value: HashMap<i128, CEnumConstantId>,
+    /// Variants with values that fit in an `i64`, in declaration order.
+    in_order: Vec<(CEnumConstantId, i64)>,
+}
+
+impl EnumVariants {
+    fn insert(&mut self, variant_id: CEnumConstantId, value: ConstIntExpr) {
+        let value = match value {
+            ConstIntExpr::I(v) => v as i128,
+            ConstIntExpr::U(v) => v as i128,
+        };
+        // Several variants may share a value; prefer the one declared first
+        self.by_value.entry(value).or_insert(variant_id);
+        if value >= i64::min_value() as i128 && value <= i64::max_value() as i128 {
+            self.in_order.push((variant_id, value as i64));
+        }
+    }
+
+    /// Find the first variant declared with exactly `value`.
+    fn get(&self, value: i64) -> Option<CEnumConstantId> {
+        self.by_value.get(&(value as i128)).cloned()
+    }
+}
+
+/// Build `value` for a cast to an enum whose underlying integer type is `underlying`.
+/// Negative values wrap around for unsigned types, which can't be negated, and keep their sign
+/// for signed ones.
+fn enum_fallback_value(underlying: &CTypeKind, value: i64) -> P<Expr> {
+    let unsigned = match *underlying {
+        CTypeKind::UChar => Some(value as u8 as u128),
+        CTypeKind::UShort => Some(value as u16 as u128),
+        CTypeKind::UInt => Some(value as u32 as u128),
+        CTypeKind::ULong | CTypeKind::ULongLong => Some(value as u64 as u128),
+        CTypeKind::UInt128 => Some(value as u128),
+        _ => None,
+    };
+    match unsigned {
+        Some(value) => mk().lit_expr(mk().int_lit(value, LitIntType::Unsuffixed)),
+        None => signed_int_expr(value),
+    }
+}
+
+/// Find distinct variants whose values, taken as bit flags, are disjoint and together make up
+/// exactly `value`. Variants are picked in declaration order, skipping any whose bits are
+/// already covered. Returns `None` unless at least two variants are needed.
+fn enum_flag_variants<T: Copy>(variants: &[(T, i64)], value: i64) -> Option<Vec<T>> {
+    if value <= 0 {
+        return None;
+    }
+
+    let mut covered = 0;
+    let mut flags = vec![];
+    for &(variant, v) in variants {
+        if v > 0 && v & !value == 0 && v & covered == 0 {
+            covered |= v;
+            flags.push(variant);
+        }
+    }
+
+    if covered == value && flags.len() >= 2 {
+        Some(flags)
+    } else {
+        None
+    }
+}
+
+/// The width in bits and signedness of a suffixed integer literal type.
+fn int_lit_layout(lit_ty: LitIntType) -> (u32, bool) {
+    match lit_ty {
+        LitIntType::Signed(IntTy::I8) => (8, true),
+        LitIntType::Signed(IntTy::I16) => (16, true),
+        LitIntType::Signed(IntTy::I32) => (32, true),
+        LitIntType::Signed(IntTy::I64) => (64, true),
+        LitIntType::Unsigned(UintTy::U8) => (8, false),
+        LitIntType::Unsigned(UintTy::U16) => (16, false),
+        LitIntType::Unsigned(UintTy::U32) => (32, false),
+        LitIntType::Unsigned(UintTy::U64) => (64, false),
+        LitIntType::Unsigned(_) => (128, false),
+        _ => (128, true),
+    }
+}
+
+/// Build a float literal for a value that has no source spelling. The sign goes on a negation
+/// rather than into the literal token, which also keeps it for `-0.0`.
+fn float_lit_expr(val: f64, ty: FloatTy) -> P<Expr> {
+    let text = match ty {
+        FloatTy::F32 => float_literal_text((val as f32).abs()),
+        FloatTy::F64 => float_literal_text(val.abs()),
+    };
+    let lit = mk().lit_expr(mk().float_lit(text, ty));
+    if val.is_sign_negative() {
+        mk().unary_expr(ast::UnOp::Neg, lit)
+    } else {
+        lit
+    }
+}
+
+/// Build `panic!(msg, arg)`, where `msg` is a format string taking the variable `arg`.
+fn panic_mac(msg: &str, arg: &str) -> Mac {
+    let tokens = vec![
+        TokenTree::token(
+            token::Interpolated(Rc::new(Nonterminal::NtExpr(mk().lit_expr(msg)))),
+            DUMMY_SP,
+        ),
+        TokenTree::token(token::Comma, DUMMY_SP),
+        TokenTree::token(
+            token::Interpolated(Rc::new(Nonterminal::NtExpr(mk().ident_expr(arg)))),
+            DUMMY_SP,
+        ),
+    ]
+    .into_iter()
+    .collect::<TokenStream>();
+    mk().mac(vec!["panic"], tokens, MacDelimiter::Parenthesis)
+}
+
+/// Block comment showing the spelling of a multi-character constant. Rust block comments
+/// nest, so a `/*` or `*/` in the spelling, as in `'*/'`, is broken up with a backslash.
+fn multi_character_comment(spelling: &str) -> String {
+    let spelling = spelling.replace("*/", "*\\/").replace("/*", "/\\*");
+    format!("/* {} */ ", spelling)
+}
+
+/// Build the static or constant `name` holding the null-terminated `bytes`.
+fn string_literal_static_item(name: &str, bytes: &[u8], storage: StringLiteralStorage) -> P<Item> {
+    let mut bytes = bytes.to_vec();
+    bytes.push(0);
+    let len = mk().lit_expr(mk().int_lit(bytes.len() as u128, LitIntType::Unsuffixed));
+    let ty = mk().array_ty(mk().path_ty(vec!["u8"]), len);
+    let init = mk().unary_expr(ast::UnOp::Deref, mk().lit_expr(bytes));
+    match storage {
+        StringLiteralStorage::Static => mk().static_item(name, ty, init),
+        StringLiteralStorage::UsedStatic => mk().single_attr("used").static_item(name, ty, init),
+        StringLiteralStorage::Const => mk().const_item(name, ty, init),
+    }
+}
+
+/// An element of an integer table, see `Translation::convert_integer_table`.
+#[derive(Clone, Copy, Debug)]
+struct TableLiteral {
+    /// The C literal, or `None` for an element the initializer leaves out, which is zero.
+    expr_id: Option<CExprId>,
+    val: u64,
+    base: IntBase,
+    negated: bool,
+}
+
+impl TableLiteral {
+    fn value(&self) -> i128 {
+        if self.negated {
+            -i128::from(self.val)
+        } else {
+            i128::from(self.val)
+        }
+    }
+}
+
+/// Run the default translation of a literal through `hook`, if there is one.
+fn apply_literal_hook(
+    hook: Option<&LiteralHook>,
+    kind: &CLiteral,
+    ty: &CTypeKind,
+    val: WithStmts<P<Expr>>,
+) -> WithStmts<P<Expr>> {
+    match hook {
+        Some(hook) => val.map(|expr| hook(kind, ty, expr)),
+        None => val,
+    }
+}
+
+/// If `val` is too big for a signed type of `bits` bits, get the magnitude of the negative value
+/// C wraps it to. The minimum value of the type has a magnitude one past its maximum, which Rust
+/// accepts as the operand of a negation.
+fn negative_wrapped_magnitude(val: u128, bits: u32) -> Option<u128> {
+    let signed_max = u128::max_value() >> (128 - bits + 1);
+    if val <= signed_max {
+        return None;
+    }
+    let wrapped = wrap_int(val as i128, bits, true);
+    if wrapped < 0 {
+        Some((wrapped as u128).wrapping_neg())
+    } else {
+        None
+    }
+}
+
+/// Truncate `val` to `bits` bits, reinterpreting the result as signed or unsigned as C does
+/// when converting between integer types.
+fn wrap_int(val: i128, bits: u32, signed: bool) -> i128 {
+    if bits >= 128 {
+        return val;
+    }
+    let shift = 128 - bits;
+    if signed {
+        (val << shift) >> shift
+    } else {
+        (((val as u128) << shift) >> shift) as i128
+    }
+}
+
+/// The value `val` truncates to in a `bits`-wide integer type, or `None` if it fits unchanged.
+fn truncated_constant(val: i128, bits: u32, signed: bool) -> Option<i128> {
+    let wrapped = wrap_int(val, bits, signed);
+    if wrapped == val {
+        None
+    } else {
+        Some(wrapped)
+    }
+}
+
+/// Build the `compile_error!` that stands in for a cast of `val` to `ty_name` under
+/// `--narrowing-literals=strict`.
+fn narrowing_literal_error(val: i128, ty_name: &str) -> P<Expr> {
+    let msg = format!("integer constant {} does not fit in {}", val, ty_name);
+    mk().mac_expr(compile_error_mac(&msg))
+}
+
+/// Reject an initializer list of `len` elements for an array of `n`. Clang drops the excess
+/// elements of an over-long initializer with a warning, so these only come from unusual ASTs, and
+/// silently dropping them again could hide a mistranslation.
+fn check_array_init_len(len: usize, n: usize) -> Result<(), TranslationError> {
+    if len > n {
+        Err(format_err!(
+            "Array initializer has {} elements, but the array only holds {}",
+            len,
+            n
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether all of `exprs` print identically.
+fn all_exprs_equal(exprs: &[P<Expr>]) -> bool {
+    let mut printed = exprs.iter().map(|e| pprust::expr_to_string(e));
+    match printed.next() {
+        Some(first) => printed.all(|e| e == first),
+        None => true,
+    }
+}
+
+/// Combine the bytes of a wide string literal into `width`-byte code units. Clang gives us the
+/// bytes in target order, and all of the targets we support are little-endian.
+fn wide_string_units(bytes: &[u8], width: u8) -> Vec<u32> {
+    bytes
+        .chunks(width as usize)
+        .map(|unit| unit.iter().rev().fold(0, |acc, &b| (acc << 8) | b as u32))
+        .collect()
+}
+
+/// Literal for one byte of a string initializing a `char` array, or an `unsigned char` array
+/// when `unsigned` is set. Bytes outside the ASCII range are negative as `char`; Python 2 doc
+/// strings, for example, contain non-ASCII chars (https://git.io/fjAxu).
+fn string_byte_expr(c: u8, unsigned: bool) -> P<Expr> {
+    let val = if unsigned { c as i16 } else { c as i8 as i16 };
+    let lit = mk().lit_expr(mk().int_lit(val.abs() as u128, LitIntType::Unsuffixed));
+    if val < 0 {
+        mk().unary_expr("-", lit)
+    } else {
+        lit
+    }
+}
+
+/// Build `*b"..."` for an array of `elem` initialized by a string literal with the given bytes.
+/// A byte string only has the right type when the elements are `u8`, so anything other than
+/// an `unsigned char` array gets `None`.
+fn byte_string_array(elem: &CTypeKind, width: u8, bytes: &[u8]) -> Option<P<Expr>> {
+    if width != 1 || *elem != CTypeKind::UChar {
+        return None;
+    }
+    Some(mk().unary_expr(ast::UnOp::Deref, mk().lit_expr(bytes.to_vec())))
+}
+
+/// Format a finite float as the shortest Rust literal that parses back to exactly `val`.
+fn float_literal_text<F>(val: F) -> String
+where
+    F: Copy + Into<f64> + fmt::Display + fmt::LowerExp,
+{
+    // `Display` drops the sign of negative zero
+    if val.into() == 0.0 && val.into().is_sign_negative() {
+        return "-0.0".to_string();
+    }
+
+    // `Display` never uses an exponent, which gets unwieldy for very large or small values
+    let abs = val.into().abs();
+    let mut text = if abs != 0.0 && (abs < 1e-5 || abs >= 1e16) {
+        format!("{:e}", val)
+    } else {
+        format!("{}", val)
+    };
+    if !text.contains(|c| c == '.' || c == 'e') {
+        text.push_str(".0");
+    }
+    text
+}
+
+/// Get the IEEE binary128 bits of the value of `val`. Every `f64` is exactly representable.
+fn f64_to_f128_bits(val: f64) -> u128 {
+    let bits = val.to_bits();
+    let sign = u128::from(bits >> 63);
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = u128::from(bits & ((1 << 52) - 1));
+
+    let (exponent, fraction) = match exponent {
+        0 if fraction == 0 => (0, 0),
+        // Subnormal `f64`s are normal in binary128, so shift out the leading one
+        0 => {
+            let top_bit = 127 - fraction.leading_zeros() as i32;
+            let fraction = (fraction & !(1 << top_bit)) << (112 - top_bit);
+            (top_bit - 1074 + 16383, fraction)
+        }
+        0x7ff => (0x7fff, fraction << 60),
+        _ => (exponent - 1023 + 16383, fraction << 60),
+    };
+
+    (sign << 127) | ((exponent as u128) << 112) | fraction
+}
+
+/// Source text for an integer literal in the given base, or `None` when the
+/// default decimal rendering should be used. With `group_digits`, decimal
+/// literals get a `_` separator every three digits and hex ones every four.
+fn int_lit_text(val: u128, base: IntBase, group_digits: bool) -> Option<String> {
+    match base {
+        IntBase::Dec if group_digits && val >= 1000 => Some(group(&val.to_string(), 3)),
+        IntBase::Dec => None,
+        IntBase::Hex if group_digits => Some(format!("0x{}", group(&format!("{:x}", val), 4))),
+        IntBase::Hex => Some(format!("0x{:x}", val)),
+        IntBase::Oct => Some(format!("0o{:o}", val)),
+        IntBase::Bin => Some(format!("0b{:b}", val)),
+    }
+}
+
+/// Insert a `_` between every `size` digits, counting from the right.
+fn group(digits: &str, size: usize) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / size);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % size == 0 {
+            grouped.push('_');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Spell `lit` the way it could appear in C source, including the suffix its type `ty`
+/// needs. Floating literals keep the spelling clang recorded for them when there is one.
+pub fn c_literal_spelling(lit: &CLiteral, ty: &CTypeKind) -> String {
+    match *lit {
+        CLiteral::Integer(val, base) => {
+            let digits = match base {
+                IntBase::Dec => format!("{}", val),
+                IntBase::Hex => format!("0x{:x}", val),
+                IntBase::Oct if val == 0 => "0".to_string(),
+                IntBase::Oct => format!("0{:o}", val),
+                IntBase::Bin => format!("0b{:b}", val),
+            };
+            let suffix = match ty {
+                CTypeKind::UInt => "U",
+                CTypeKind::Long => "L",
+                CTypeKind::ULong => "UL",
+                CTypeKind::LongLong => "LL",
+                CTypeKind::ULongLong => "ULL",
+                _ => "",
+            };
+            digits + suffix
+        }
+        CLiteral::Character(val) => {
+            let mut text = "'".to_string();
+            push_c_char_escape(&mut text, val as u32, '\'');
+            text.push('\'');
+            text
+        }
+        CLiteral::MultiCharacter(_, ref spelling) => spelling.clone(),
+        CLiteral::Floating(_, ref spelling) if !spelling.is_empty() => spelling.clone(),
+        CLiteral::Floating(val, _) => {
+            let suffix = match ty {
+                CTypeKind::Float => "f",
+                CTypeKind::LongDouble => "L",
+                _ => "",
+            };
+            float_literal_text(val) + suffix
+        }
+        CLiteral::Imaginary(val) => float_literal_text(val) + "i",
+        CLiteral::String(ref bytes, width) => {
+            let mut text = match width {
+                1 => "\"",
+                2 => "u\"",
+                _ => "U\"",
+            }
+            .to_string();
+            let mut after_hex_escape = false;
+            for unit in wide_string_units(bytes, width) {
+                let is_hex_digit = char::from_u32(unit).map_or(false, |c| c.is_ascii_hexdigit());
+                if after_hex_escape && is_hex_digit {
+                    // Split the literal so the digit doesn't extend the escape before it
+                    text.push_str("\" \"");
+                }
+                after_hex_escape = push_c_char_escape(&mut text, unit, '"');
+            }
+            text.push('"');
+            text
+        }
+    }
+}
+
+/// Append the C spelling of the code unit `unit` to a character or string literal delimited by
+/// `quote`, returning whether it was spelled as a hex escape. Anything other than printable
+/// ASCII is escaped. Bytes use octal escapes since, unlike hex escapes, they can't run into a
+/// following digit, and universal character names are only used for the values C allows them
+/// to name.
+fn push_c_char_escape(text: &mut String, unit: u32, quote: char) -> bool {
+    match unit {
+        0x0a => text.push_str("\\n"),
+        0x09 => text.push_str("\\t"),
+        0x5c => text.push_str("\\\\"),
+        _ if unit == quote as u32 => {
+            text.push('\\');
+            text.push(quote);
+        }
+        0x20..=0x7e => text.push(unit as u8 as char),
+        0..=0xff => text.push_str(&format!("\\{:03o}", unit)),
+        // Surrogates and values past the end of Unicode, e.g. the halves of a UTF-16 pair
+        0xd800..=0xdfff | 0x11_0000..=0xffff_ffff => {
+            text.push_str(&format!("\\x{:x}", unit));
+            return true;
+        }
+        0x100..=0xffff => text.push_str(&format!("\\u{:04x}", unit)),
+        _ => text.push_str(&format!("\\U{:08x}", unit)),
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use super::*;
+
+    /// Deterministic xorshift generator so the test needs no extra dependencies
+    fn random_bits(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn float_literals_round_trip() {
+        let mut state = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..100_000 {
+            let val = f64::from_bits(random_bits(&mut state));
+            if !val.is_finite() {
+                continue;
+            }
+            let text = float_literal_text(val);
+            assert_eq!(text.parse::<f64>().unwrap().to_bits(), val.to_bits(), "{}", text);
+
+            let val = val as f32;
+            if val.is_finite() {
+                let text = float_literal_text(val);
+                assert_eq!(text.parse::<f32>().unwrap().to_bits(), val.to_bits(), "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn float_literal_forms() {
+        assert_eq!(float_literal_text(1.0f64), "1.0");
+        assert_eq!(float_literal_text(0.1f64), "0.1");
+        assert_eq!(float_literal_text(0.0f64), "0.0");
+        assert_eq!(float_literal_text(1e300f64), "1e300");
+        assert_eq!(float_literal_text(0.1f32), "0.1");
+    }
+
+    #[test]
+    fn negative_zero_literals() {
+        assert_eq!(float_literal_text(-0.0f64), "-0.0");
+        assert_eq!(float_literal_text(-0.0f32), "-0.0");
+
+        syntax::with_default_globals(|| {
+            let f64_zero = pprust::expr_to_string(&float_lit_expr(-0.0, FloatTy::F64));
+            assert_eq!(f64_zero, "-0.0f64");
+            let f32_zero = pprust::expr_to_string(&float_lit_expr(-0.0, FloatTy::F32));
+            assert_eq!(f32_zero, "-0.0f32");
+            assert_eq!(pprust::expr_to_string(&float_lit_expr(0.0, FloatTy::F64)), "0.0f64");
+
+            // The emitted text must keep the sign bit once Rust parses it back
+            let parsed = f64_zero.trim_end_matches("f64").parse::<f64>().unwrap();
+            assert_eq!(parsed.to_bits(), (-0.0f64).to_bits());
+            let parsed = f32_zero.trim_end_matches("f32").parse::<f32>().unwrap();
+            assert_eq!(parsed.to_bits(), (-0.0f32).to_bits());
+        });
+    }
+
+    #[test]
+    fn f128_bits() {
+        assert_eq!(f64_to_f128_bits(0.0), 0);
+        assert_eq!(f64_to_f128_bits(1.0), 0x3fff_0000_0000_0000_0000_0000_0000_0000);
+        assert_eq!(f64_to_f128_bits(-2.0), 0xc000_0000_0000_0000_0000_0000_0000_0000);
+        assert_eq!(f64_to_f128_bits(f64::from_bits(1)), 0x3bcd_0000_0000_0000_0000_0000_0000_0000);
+    }
+
+    #[test]
+    fn int_literal_bases() {
+        let decimal = LiteralBaseMode::Decimal;
+        let hex = LiteralBaseMode::Hex;
+        let preserve = LiteralBaseMode::PreserveSource;
+
+        assert_eq!(int_lit_text(255, decimal.apply(IntBase::Hex), false), None);
+        assert_eq!(int_lit_text(255, hex.apply(IntBase::Dec), false), Some("0xff".to_string()));
+        assert_eq!(int_lit_text(8, hex.apply(IntBase::Oct), false), Some("0x8".to_string()));
+        assert_eq!(int_lit_text(8, preserve.apply(IntBase::Oct), false), Some("0o10".to_string()));
+        assert_eq!(int_lit_text(5, preserve.apply(IntBase::Bin), false), Some("0b101".to_string()));
+        assert_eq!(int_lit_text(5, preserve.apply(IntBase::Dec), false), None);
+    }
+
+    #[test]
+    fn over_long_array_initializers() {
+        assert!(check_array_init_len(0, 3).is_ok());
+        assert!(check_array_init_len(3, 3).is_ok());
+        let err = check_array_init_len(4, 3).unwrap_err().to_string();
+        assert!(
+            err.contains("Array initializer has 4 elements, but the array only holds 3"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn narrowing_literal_casts() {
+        // `signed char c = 300;`
+        assert_eq!(truncated_constant(300, 8, true), Some(44));
+        assert_eq!(truncated_constant(200, 8, true), Some(-56));
+        assert_eq!(truncated_constant(-1, 8, false), Some(255));
+        assert_eq!(truncated_constant(127, 8, true), None);
+        assert_eq!(truncated_constant(-128, 8, true), None);
+        assert_eq!(truncated_constant(255, 8, false), None);
+
+        syntax::with_default_globals(|| {
+            let err = narrowing_literal_error(300, "libc::c_schar");
+            assert_eq!(
+                pprust::expr_to_string(&err),
+                r#"compile_error!("integer constant 300 does not fit in libc::c_schar")"#
+            );
+        });
+    }
+
+    #[test]
+    fn grouped_int_literals() {
+        let grouped = |val, base| int_lit_text(val, base, true);
+
+        assert_eq!(grouped(7, IntBase::Dec), None);
+        assert_eq!(grouped(999, IntBase::Dec), None);
+        assert_eq!(grouped(1000, IntBase::Dec), Some("1_000".to_string()));
+        assert_eq!(grouped(1000000, IntBase::Dec), Some("1_000_000".to_string()));
+        assert_eq!(grouped(12345678, IntBase::Dec), Some("12_345_678".to_string()));
+        assert_eq!(grouped(0xff, IntBase::Hex), Some("0xff".to_string()));
+        assert_eq!(grouped(0xffff, IntBase::Hex), Some("0xffff".to_string()));
+        assert_eq!(grouped(0x12345678, IntBase::Hex), Some("0x1234_5678".to_string()));
+        assert_eq!(grouped(0x1_0000_0000, IntBase::Hex), Some("0x1_0000_0000".to_string()));
+
+        // Grouped literals still denote the same value
+        for &val in &[1000u64, 65536, 1234567, u64::max_value()] {
+            let text = grouped(val.into(), IntBase::Dec).unwrap().replace('_', "");
+            assert_eq!(text.parse::<u64>().unwrap(), val);
+            let text = grouped(val.into(), IntBase::Hex).unwrap().replace('_', "");
+            assert_eq!(u64::from_str_radix(&text[2..], 16).unwrap(), val);
+        }
+    }
+
+    #[test]
+    fn int128_literals() {
+        let max = u128::max_value();
+        assert_eq!(
+            int_lit_text(max, IntBase::Hex, false),
+            Some(format!("0x{}", "f".repeat(32))),
+        );
+        assert_eq!(int_lit_text(max, IntBase::Dec, true).unwrap().replace('_', ""), max.to_string());
+
+        // Only values past the signed maximum of the target width wrap negative
+        assert_eq!(negative_wrapped_magnitude(u64::max_value().into(), 128), None);
+        assert_eq!(negative_wrapped_magnitude(max, 128), Some(1));
+        assert_eq!(negative_wrapped_magnitude(1 << 127, 128), Some(1 << 127));
+        assert_eq!(negative_wrapped_magnitude(i64::max_value() as u128, 64), None);
+        assert_eq!(negative_wrapped_magnitude(u64::max_value().into(), 64), Some(1));
+        assert_eq!(negative_wrapped_magnitude(1 << 63, 64), Some(1 << 63));
+    }
+
+    #[test]
+    fn wrapped_signed_int_literals() {
+        syntax::with_default_globals(|| {
+            let mut ast_context = TypedAstContext::new(&[]);
+            let long_long = CQualTypeId::new(ast_context.add_type(CTypeKind::LongLong));
+            let long = CQualTypeId::new(ast_context.add_type(CTypeKind::Long));
+
+            let tcfg = crate::TranspilerConfig::for_tests();
+            let t = Translation::new(ast_context, &tcfg, std::path::Path::new("test.c"));
+            let lit = |ty, val| pprust::expr_to_string(&t.mk_int_lit(ty, val, IntBase::Hex).unwrap());
+
+            assert_eq!(lit(long_long, u64::max_value().into()), "-1i64");
+            assert_eq!(lit(long_long, 1 << 63), "-9223372036854775808i64");
+            assert_eq!(lit(long, u64::max_value().into()), "-1 as libc::c_long");
+            // Values that fit are left alone
+            assert_eq!(lit(long_long, 0x7fff_ffff_ffff_ffff), "0x7fffffffffffffffi64");
+
+            let comments = t.comment_store.replace(CommentStore::new()).into_comments();
+            let lines: Vec<_> = comments.iter().flat_map(|c| c.lines.iter()).collect();
+            assert_eq!(
+                lines,
+                vec![
+                    "// 0xffffffffffffffff wrapped to a signed value",
+                    "// 0x8000000000000000 wrapped to a signed value",
+                    "// 0xffffffffffffffff wrapped to a signed value",
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn integer_tables() {
+        syntax::with_default_globals(|| {
+            let mut ast_context = TypedAstContext::new(&[]);
+            let int = CQualTypeId::new(ast_context.add_type(CTypeKind::Int));
+            let uchar = ast_context.add_type(CTypeKind::UChar);
+            let schar = ast_context.add_type(CTypeKind::SChar);
+            let short = ast_context.add_type(CTypeKind::Short);
+
+            // An `int` literal converted to the element type `ty`, negated first if `negate`
+            let mut element = |ty, val, base, negate| {
+                let lit = CExprKind::Literal(int, CLiteral::Integer(val, base));
+                let mut e = ast_context.add_expr(lit);
+                if negate {
+                    let neg = CExprKind::Unary(int, c_ast::UnOp::Negate, e, LRValue::RValue);
+                    e = ast_context.add_expr(neg);
+                }
+                let cast = CExprKind::ImplicitCast(
+                    CQualTypeId::new(ty),
+                    e,
+                    CastKind::IntegralCast,
+                    None,
+                    LRValue::RValue,
+                );
+                Some(ast_context.add_expr(cast))
+            };
+            let bytes = vec![
+                element(uchar, 0xff, IntBase::Hex, false),
+                element(uchar, 0o10, IntBase::Oct, false),
+                None,
+            ];
+            let shorts = vec![
+                element(short, 32768, IntBase::Dec, true),
+                element(short, 5, IntBase::Dec, false),
+            ];
+            let negative_bytes = vec![element(uchar, 1, IntBase::Dec, true)];
+            let too_big = vec![
+                element(schar, 300, IntBase::Dec, false),
+                element(schar, 1, IntBase::Dec, false),
+            ];
+
+            let mut tcfg = crate::TranspilerConfig::for_tests();
+            tcfg.emit_provenance = true;
+            let t = Translation::new(ast_context.clone(), &tcfg, std::path::Path::new("test.c"));
+            let table = |t: &Translation, ty, elements: &[_]| {
+                t.convert_integer_table(ty, elements).map(|e| pprust::expr_to_string(&e))
+            };
+
+            // Elements keep their source base, and only the first carries a suffix
+            assert_eq!(table(&t, uchar, &bytes).unwrap(), "[0xffu8, 0o10, 0]");
+            assert_eq!(table(&t, short, &shorts).unwrap(), "[-32768i16, 5]");
+            assert_eq!(t.literal_provenance().unwrap().len(), 4);
+            // Values that don't fit in the element type are left to the usual cast, which
+            // `--narrowing-literals` reports
+            assert_eq!(table(&t, uchar, &negative_bytes), None);
+            assert_eq!(table(&t, schar, &too_big), None);
+
+            tcfg.literal_base = LiteralBaseMode::Decimal;
+            let mut t = Translation::new(ast_context, &tcfg, std::path::Path::new("test.c"));
+            t.set_literal_hook(Box::new(|_, _, e| {
+                mk().call_expr(mk().path_expr(vec!["named"]), vec![e])
+            }));
+            assert_eq!(table(&t, uchar, &bytes).unwrap(), "[named(255u8), named(8), 0]");
+        });
+    }
+
+    #[test]
+    fn literal_hook_replaces_literals() {
+        use std::cell::Cell;
+
+        syntax::with_default_globals(|| {
+            let fired = Rc::new(Cell::new(0));
+            let counter = fired.clone();
+            let hook: LiteralHook = Box::new(move |kind, _ty, expr| match *kind {
+                CLiteral::Integer(..) => {
+                    counter.set(counter.get() + 1);
+                    let tokens = vec![TokenTree::token(
+                        token::Interpolated(Rc::new(Nonterminal::NtExpr(expr))),
+                        DUMMY_SP,
+                    )]
+                    .into_iter()
+                    .collect::<TokenStream>();
+                    let mac = mk().mac(vec!["named_constant"], tokens, MacDelimiter::Parenthesis);
+                    mk().mac_expr(mac)
+                }
+                _ => expr,
+            });
+            let convert = |hook: Option<&LiteralHook>, kind: &CLiteral, expr: P<Expr>| {
+                let val = apply_literal_hook(hook, kind, &CTypeKind::Int, WithStmts::new_val(expr));
+                pprust::expr_to_string(&val.to_expr())
+            };
+
+            let int = CLiteral::Integer(42, IntBase::Dec);
+            let int_expr = || mk().lit_expr(mk().int_lit(42, "i32"));
+            assert_eq!(convert(Some(&hook), &int, int_expr()), "named_constant!(42i32)");
+            assert_eq!(fired.get(), 1);
+
+            // Literals the hook passes over, and translation without a hook, are unchanged
+            let float = CLiteral::Floating(1.5, "1.5".to_string());
+            let float_expr = mk().lit_expr(mk().float_lit("1.5", FloatTy::F64));
+            assert_eq!(convert(Some(&hook), &float, float_expr), "1.5f64");
+            assert_eq!(convert(None, &int, int_expr()), "42i32");
+            assert_eq!(fired.get(), 1);
+        });
+    }
+
+    #[test]
+    fn used_string_literal_statics() {
+        syntax::with_default_globals(|| {
+            let item = string_literal_static_item("STRING_LITERAL", b"hi", StringLiteralStorage::UsedStatic);
+            assert_eq!(
+                pprust::item_to_string(&item),
+                r#"#[used]
+static STRING_LITERAL: [u8; 3] = *b"hi\x00";"#
+            );
+
+            let item = string_literal_static_item("STRING_LITERAL", b"hi", StringLiteralStorage::Static);
+            assert!(!pprust::item_to_string(&item).contains("#[used]"));
+
+            let item = string_literal_static_item("STRING_LITERAL", b"hi", StringLiteralStorage::Const);
+            assert_eq!(
+                pprust::item_to_string(&item),
+                r#"const STRING_LITERAL: [u8; 3] = *b"hi\x00";"#
+            );
+        });
+    }
+
+    #[test]
+    fn string_literal_statics_by_context() {
+        syntax::with_default_globals(|| {
+            let tcfg = crate::TranspilerConfig::for_tests();
+            let t = Translation::new(TypedAstContext::new(&[]), &tcfg, std::path::Path::new("test.c"));
+
+            // Uses in the same kind of context share one item, constants get their own
+            let in_static = t.string_literal_static(b"hi", false);
+            assert_eq!(t.string_literal_static(b"hi", false), in_static);
+            let in_const = t.string_literal_static(b"hi", true);
+            assert_ne!(in_const, in_static);
+            assert_eq!(t.string_literal_static(b"hi", true), in_const);
+        });
+    }
+
+    #[test]
+    fn multi_character_literals() {
+        assert!(is_multi_character_literal("'ABCD'"));
+        assert!(is_multi_character_literal("'AB'"));
+        assert!(is_multi_character_literal(r"'\x01\x02'"));
+        assert!(is_multi_character_literal(r"'\nA'"));
+
+        assert!(!is_multi_character_literal("'A'"));
+        assert!(!is_multi_character_literal(r"'\''"));
+        assert!(!is_multi_character_literal(r"'\377'"));
+        assert!(!is_multi_character_literal(r"'\x41B'"));
+        assert!(!is_multi_character_literal("L'AB'"));
+    }
+
+    #[test]
+    fn multi_character_comments() {
+        assert_eq!(multi_character_comment("'ABCD'"), "/* 'ABCD' */ ");
+        assert_eq!(multi_character_comment("'*/'"), r"/* '*\/' */ ");
+        assert_eq!(multi_character_comment("'/*'"), r"/* '/\*' */ ");
+        assert_eq!(multi_character_comment("'*/*'"), r"/* '*\/\*' */ ");
+    }
+
+    #[test]
+    fn literal_spelling_pairs() {
+        syntax::with_default_globals(|| {
+            let mut ast_context = TypedAstContext::new(&[]);
+            let uint = CQualTypeId::new(ast_context.add_type(CTypeKind::UInt));
+            let float = CQualTypeId::new(ast_context.add_type(CTypeKind::Float));
+            let tcfg = crate::TranspilerConfig::for_tests();
+            let t = Translation::new(ast_context, &tcfg, std::path::Path::new("test.c"));
+
+            let hex = CLiteral::Integer(26, IntBase::Hex);
+            assert_eq!(
+                t.literal_spellings(uint, &hex).unwrap(),
+                ("0x1aU".to_string(), "0x1au32".to_string()),
+            );
+
+            let float_lit = CLiteral::Floating(2.5, String::new());
+            assert_eq!(
+                t.literal_spellings(float, &float_lit).unwrap(),
+                ("2.5f".to_string(), "2.5f32".to_string()),
+            );
+        });
+
+        let octal = CLiteral::Integer(8, IntBase::Oct);
+        assert_eq!(c_literal_spelling(&octal, &CTypeKind::Long), "010L");
+        assert_eq!(c_literal_spelling(&CLiteral::Character(39), &CTypeKind::Int), r"'\''");
+        assert_eq!(
+            c_literal_spelling(&CLiteral::String(b"a\"\x01".to_vec(), 1), &CTypeKind::Char),
+            r#""a\"\001""#,
+        );
+
+        // Surrogates can't be universal character names, and a hex escape must not swallow
+        // the digit after it
+        let utf16 = |units: &[u16]| -> CLiteral {
+            let bytes = units.iter().flat_map(|u| u.to_le_bytes().to_vec()).collect();
+            CLiteral::String(bytes, 2)
+        };
+        assert_eq!(
+            c_literal_spelling(&utf16(&[0xd83d, 0xde00, 0x41, 0x67]), &CTypeKind::UShort),
+            r#"u"\xd83d\xde00" "Ag""#,
+        );
+        assert_eq!(
+            c_literal_spelling(&utf16(&[0x7f, 0xe9, 0x3b1]), &CTypeKind::UShort),
+            r#"u"\177\351\u03b1""#,
+        );
+    }
+
+    #[test]
+    fn byte_string_arrays() {
+        syntax::with_default_globals(|| {
+            let array = byte_string_array(&CTypeKind::UChar, 1, b"hi\0").unwrap();
+            assert_eq!(pprust::expr_to_string(&array), r#"*b"hi\x00""#);
+        });
+
+        // `char` and `signed char` arrays are `i8`, which a byte string can't initialize
+        assert!(byte_string_array(&CTypeKind::Char, 1, b"hi\0").is_none());
+        assert!(byte_string_array(&CTypeKind::SChar, 1, b"hi\0").is_none());
+        assert!(byte_string_array(&CTypeKind::UShort, 2, b"h\0\0\0").is_none());
+    }
+
+    #[test]
+    fn string_bytes_round_trip() {
+        syntax::with_default_globals(|| {
+            for c in 0..=255u8 {
+                let text = pprust::expr_to_string(&string_byte_expr(c, false));
+                assert_eq!(text.parse::<i8>(), Ok(c as i8), "{}", text);
+                let text = pprust::expr_to_string(&string_byte_expr(c, true));
+                assert_eq!(text.parse::<u8>(), Ok(c), "{}", text);
+            }
+        });
+    }
+
+    #[test]
+    fn enum_variants_match_exact_values() {
+        let mut variants = EnumVariants {
+            by_value: HashMap::new(),
+            in_order: vec![],
+        };
+        // `BIG` would be `-1` if reinterpreted as an `i64`
+        variants.insert(CDeclId(1), ConstIntExpr::U(u64::max_value()));
+        variants.insert(CDeclId(2), ConstIntExpr::I(-1));
+        variants.insert(CDeclId(3), ConstIntExpr::U(1));
+
+        assert_eq!(variants.get(-1), Some(CDeclId(2)));
+        assert_eq!(variants.get(1), Some(CDeclId(3)));
+        assert_eq!(variants.get(-2), None);
+        assert_eq!(variants.get(2), None);
+        assert_eq!(variants.in_order, vec![(CDeclId(2), -1), (CDeclId(3), 1)]);
+    }
+
+    #[test]
+    fn enum_fallback_values() {
+        syntax::with_default_globals(|| {
+            let text = |ty, value| pprust::expr_to_string(&enum_fallback_value(&ty, value));
+            assert_eq!(text(CTypeKind::Int, -2), "-2");
+            assert_eq!(text(CTypeKind::Long, i64::min_value()), "-9223372036854775808");
+            assert_eq!(text(CTypeKind::UChar, -1), "255");
+            assert_eq!(text(CTypeKind::UInt, -1), "4294967295");
+            assert_eq!(text(CTypeKind::ULong, -1), "18446744073709551615");
+            assert_eq!(text(CTypeKind::UInt, 7), "7");
+        });
+    }
+
+    #[test]
+    fn enum_for_i64_errors() {
+        syntax::with_default_globals(|| {
+            let mut ast_context = TypedAstContext::new(&[]);
+            let int = ast_context.add_type(CTypeKind::Int);
+            // `enum forward;` has neither variants nor an underlying type
+            let forward = ast_context.add_top_decl(CDeclKind::Enum {
+                name: Some("forward".into()),
+                variants: vec![],
+                integral_type: None,
+            });
+            let forward_ty = ast_context.add_type(CTypeKind::Enum(forward));
+            let constant = ast_context.add_top_decl(CDeclKind::EnumConstant {
+                name: "CONSTANT".into(),
+                value: ConstIntExpr::I(3),
+            });
+            let not_an_enum = ast_context.add_type(CTypeKind::Enum(constant));
+
+            let tcfg = crate::TranspilerConfig::for_tests();
+            let t = Translation::new(ast_context, &tcfg, std::path::Path::new("test.c"));
+            let err = |ty, value| t.enum_for_i64(ty, value).unwrap_err().to_string();
+
+            let msg = err(forward_ty, 3);
+            let expected = format!("Attempt to construct value 3 of forward declared enum {:?}", forward);
+            assert!(msg.contains(&expected), "{}", msg);
+            let msg = err(int, 3);
+            assert!(msg.contains("Int does not point to an `enum` type"), "{}", msg);
+            let msg = err(not_an_enum, 3);
+            assert!(msg.contains("does not point to an `enum` declaration"), "{}", msg);
+        });
+    }
+
+    #[test]
+    fn literal_to_rust_by_kind() {
+        syntax::with_default_globals(|| {
+            let mut ast_context = TypedAstContext::new(&[]);
+            let mut add = |kind| CQualTypeId::new(ast_context.add_type(kind));
+            let int = add(CTypeKind::Int);
+            let uint = add(CTypeKind::UInt);
+            let float = add(CTypeKind::Float);
+            let double = add(CTypeKind::Double);
+            let char_ty = add(CTypeKind::Char);
+            let string = add(CTypeKind::ConstantArray(char_ty.ctype, 3));
+            let complex = add(CTypeKind::Complex(double.ctype));
+            let int_complex = add(CTypeKind::Complex(int.ctype));
+
+            let tcfg = crate::TranspilerConfig::for_tests();
+            let t = Translation::new(ast_context, &tcfg, std::path::Path::new("test.c"));
+            let rust = |ty, lit| t.literal_to_rust(ty, &lit);
+
+            assert_eq!(rust(int, CLiteral::Integer(42, IntBase::Dec)).unwrap(), "42i32");
+            assert_eq!(rust(uint, CLiteral::Integer(255, IntBase::Hex)).unwrap(), "0xffu32");
+            assert_eq!(rust(int, CLiteral::Character('A' as u64)).unwrap(), "65i32");
+            assert_eq!(rust(double, CLiteral::Floating(1.5, "1.5".into())).unwrap(), "1.5f64");
+            assert_eq!(rust(float, CLiteral::Floating(0.1, String::new())).unwrap(), "0.1f32");
+            assert_eq!(
+                rust(string, CLiteral::String(b"hi".to_vec(), 1)).unwrap(),
+                r#"*::std::mem::transmute::<&[u8; 3], &mut [libc::c_char; 3]>(b"hi\x00")"#
+            );
+            assert_eq!(
+                rust(complex, CLiteral::Imaginary(2.0)).unwrap(),
+                "num_complex::Complex{re: 0., im: 2.0f64,}"
+            );
+
+            let err = rust(int_complex, CLiteral::Imaginary(2.0)).unwrap_err().to_string();
+            assert!(err.contains("Unsupported imaginary literal"), "{}", err);
+            let err = rust(int, CLiteral::String(b"hi".to_vec(), 3)).unwrap_err().to_string();
+            assert!(err.contains("Unsupported string literal element width 3"), "{}", err);
+            let err = rust(double, CLiteral::Imaginary(2.0)).unwrap_err().to_string();
+            assert!(err.contains("Unsupported imaginary literal type Double"), "{}", err);
+            let err = rust(int, CLiteral::Floating(1.5, "1.5".into())).unwrap_err().to_string();
+            assert!(err.contains("Unsupported floating point literal type Int"), "{}", err);
+        });
+    }
+
+    /// A 500-variant enum whose every value is constructed once, along with the enum type.
+    fn large_enum(ast_context: &mut TypedAstContext) -> (CTypeId, Vec<CEnumConstantId>) {
+        let int = CQualTypeId::new(ast_context.add_type(CTypeKind::Int));
+        let variants: Vec<_> = (0..500)
+            .map(|i| {
+                ast_context.add_top_decl(CDeclKind::EnumConstant {
+                    name: format!("V{}", i),
+                    value: ConstIntExpr::I(i),
+                })
+            })
+            .collect();
+        let def_id = ast_context.add_top_decl(CDeclKind::Enum {
+            name: Some("large".into()),
+            variants: variants.clone(),
+            integral_type: Some(int),
+        });
+        (ast_context.add_type(CTypeKind::Enum(def_id)), variants)
+    }
+
+    #[bench]
+    fn enum_for_i64_large_enum(b: &mut test::Bencher) {
+        syntax::with_default_globals(|| {
+            let mut ast_context = TypedAstContext::new(&[]);
+            let (ty, variants) = large_enum(&mut ast_context);
+
+            let tcfg = crate::TranspilerConfig::for_tests();
+            let t = Translation::new(ast_context, &tcfg, std::path::Path::new("test.c"));
+            for (i, &variant) in variants.iter().enumerate() {
+                t.renamer.borrow_mut().insert(variant, &format!("V{}", i));
+            }
+
+            b.iter(|| {
+                for value in 0..500 {
+                    test::black_box(t.enum_for_i64(ty, value).unwrap());
+                }
+            });
+        });
+    }
+
+    /// The same uses as `enum_for_i64_large_enum`, looking each value up by scanning the
+    /// variants as `enum_for_i64` did before they were indexed, for comparison.
+    #[bench]
+    fn enum_variant_scan_large_enum(b: &mut test::Bencher) {
+        let mut ast_context = TypedAstContext::new(&[]);
+        let (_, variants) = large_enum(&mut ast_context);
+
+        b.iter(|| {
+            for value in 0..500 {
+                let found = variants.iter().find(|&&id| match ast_context[id].kind {
+                    CDeclKind::EnumConstant { value: v, .. } => {
+                        v == ConstIntExpr::I(value) || v == ConstIntExpr::U(value as u64)
+                    }
+                    _ => false,
+                });
+                test::black_box(found);
+            }
+        });
+    }
+}
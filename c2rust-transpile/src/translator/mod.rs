@@ -1,13 +1,11 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::mem;
 use std::ops::Index;
 use std::path::{self, PathBuf};
 use std::rc::Rc;
 use std::char;
 
-use dtoa;
-
 use failure::{err_msg, Fail};
 use indexmap::{IndexMap, IndexSet};
 
@@ -51,6 +49,7 @@ mod simd;
 mod structs;
 mod variadic;
 
+use self::literals::EnumVariants;
 pub use crate::diagnostics::{TranslationError, TranslationErrorKind};
 use crate::CrateSet;
 use crate::PragmaVec;
@@ -103,6 +102,37 @@ pub enum ReplaceMode {
     Extern,
 }
 
+/// Base in which integer literals are written in the translated code
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LiteralBaseMode {
+    /// Keep the base the literal was written in in the C source
+    PreserveSource,
+    Decimal,
+    Hex,
+}
+
+impl LiteralBaseMode {
+    /// Pick the base to emit for a literal written in `source` base in C
+    pub fn apply(self, source: IntBase) -> IntBase {
+        match self {
+            LiteralBaseMode::PreserveSource => source,
+            LiteralBaseMode::Decimal => IntBase::Dec,
+            LiteralBaseMode::Hex => IntBase::Hex,
+        }
+    }
+}
+
+/// How casts of integer constants to types too narrow to hold them are translated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NarrowingLiteralMode {
+    /// Emit a plain `as` cast, which truncates like the C conversion
+    Truncate,
+    /// Emit the `as` cast with a comment noting the value it truncates to
+    Warn,
+    /// Emit a `compile_error!` in place of the cast
+    Strict,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct ExprContext {
     used: bool,
@@ -246,6 +276,51 @@ struct MacroExpansion {
     ty: CTypeId,
 }
 
+/// How the item shared by the uses of a repeated string literal is declared
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum StringLiteralStorage {
+    Static,
+    /// A `#[used]` static, for literals referenced from outside of Rust
+    UsedStatic,
+    /// A constant, for literals in constant contexts, which cannot refer to statics
+    Const,
+}
+
+/// Callback for post-processing literals, see `Translation::set_literal_hook`. It receives the
+/// C literal, its resolved type, and the expression the transpiler would emit, and returns the
+/// expression to use instead.
+pub type LiteralHook = Box<dyn Fn(&CLiteral, &CTypeKind, P<Expr>) -> P<Expr>>;
+
+/// Where a literal in the translated output came from, recorded when `--emit-provenance` is set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiteralProvenance {
+    pub expr_id: CExprId,
+    /// The C file containing the literal, if clang gave it a location
+    pub file: Option<PathBuf>,
+    pub line: u64,
+    pub column: u64,
+    /// The Rust expression emitted for the literal
+    pub rust: String,
+}
+
+/// Render literal provenance records as the JSON written next to the translation with
+/// `--emit-provenance`.
+pub fn provenance_json(records: &[LiteralProvenance]) -> String {
+    let records: Vec<_> = records
+        .iter()
+        .map(|record| {
+            serde_json::json!({
+                "expr_id": record.expr_id.0,
+                "file": record.file.as_ref().map(|file| file.display().to_string()),
+                "line": record.line,
+                "column": record.column,
+                "rust": record.rust,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&records).unwrap()
+}
+
 pub struct Translation<'c> {
     // Translation environment
     pub ast_context: TypedAstContext,
@@ -260,9 +335,19 @@ pub struct Translation<'c> {
     type_converter: RefCell<TypeConverter>,
     renamer: RefCell<Renamer<CDeclId>>,
     zero_inits: RefCell<IndexMap<CDeclId, WithStmts<P<Expr>>>>,
+    enum_variants: RefCell<HashMap<CEnumId, EnumVariants>>,
+    enum_from_i64_fns: RefCell<HashMap<(FileId, CEnumId), String>>,
+    repeated_strings: HashSet<Vec<u8>>,
+    string_statics: RefCell<HashMap<(FileId, Vec<u8>, StringLiteralStorage), String>>,
+    // Whether string literals being translated are referenced from outside of Rust, e.g. by
+    // inline assembly, so that their statics need `#[used]` to survive
+    string_literals_escape: Cell<bool>,
     function_context: RefCell<FunContext>,
     potential_flexible_array_members: RefCell<IndexSet<CDeclId>>,
     macro_expansions: RefCell<IndexMap<CDeclId, Option<MacroExpansion>>>,
+    literal_hook: Option<LiteralHook>,
+    // Source locations of the literals emitted so far, only kept with `--emit-provenance`
+    literal_provenance: Option<RefCell<Vec<LiteralProvenance>>>,
 
     // Comment support
     pub comment_context: CommentContext, // Incoming comments
@@ -352,6 +437,29 @@ fn unwrap_function_pointer(ptr: P<Expr>) -> P<Expr> {
     mk().method_call_expr(ptr, "expect", vec![err_msg])
 }
 
+/// Contents of the narrow string literals which decay to `const` pointers in
+/// more than one place, and so are worth sharing through a single static.
+fn repeated_string_literals(ast_context: &TypedAstContext) -> HashSet<Vec<u8>> {
+    let mut seen = HashSet::new();
+    let mut repeated = HashSet::new();
+
+    for (_, expr) in ast_context.iter_exprs() {
+        if let CExprKind::ImplicitCast(ty, child, CastKind::ArrayToPointerDecay, _, _) = expr.kind {
+            let is_const = match ast_context.resolve_type(ty.ctype).kind {
+                CTypeKind::Pointer(pointee) => pointee.qualifiers.is_const,
+                _ => false,
+            };
+            if let CExprKind::Literal(_, CLiteral::String(ref bytes, 1)) = ast_context[child].kind {
+                if is_const && !seen.insert(bytes.clone()) {
+                    repeated.insert(bytes.clone());
+                }
+            }
+        }
+    }
+
+    repeated
+}
+
 fn transmute_expr(source_ty: P<Ty>, target_ty: P<Ty>, expr: P<Expr>, no_std: bool) -> P<Expr> {
     let type_args = match (&source_ty.kind, &target_ty.kind) {
         (TyKind::Infer, TyKind::Infer) => Vec::new(),
@@ -379,6 +487,15 @@ fn vec_expr(val: P<Expr>, count: P<Expr>) -> P<Expr> {
     mk().call_expr(from_elem, vec![val, count])
 }
 
+/// Build `const _: [(); c_size] = [(); size];`, which fails to compile with mismatched array
+/// lengths unless the Rust expression `size` evaluates to `c_size`.
+fn size_check_item(size: P<Expr>, c_size: u64) -> P<Item> {
+    let c_size = mk().lit_expr(mk().int_lit(c_size.into(), LitIntType::Unsuffixed));
+    let ty = mk().array_ty(mk().tuple_ty(vec![] as Vec<P<Ty>>), c_size);
+    let init = mk().repeat_expr(mk().tuple_expr(vec![] as Vec<P<Expr>>), size);
+    mk().const_item("_", ty, init)
+}
+
 pub fn stmts_block(mut stmts: Vec<Stmt>) -> P<Block> {
     if stmts.len() == 1 {
         if let StmtKind::Expr(ref e) = stmts[0].kind {
@@ -413,7 +530,8 @@ pub fn signed_int_expr(value: i64) -> P<Expr> {
     if value < 0 {
         mk().unary_expr(
             ast::UnOp::Neg,
-            mk().lit_expr(mk().int_lit((-value) as u128, "")),
+            // Widen first so that negating `i64::MIN` doesn't overflow
+            mk().lit_expr(mk().int_lit((-(value as i128)) as u128, "")),
         )
     } else {
         mk().lit_expr(mk().int_lit(value as u128, ""))
@@ -498,273 +616,128 @@ pub fn translate_failure(tcfg: &TranspilerConfig, msg: &str) {
     }
 }
 
+/// Build a doc comment recording that an item was translated from `file` at `line`, e.g.
+/// `/// from foo.c:123`.
+fn source_location_doc(file: &str, line: u64) -> Attribute {
+    Attribute {
+        id: AttrId(0),
+        style: AttrStyle::Outer,
+        kind: AttrKind::DocComment(format!("/// from {}:{}", file, line).into_symbol()),
+        span: DUMMY_SP,
+    }
+}
+
+/// The name of the function given in a variable's `cleanup` attribute, if it has one.
+fn cleanup_attr(attrs: &IndexSet<c_ast::Attribute>) -> Option<&str> {
+    attrs.iter().filter_map(|attr| match attr {
+        c_ast::Attribute::Cleanup(name) => Some(name.as_str()),
+        _ => None,
+    }).next()
+}
+
+/// Build the tuple struct `wrapper` holding a pointer to a `cleanup` variable of type `ty`,
+/// along with a `Drop` impl that passes the pointer to `cleanup_fn` when it goes out of scope.
+/// The pointer is cast to `arg_ty` when the function expects something else.
+fn cleanup_wrapper_items(
+    wrapper: &str,
+    ty: P<Ty>,
+    cleanup_fn: &str,
+    arg_ty: Option<P<Ty>>,
+) -> Vec<P<Item>> {
+    let ptr_ty = mk().mutbl().ptr_ty(ty);
+    let struct_item = mk().struct_item(wrapper, vec![mk().enum_field(ptr_ty)], true);
+
+    let mut arg = mk().field_expr(mk().ident_expr("self"), "0");
+    if let Some(arg_ty) = arg_ty {
+        arg = mk().cast_expr(arg, arg_ty);
+    }
+    let call = mk().call_expr(mk().path_expr(vec![cleanup_fn]), vec![arg]);
+    let body = mk().block(vec![mk().expr_stmt(mk().block_expr(
+        mk().unsafe_().block(vec![mk().semi_stmt(call)]),
+    ))]);
+    let decl = mk().fn_decl(
+        vec![mk().self_arg(SelfKind::Region(None, Mutability::Mutable))],
+        FunctionRetTy::Default(DUMMY_SP),
+    );
+    let drop_fn = mk().fn_impl_item("drop", decl, body);
+    let drop_impl = mk().trait_impl_item(vec!["Drop"], mk().path_ty(vec![wrapper]), vec![drop_fn]);
+
+    vec![struct_item, drop_impl]
+}
+
+/// Build an item to stand in for a declaration that failed to translate: a `compile_error!`
+/// reporting `msg` when the translated code is built.
+fn failed_decl_stub(msg: &str) -> P<Item> {
+    mk().mac_item(compile_error_mac(msg))
+}
+
+/// Build a `compile_error!` invocation reporting `msg`.
+fn compile_error_mac(msg: &str) -> Mac {
+    let tokens = vec![TokenTree::token(
+        token::Interpolated(Rc::new(Nonterminal::NtExpr(mk().lit_expr(msg)))),
+        DUMMY_SP,
+    )]
+    .into_iter()
+    .collect::<TokenStream>();
+    mk().mac(vec!["compile_error"], tokens, MacDelimiter::Parenthesis)
+}
+
+/// Translate a C translation unit, returning the Rust source along with the pragmas and crates it
+/// needs, and the provenance of its literals when `--emit-provenance` is set.
 pub fn translate(
     ast_context: TypedAstContext,
     tcfg: &TranspilerConfig,
     main_file: PathBuf,
-) -> (String, PragmaVec, CrateSet) {
+) -> (String, PragmaVec, CrateSet, Option<Vec<LiteralProvenance>>) {
     let mut t = Translation::new(ast_context, tcfg, main_file.as_path());
-    let ctx = ExprContext {
-        used: true,
-        is_static: false,
-        is_const: false,
-        decay_ref: DecayRef::Default,
-        is_bitfield_write: false,
-        needs_address: false,
-        expecting_valistimpl: false,
-        ternary_needs_parens: false,
-        expanding_macro: None,
-    };
 
     // `with_globals` sets up a thread-local variable required by the syntax crate.
     with_globals(Edition::Edition2018, || {
-        t.use_crate(ExternCrate::Libc);
-
-        // Sort the top-level declarations by file and source location so that we
-        // preserve the ordering of all declarations in each file.
-        t.ast_context.sort_top_decls();
-
-        t.locate_comments();
-
-        // Headers often pull in declarations that are unused;
-        // we simplify the translator output by omitting those.
-        t.ast_context.prune_unused_decls();
-
-        enum Name<'a> {
-            VarName(&'a str),
-            TypeName(&'a str),
-            AnonymousType,
-            NoName,
-        }
-
-        fn some_type_name(s: Option<&str>) -> Name {
-            match s {
-                None => Name::AnonymousType,
-                Some(r) => Name::TypeName(r),
-            }
-        }
-
-        // Used for testing; so that we don't overlap with C function names
-        if let Some(ref prefix) = t.tcfg.prefix_function_names {
-            prefix_names(&mut t, prefix);
-        }
-
-        // Identify typedefs that name unnamed types and collapse the two declarations
-        // into a single name and declaration, eliminating the typedef altogether.
-        let mut prenamed_decls: IndexMap<CDeclId, CDeclId> = IndexMap::new();
-        for (&decl_id, decl) in t.ast_context.iter_decls() {
-            if let CDeclKind::Typedef { ref name, typ, .. } = decl.kind {
-                if let Some(subdecl_id) = t
-                    .ast_context
-                    .resolve_type(typ.ctype)
-                    .kind
-                    .as_underlying_decl()
-                {
-                    let is_unnamed = match t.ast_context[subdecl_id].kind {
-                        CDeclKind::Struct { name: None, .. }
-                        | CDeclKind::Union { name: None, .. }
-                        | CDeclKind::Enum { name: None, .. } => true,
-
-                        // Detect case where typedef and struct share the same name.
-                        // In this case the purpose of the typedef was simply to eliminate
-                        // the need for the 'struct' tag when refering to the type name.
-                        CDeclKind::Struct {
-                            name: Some(ref target_name),
-                            ..
-                        }
-                        | CDeclKind::Union {
-                            name: Some(ref target_name),
-                            ..
-                        }
-                        | CDeclKind::Enum {
-                            name: Some(ref target_name),
-                            ..
-                        } => name == target_name,
-
-                        _ => false,
-                    };
-
-                    if is_unnamed
-                        && !prenamed_decls
-                            .values()
-                            .find(|decl_id| *decl_id == &subdecl_id)
-                            .is_some()
-                    {
-                        prenamed_decls.insert(decl_id, subdecl_id);
-
-                        t.type_converter
-                            .borrow_mut()
-                            .declare_decl_name(decl_id, name);
-                        t.type_converter
-                            .borrow_mut()
-                            .alias_decl_name(subdecl_id, decl_id);
-                    }
-                }
-            }
-        }
-
-        t.ast_context.prenamed_decls = prenamed_decls;
-
-        // Helper function that returns true if there is either a matching typedef or its
-        // corresponding struct/union/enum
-        fn contains(prenamed_decls: &IndexMap<CDeclId, CDeclId>, decl_id: &CDeclId) -> bool {
-            (prenamed_decls.contains_key(decl_id)
-                || prenamed_decls.values().find(|id| *id == decl_id).is_some())
-        }
-
-        // Populate renamer with top-level names
-        for (&decl_id, decl) in t.ast_context.iter_decls() {
-            let decl_name = match decl.kind {
-                _ if contains(&t.ast_context.prenamed_decls, &decl_id) => Name::NoName,
-                CDeclKind::Struct { ref name, .. } => {
-                    some_type_name(name.as_ref().map(String::as_str))
-                }
-                CDeclKind::Enum { ref name, .. } => {
-                    some_type_name(name.as_ref().map(String::as_str))
-                }
-                CDeclKind::Union { ref name, .. } => {
-                    some_type_name(name.as_ref().map(String::as_str))
-                }
-                CDeclKind::Typedef { ref name, .. } => Name::TypeName(name),
-                CDeclKind::Function { ref name, .. } => Name::VarName(name),
-                CDeclKind::EnumConstant { ref name, .. } => Name::VarName(name),
-                CDeclKind::Variable { ref ident, .. }
-                    if t.ast_context.c_decls_top.contains(&decl_id) =>
-                {
-                    Name::VarName(ident)
-                }
-                CDeclKind::MacroObject { ref name, .. } => Name::VarName(name),
-                _ => Name::NoName,
-            };
-            match decl_name {
-                Name::NoName => (),
-                Name::AnonymousType => {
-                    t.type_converter
-                        .borrow_mut()
-                        .declare_decl_name(decl_id, "C2RustUnnamed");
+        t.prepare();
+
+        for translated in t.top_level_items() {
+            let decl_id = translated.decl_id;
+            let decl = &t.ast_context[decl_id];
+            t.add_side_items(translated.side_items);
+            match translated.result {
+                Ok(ConvertedDecl::Item(item)) => {
+                    t.insert_item(item, decl);
                 }
-                Name::TypeName(name) => {
-                    t.type_converter
-                        .borrow_mut()
-                        .declare_decl_name(decl_id, name);
-                }
-                Name::VarName(name) => {
-                    t.renamer.borrow_mut().insert(decl_id, &name);
+                Ok(ConvertedDecl::ForeignItem(item)) => {
+                    t.insert_foreign_item(item, decl);
                 }
-            }
-        }
-
-        {
-            let convert_type = |decl_id: CDeclId, decl: &CDecl| {
-                let decl_file_id = t.ast_context.file_id(decl);
-                if t.tcfg.reorganize_definitions {
-                    *t.cur_file.borrow_mut() = decl_file_id;
-                }
-                match t.convert_decl(ctx, decl_id) {
-                    Ok(ConvertedDecl::Item(item)) => {
+                Ok(ConvertedDecl::Items(items)) => {
+                    for item in items {
                         t.insert_item(item, decl);
                     }
-                    Ok(ConvertedDecl::ForeignItem(item)) => {
-                        t.insert_foreign_item(item, decl);
-                    }
-                    Ok(ConvertedDecl::Items(items)) => {
-                        for item in items {
-                            t.insert_item(item, decl);
-                        }
-                    }
-                    Ok(ConvertedDecl::NoItem) => {}
-                    Err(e) => {
-                        let ref k = t.ast_context.get_decl(&decl_id).map(|x| &x.kind);
-                        let msg = format!("Skipping declaration {:?} due to error: {}", k, e);
-                        translate_failure(&t.tcfg, &msg);
-                    }
                 }
-                t.cur_file.borrow_mut().take();
-
-                if t.tcfg.reorganize_definitions
-                    && decl_file_id.map_or(false, |id| id != t.main_file)
-                {
-                    t.generate_submodule_imports(decl_id, decl_file_id);
-                }
-            };
-
-            // Export all types
-            for (&decl_id, decl) in t.ast_context.iter_decls() {
-                let needs_export = match decl.kind {
-                    CDeclKind::Struct { .. } => true,
-                    CDeclKind::Enum { .. } => true,
-                    CDeclKind::EnumConstant { .. } => true,
-                    CDeclKind::Union { .. } => true,
-                    CDeclKind::Typedef { .. } =>
-                    // Only check the key as opposed to `contains` because the key should be the
-                    // typedef id
-                    {
-                        !t.ast_context.prenamed_decls.contains_key(&decl_id)
+                Ok(ConvertedDecl::NoItem) => {}
+                Err(e) => {
+                    let msg = if translated.is_type {
+                        let ref k = t.ast_context.get_decl(&decl_id).map(|x| &x.kind);
+                        format!("Skipping declaration {:?} due to error: {}", k, e)
+                    } else {
+                        let decl_identifier = decl.kind.get_name().map_or_else(
+                            || {
+                                t.ast_context.display_loc(&decl.loc)
+                                    .map_or("Unknown".to_string(), |l| format!("at {}", l))
+                            },
+                            |name| name.clone(),
+                        );
+                        format!("Failed to translate {}: {}", decl_identifier, e)
+                    };
+                    translate_failure(&t.tcfg, &msg);
+                    if t.tcfg.stub_failed_decls {
+                        t.stub_failed_decl(decl, &e);
                     }
-                    _ => false,
-                };
-                if needs_export {
-                    convert_type(decl_id, decl);
                 }
             }
-        }
-
-        // Export top-level value declarations
-        for top_id in &t.ast_context.c_decls_top {
-            let needs_export = match t.ast_context[*top_id].kind {
-                CDeclKind::Function { is_implicit, .. } => !is_implicit,
-                CDeclKind::Variable { .. } => true,
-                CDeclKind::MacroObject { .. } => tcfg.translate_const_macros,
-                _ => false,
-            };
-            if needs_export {
-                let decl_opt = t.ast_context.get_decl(top_id);
-                let decl = decl_opt.as_ref().unwrap();
-                let decl_file_id = t.ast_context.file_id(decl);
-
-                if t.tcfg.reorganize_definitions
-                    && decl_file_id.map_or(false, |id| id != t.main_file)
-                {
-                    *t.cur_file.borrow_mut() = decl_file_id;
-                }
-                match t.convert_decl(ctx, *top_id) {
-                    Ok(ConvertedDecl::Item(item)) => {
-                        t.insert_item(item, decl);
-                    }
-                    Ok(ConvertedDecl::ForeignItem(item)) => {
-                        t.insert_foreign_item(item, decl);
-                    }
-                    Ok(ConvertedDecl::Items(items)) => {
-                        for item in items {
-                            t.insert_item(item, decl);
-                        }
-                    }
-                    Ok(ConvertedDecl::NoItem) => {}
-                    Err(e) => {
-                        let ref decl = t.ast_context.get_decl(top_id);
-                        let msg = match decl {
-                            Some(decl) => {
-                                let decl_identifier = decl.kind.get_name().map_or_else(
-                                    || {
-                                        t.ast_context.display_loc(&decl.loc)
-                                            .map_or("Unknown".to_string(), |l| format!("at {}", l))
-                                    },
-                                    |name| name.clone(),
-                                );
-                                format!("Failed to translate {}: {}", decl_identifier, e)
-                            }
-                            _ => format!("Failed to translate declaration: {}", e,),
-                        };
-                        translate_failure(&t.tcfg, &msg);
-                    }
-                }
-                t.cur_file.borrow_mut().take();
 
-                if t.tcfg.reorganize_definitions
-                    && decl_file_id.map_or(false, |id| id != t.main_file)
-                {
-                    t.generate_submodule_imports(*top_id, decl_file_id);
-                }
+            let decl_file_id = t.ast_context.file_id(decl);
+            if t.tcfg.reorganize_definitions
+                && decl_file_id.map_or(false, |id| id != t.main_file)
+            {
+                t.generate_submodule_imports(decl_id, decl_file_id);
             }
         }
 
@@ -789,6 +762,9 @@ pub fn translate(
         }
 
         let pragmas = t.get_pragmas();
+        t.extern_crates
+            .borrow_mut()
+            .extend(t.type_converter.borrow().crates_used());
         let crates = t.extern_crates.borrow().clone();
 
         let mut mod_items: Vec<P<Item>> = Vec::new();
@@ -890,10 +866,37 @@ pub fn translate(
 
             s.print_remaining_comments();
         });
-        (translation, pragmas, crates)
+        (translation, pragmas, crates, t.literal_provenance())
     })
 }
 
+/// Give each array length used by more than one variable or field a named constant in the main
+/// file, which the types and initializers of arrays of that length then refer to.
+fn name_shared_array_lengths(t: &Translation) {
+    let mut uses: BTreeMap<usize, usize> = BTreeMap::new();
+    for (_, decl) in t.ast_context.iter_decls() {
+        let mut ty = match decl.kind {
+            CDeclKind::Variable { typ, .. } | CDeclKind::Field { typ, .. } => typ.ctype,
+            _ => continue,
+        };
+        while let CTypeKind::ConstantArray(elt, len) = t.ast_context.resolve_type(ty).kind {
+            *uses.entry(len).or_insert(0) += 1;
+            ty = elt;
+        }
+    }
+
+    for (len, count) in uses {
+        if len == 0 || count < 2 {
+            continue;
+        }
+        let name = t.renamer.borrow_mut().pick_name_root(&format!("ARRAY_LEN_{}", len));
+        let val = mk().lit_expr(mk().int_lit(len as u128, LitIntType::Unsuffixed));
+        let item = mk().pub_().const_item(&*name, mk().path_ty(vec!["usize"]), val);
+        t.items.borrow_mut()[&t.main_file].add_item(item);
+        t.type_converter.borrow_mut().name_array_length(len, name);
+    }
+}
+
 fn make_submodule(
     ast_context: &TypedAstContext,
     item_store: &mut ItemStore,
@@ -1082,6 +1085,121 @@ pub enum ConvertedDecl {
     NoItem,
 }
 
+/// A top-level declaration translated by `Translation::top_level_items`, together with the
+/// outcome of converting it.
+#[derive(Debug)]
+pub struct TranslatedDecl {
+    pub decl_id: CDeclId,
+    /// Types are exported ahead of all top-level values
+    pub is_type: bool,
+    pub result: Result<ConvertedDecl, TranslationError>,
+    /// Items added while converting the declaration, such as the statics shared string
+    /// literals live in, keyed by the file whose module they belong to
+    pub side_items: IndexMap<FileId, ItemStore>,
+}
+
+/// Iterator converting top-level declarations one at a time, in the order `translate` emits
+/// them. See `Translation::top_level_items`.
+pub struct TopLevelItems<'a, 'c> {
+    t: &'a Translation<'c>,
+    ctx: ExprContext,
+    decls: std::vec::IntoIter<(CDeclId, bool)>,
+}
+
+impl<'a, 'c> Iterator for TopLevelItems<'a, 'c> {
+    type Item = TranslatedDecl;
+
+    fn next(&mut self) -> Option<TranslatedDecl> {
+        let (decl_id, is_type) = self.decls.next()?;
+        let t = self.t;
+        let decl_file_id = t.ast_context.file_id(&t.ast_context[decl_id]);
+        if t.tcfg.reorganize_definitions
+            && (is_type || decl_file_id.map_or(false, |id| id != t.main_file))
+        {
+            *t.cur_file.borrow_mut() = decl_file_id;
+        }
+        // Collect whatever the conversion adds to the item stores separately, so that it goes
+        // out with this declaration instead of staying behind in the translation
+        let saved_items = t.items.replace(indexmap!{t.main_file => ItemStore::new()});
+        let mut result = t.convert_decl(self.ctx, decl_id);
+        let side_items = t.items.replace(saved_items);
+        t.cur_file.borrow_mut().take();
+
+        if t.tcfg.source_location_docs {
+            let decl = &t.ast_context[decl_id];
+            let file = t.ast_context.get_source_path(decl).and_then(|path| path.file_name());
+            if let (Some(file), Some(loc)) = (file, decl.loc) {
+                let attr = source_location_doc(&file.to_string_lossy(), loc.begin_line);
+                match result {
+                    Ok(ConvertedDecl::Item(ref mut item)) => item.attrs.push(attr),
+                    Ok(ConvertedDecl::ForeignItem(ref mut item)) => item.attrs.push(attr),
+                    Ok(ConvertedDecl::Items(ref mut items)) => {
+                        for item in items {
+                            item.attrs.push(attr.clone());
+                        }
+                    }
+                    Ok(ConvertedDecl::NoItem) | Err(_) => {}
+                }
+            }
+        }
+        Some(TranslatedDecl {
+            decl_id,
+            is_type,
+            result,
+            side_items,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.decls.size_hint()
+    }
+}
+
+/// Select the declarations which are exported as top-level items, in output order: all types
+/// first, in declaration order, followed by the top-level values. Each id is paired with
+/// whether it names a type.
+fn top_level_export_order<'a>(
+    decls: impl Iterator<Item = (&'a CDeclId, &'a CDecl)>,
+    c_decls_top: &[CDeclId],
+    prenamed_decls: &IndexMap<CDeclId, CDeclId>,
+    translate_const_macros: bool,
+) -> Vec<(CDeclId, bool)> {
+    let mut kinds = HashMap::new();
+    let mut order = vec![];
+    for (&decl_id, decl) in decls {
+        kinds.insert(decl_id, &decl.kind);
+        let needs_export = match decl.kind {
+            CDeclKind::Struct { .. } => true,
+            CDeclKind::Enum { .. } => true,
+            CDeclKind::EnumConstant { .. } => true,
+            CDeclKind::Union { .. } => true,
+            CDeclKind::Typedef { .. } =>
+            // Only check the key as opposed to `contains` because the key should be the
+            // typedef id
+            {
+                !prenamed_decls.contains_key(&decl_id)
+            }
+            _ => false,
+        };
+        if needs_export {
+            order.push((decl_id, true));
+        }
+    }
+
+    for &top_id in c_decls_top {
+        let needs_export = match kinds.get(&top_id) {
+            Some(CDeclKind::Function { is_implicit, .. }) => !is_implicit,
+            Some(CDeclKind::Variable { .. }) => true,
+            Some(CDeclKind::MacroObject { .. }) => translate_const_macros,
+            _ => false,
+        };
+        if needs_export {
+            order.push((top_id, false));
+        }
+    }
+    order
+}
+
 impl<'c> Translation<'c> {
     pub fn new(
         mut ast_context: TypedAstContext,
@@ -1095,45 +1213,265 @@ impl<'c> Translation<'c> {
             type_converter.translate_valist = true
         }
 
-        let main_file = ast_context.find_file_id(main_file).unwrap_or(0);
-        let items = indexmap!{main_file => ItemStore::new()};
-
-        Translation {
-            features: RefCell::new(IndexSet::new()),
-            type_converter: RefCell::new(type_converter),
-            ast_context,
-            tcfg,
-            renamer: RefCell::new(Renamer::new(&[
-                // Keywords currently in use
-                "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false",
-                "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
-                "pub", "ref", "return", "Self", "self", "static", "struct", "super", "trait",
-                "true", "type", "unsafe", "use", "where", "while", "dyn",
-                // Keywords reserved for future use
-                "abstract", "alignof", "become", "box", "do", "final", "macro", "offsetof",
-                "override", "priv", "proc", "pure", "sizeof", "typeof", "unsized", "virtual",
-                "async", "try", "yield", // Prevent use for other reasons
-                "main",  // prelude names
-                "drop", "Some", "None", "Ok", "Err",
-            ])),
-            zero_inits: RefCell::new(IndexMap::new()),
-            function_context: RefCell::new(FunContext::new()),
-            potential_flexible_array_members: RefCell::new(IndexSet::new()),
-            macro_expansions: RefCell::new(IndexMap::new()),
-            comment_context,
-            comment_store: RefCell::new(CommentStore::new()),
-            spans: HashMap::new(),
-            sectioned_static_initializers: RefCell::new(Vec::new()),
-            items: RefCell::new(items),
-            mod_names: RefCell::new(IndexMap::new()),
-            main_file,
-            extern_crates: RefCell::new(IndexSet::new()),
-            cur_file: RefCell::new(None),
+        let main_file = ast_context.find_file_id(main_file).unwrap_or(0);
+        let items = indexmap!{main_file => ItemStore::new()};
+        let repeated_strings = repeated_string_literals(&ast_context);
+
+        Translation {
+            features: RefCell::new(IndexSet::new()),
+            type_converter: RefCell::new(type_converter),
+            ast_context,
+            tcfg,
+            renamer: RefCell::new(Renamer::new(&[
+                // Keywords currently in use
+                "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false",
+                "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+                "pub", "ref", "return", "Self", "self", "static", "struct", "super", "trait",
+                "true", "type", "unsafe", "use", "where", "while", "dyn",
+                // Keywords reserved for future use
+                "abstract", "alignof", "become", "box", "do", "final", "macro", "offsetof",
+                "override", "priv", "proc", "pure", "sizeof", "typeof", "unsized", "virtual",
+                "async", "try", "yield", // Prevent use for other reasons
+                "main",  // prelude names
+                "drop", "Some", "None", "Ok", "Err",
+            ])),
+            zero_inits: RefCell::new(IndexMap::new()),
+            enum_variants: RefCell::new(HashMap::new()),
+            enum_from_i64_fns: RefCell::new(HashMap::new()),
+            repeated_strings,
+            string_statics: RefCell::new(HashMap::new()),
+            string_literals_escape: Cell::new(false),
+            function_context: RefCell::new(FunContext::new()),
+            potential_flexible_array_members: RefCell::new(IndexSet::new()),
+            macro_expansions: RefCell::new(IndexMap::new()),
+            literal_hook: None,
+            literal_provenance: if tcfg.emit_provenance {
+                Some(RefCell::new(Vec::new()))
+            } else {
+                None
+            },
+            comment_context,
+            comment_store: RefCell::new(CommentStore::new()),
+            spans: HashMap::new(),
+            sectioned_static_initializers: RefCell::new(Vec::new()),
+            items: RefCell::new(items),
+            mod_names: RefCell::new(IndexMap::new()),
+            main_file,
+            extern_crates: RefCell::new(IndexSet::new()),
+            cur_file: RefCell::new(None),
+        }
+    }
+
+    fn use_crate(&self, extern_crate: ExternCrate) {
+        self.extern_crates.borrow_mut().insert(extern_crate);
+    }
+
+    /// Post-process every literal expression the translation emits with `hook`.
+    pub fn set_literal_hook(&mut self, hook: LiteralHook) {
+        self.literal_hook = Some(hook);
+    }
+
+    /// The C origin of every literal translated so far, or `None` unless `--emit-provenance`
+    /// is set.
+    pub fn literal_provenance(&self) -> Option<Vec<LiteralProvenance>> {
+        self.literal_provenance.as_ref().map(|records| records.borrow().clone())
+    }
+
+    /// Record that the C literal `expr_id` was translated to `val`, if provenance is being kept.
+    fn record_literal_provenance(&self, expr_id: CExprId, val: &P<Expr>) {
+        let records = match self.literal_provenance {
+            Some(ref records) => records,
+            None => return,
+        };
+        let expr = &self.ast_context[expr_id];
+        let (line, column) = expr
+            .loc
+            .map_or((0, 0), |loc| (loc.begin_line, loc.begin_column));
+        records.borrow_mut().push(LiteralProvenance {
+            expr_id,
+            file: self.ast_context.get_source_path(expr).map(|path| path.to_path_buf()),
+            line,
+            column,
+            rust: pprust::expr_to_string(val),
+        });
+    }
+
+    /// Prepare the declarations for conversion: sort and prune them, collapse typedefs of
+    /// unnamed types and reserve the top-level names. Must run once, within the syntax
+    /// globals, before `top_level_items`.
+    pub fn prepare(&mut self) {
+        self.use_crate(ExternCrate::Libc);
+
+        // Sort the top-level declarations by file and source location so that we
+        // preserve the ordering of all declarations in each file.
+        self.ast_context.sort_top_decls();
+
+        self.locate_comments();
+
+        // Headers often pull in declarations that are unused;
+        // we simplify the translator output by omitting those.
+        self.ast_context.prune_unused_decls();
+
+        enum Name<'a> {
+            VarName(&'a str),
+            TypeName(&'a str),
+            AnonymousType,
+            NoName,
+        }
+
+        fn some_type_name(s: Option<&str>) -> Name {
+            match s {
+                None => Name::AnonymousType,
+                Some(r) => Name::TypeName(r),
+            }
+        }
+
+        // Used for testing; so that we don't overlap with C function names
+        if let Some(ref prefix) = self.tcfg.prefix_function_names {
+            prefix_names(self, prefix);
+        }
+
+        // Identify typedefs that name unnamed types and collapse the two declarations
+        // into a single name and declaration, eliminating the typedef altogether.
+        let mut prenamed_decls: IndexMap<CDeclId, CDeclId> = IndexMap::new();
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            if let CDeclKind::Typedef { ref name, typ, .. } = decl.kind {
+                if let Some(subdecl_id) = self
+                    .ast_context
+                    .resolve_type(typ.ctype)
+                    .kind
+                    .as_underlying_decl()
+                {
+                    let is_unnamed = match self.ast_context[subdecl_id].kind {
+                        CDeclKind::Struct { name: None, .. }
+                        | CDeclKind::Union { name: None, .. }
+                        | CDeclKind::Enum { name: None, .. } => true,
+
+                        // Detect case where typedef and struct share the same name.
+                        // In this case the purpose of the typedef was simply to eliminate
+                        // the need for the 'struct' tag when refering to the type name.
+                        CDeclKind::Struct {
+                            name: Some(ref target_name),
+                            ..
+                        }
+                        | CDeclKind::Union {
+                            name: Some(ref target_name),
+                            ..
+                        }
+                        | CDeclKind::Enum {
+                            name: Some(ref target_name),
+                            ..
+                        } => name == target_name,
+
+                        _ => false,
+                    };
+
+                    if is_unnamed
+                        && !prenamed_decls
+                            .values()
+                            .find(|decl_id| *decl_id == &subdecl_id)
+                            .is_some()
+                    {
+                        prenamed_decls.insert(decl_id, subdecl_id);
+
+                        self.type_converter
+                            .borrow_mut()
+                            .declare_decl_name(decl_id, name);
+                        self.type_converter
+                            .borrow_mut()
+                            .alias_decl_name(subdecl_id, decl_id);
+                    }
+                }
+            }
+        }
+
+        self.ast_context.prenamed_decls = prenamed_decls;
+
+        // Helper function that returns true if there is either a matching typedef or its
+        // corresponding struct/union/enum
+        fn contains(prenamed_decls: &IndexMap<CDeclId, CDeclId>, decl_id: &CDeclId) -> bool {
+            (prenamed_decls.contains_key(decl_id)
+                || prenamed_decls.values().find(|id| *id == decl_id).is_some())
+        }
+
+        // Populate renamer with top-level names
+        for (&decl_id, decl) in self.ast_context.iter_decls() {
+            let decl_name = match decl.kind {
+                _ if contains(&self.ast_context.prenamed_decls, &decl_id) => Name::NoName,
+                CDeclKind::Struct { ref name, .. } => {
+                    some_type_name(name.as_ref().map(String::as_str))
+                }
+                CDeclKind::Enum { ref name, .. } => {
+                    some_type_name(name.as_ref().map(String::as_str))
+                }
+                CDeclKind::Union { ref name, .. } => {
+                    some_type_name(name.as_ref().map(String::as_str))
+                }
+                CDeclKind::Typedef { ref name, .. } => Name::TypeName(name),
+                CDeclKind::Function { ref name, .. } => Name::VarName(name),
+                CDeclKind::EnumConstant { ref name, .. } => Name::VarName(name),
+                CDeclKind::Variable { ref ident, .. }
+                    if self.ast_context.c_decls_top.contains(&decl_id) =>
+                {
+                    Name::VarName(ident)
+                }
+                CDeclKind::MacroObject { ref name, .. } => Name::VarName(name),
+                _ => Name::NoName,
+            };
+            match decl_name {
+                Name::NoName => (),
+                Name::AnonymousType => {
+                    self.type_converter
+                        .borrow_mut()
+                        .declare_decl_name(decl_id, "C2RustUnnamed");
+                }
+                Name::TypeName(name) => {
+                    self.type_converter
+                        .borrow_mut()
+                        .declare_decl_name(decl_id, name);
+                }
+                Name::VarName(name) => {
+                    self.renamer.borrow_mut().insert(decl_id, &name);
+                }
+            }
+        }
+
+        // Header submodules can't see constants in the main file, so lengths are only named
+        // when every declaration ends up there.
+        if self.tcfg.named_array_lengths && !self.tcfg.reorganize_definitions {
+            name_shared_array_lengths(self);
         }
     }
 
-    fn use_crate(&self, extern_crate: ExternCrate) {
-        self.extern_crates.borrow_mut().insert(extern_crate);
+    /// Convert the top-level declarations lazily, one declaration per step, in the same order
+    /// `translate` emits them. Callers can write each item out as it is produced rather than
+    /// holding the whole translation, and handle failures per declaration.
+    ///
+    /// Neither the converted items nor the side items they need are added to the output item
+    /// stores; each `TranslatedDecl` carries both, and adding them is left to the caller.
+    pub fn top_level_items<'a>(&'a self) -> TopLevelItems<'a, 'c> {
+        let ctx = ExprContext {
+            used: true,
+            is_static: false,
+            is_const: false,
+            decay_ref: DecayRef::Default,
+            is_bitfield_write: false,
+            needs_address: false,
+            expecting_valistimpl: false,
+            ternary_needs_parens: false,
+            expanding_macro: None,
+        };
+        let decls = top_level_export_order(
+            self.ast_context.iter_decls(),
+            &self.ast_context.c_decls_top,
+            &self.ast_context.prenamed_decls,
+            self.tcfg.translate_const_macros,
+        );
+        TopLevelItems {
+            t: self,
+            ctx,
+            decls: decls.into_iter(),
+        }
     }
 
     pub fn cur_file(&self) -> FileId {
@@ -1274,9 +1612,12 @@ impl<'c> Translation<'c> {
         };
 
         // The f128 crate doesn't currently provide a way to const initialize
-        // values, except for common mathematical constants
+        // values, except for common mathematical constants. We can still build
+        // literals from their bits.
         if let CTypeKind::LongDouble = self.ast_context[qtype.ctype].kind {
-            return true;
+            if self.long_double_literal_value(expr_id).is_none() {
+                return true;
+            }
         }
 
         let iter = DFExpr::new(&self.ast_context, expr_id.into());
@@ -1287,6 +1628,28 @@ impl<'c> Translation<'c> {
                 _ => unreachable!("Found static initializer type other than expr"),
             };
 
+            // `num_complex` arithmetic isn't const, and neither are the temporaries we use to
+            // convert between complex types
+            if self.ast_context[expr_id]
+                .kind
+                .get_type()
+                .map_or(false, |ty| self.ast_context.resolve_type(ty).kind.is_complex())
+            {
+                match self.ast_context[expr_id].kind {
+                    CExprKind::Unary(_, c_ast::UnOp::Extension, _, _)
+                    | CExprKind::Unary(_, c_ast::UnOp::Plus, _, _)
+                    | CExprKind::ImplicitCast(_, _, CastKind::FloatingRealToComplex, _, _)
+                    | CExprKind::ExplicitCast(_, _, CastKind::FloatingRealToComplex, _, _)
+                    | CExprKind::ImplicitCast(_, _, CastKind::IntegralRealToComplex, _, _)
+                    | CExprKind::ExplicitCast(_, _, CastKind::IntegralRealToComplex, _, _) => {}
+                    CExprKind::Binary(..)
+                    | CExprKind::Unary(..)
+                    | CExprKind::ImplicitCast(..)
+                    | CExprKind::ExplicitCast(..) => return true,
+                    _ => {}
+                }
+            }
+
             match self.ast_context[expr_id].kind {
                 // Technically we're being conservative here, but it's only the most
                 // contrived array indexing initializers that would be accepted
@@ -1331,34 +1694,6 @@ impl<'c> Translation<'c> {
                         }
                     }
                 }
-                CExprKind::InitList(qtype, _, _, _) => {
-                    let ty = &self.ast_context.resolve_type(qtype.ctype).kind;
-
-                    match ty {
-                        CTypeKind::Struct(decl_id) => {
-                            let decl = &self.ast_context[*decl_id].kind;
-
-                            if let CDeclKind::Struct {
-                                fields: Some(fields),
-                                ..
-                            } = decl
-                            {
-                                for field_id in fields {
-                                    let field_decl = &self.ast_context[*field_id].kind;
-
-                                    if let CDeclKind::Field {
-                                        bitfield_width: Some(_),
-                                        ..
-                                    } = field_decl
-                                    {
-                                        return true;
-                                    }
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                }
                 CExprKind::ImplicitCast(qtype, _, IntegralToPointer, _, _)
                 | CExprKind::ExplicitCast(qtype, _, IntegralToPointer, _, _) => {
                     if let CTypeKind::Pointer(qtype) =
@@ -1611,6 +1946,10 @@ impl<'c> Translation<'c> {
 
             CDeclKind::Union {
                 fields: Some(ref fields),
+                is_packed,
+                manual_alignment,
+                max_field_alignment,
+                platform_byte_size,
                 ..
             } => {
                 let name = self
@@ -1620,10 +1959,12 @@ impl<'c> Translation<'c> {
                     .unwrap();
 
                 let mut field_syns = vec![];
+                let mut has_bitfields = false;
                 for &x in fields {
                     let field_decl = self.ast_context.index(x);
                     match field_decl.kind {
-                        CDeclKind::Field { ref name, typ, .. } => {
+                        CDeclKind::Field { ref name, typ, bitfield_width, .. } => {
+                            has_bitfields |= bitfield_width.is_some();
                             let name = self
                                 .type_converter
                                 .borrow_mut()
@@ -1649,13 +1990,24 @@ impl<'c> Translation<'c> {
                             .struct_item(name, vec![], false),
                     )
                 } else {
-                    ConvertedDecl::Item(
-                        mk().span(s)
-                            .pub_()
-                            .call_attr("derive", vec!["Copy", "Clone"])
-                            .call_attr("repr", vec!["C"])
-                            .union_item(name, field_syns),
-                    )
+                    let union_item = mk().span(s)
+                        .pub_()
+                        .call_attr("derive", vec!["Copy", "Clone"])
+                        .call_attr("repr", vec!["C"])
+                        .union_item(name.clone(), field_syns);
+
+                    // Bitfields are translated as fields of their whole type, and packing and
+                    // alignment attributes aren't carried over to the `repr(C)` union, so only
+                    // check the layout of unions without either.
+                    let has_layout_attrs =
+                        is_packed || manual_alignment.is_some() || max_field_alignment.is_some();
+                    if has_bitfields || has_layout_attrs {
+                        ConvertedDecl::Item(union_item)
+                    } else {
+                        let size = self.compute_size_of_ty(mk().path_ty(vec![name]))?.to_expr();
+                        let size_check = size_check_item(size, platform_byte_size);
+                        ConvertedDecl::Items(vec![union_item, size_check])
+                    }
                 })
             }
 
@@ -2284,7 +2636,7 @@ impl<'c> Translation<'c> {
             self.tcfg.use_c_loop_info,
             self.tcfg.use_c_multiple_info,
             live_in,
-        );
+        )?;
 
         if self.tcfg.dump_structures {
             eprintln!("Relooped structures:");
@@ -2440,6 +2792,49 @@ impl<'c> Translation<'c> {
         false
     }
 
+    /// Find the function named in a `cleanup` attribute on a variable of type `typ`, returning
+    /// its Rust name and, unless it takes a pointer to `typ`, the type of its parameter.
+    fn cleanup_function(
+        &self,
+        name: &str,
+        typ: CQualTypeId,
+    ) -> Result<(String, Option<P<Ty>>), TranslationError> {
+        let (fn_id, param) = self
+            .ast_context
+            .iter_decls()
+            .filter_map(|(&decl_id, decl)| match decl.kind {
+                CDeclKind::Function { name: ref fn_name, ref parameters, .. }
+                    if fn_name == name => Some((decl_id, parameters.first().cloned())),
+                _ => None,
+            })
+            .next()
+            .ok_or_else(|| format_err!("Missing cleanup function '{}'", name))?;
+        let rust_name = self
+            .renamer
+            .borrow()
+            .get(&fn_id)
+            .ok_or_else(|| format_err!("name not declared: '{}'", name))?;
+
+        if self.tcfg.reorganize_definitions {
+            if let Some(cur_file) = self.cur_file.borrow().as_ref() {
+                self.add_import(*cur_file, fn_id, &rust_name);
+            }
+        }
+
+        let param_typ = match param.map(|param| &self.ast_context[param].kind) {
+            Some(&CDeclKind::Variable { typ, .. }) => typ,
+            _ => return Err(format_err!("Cleanup function '{}' takes no argument", name).into()),
+        };
+        let arg_ty = match self.ast_context.resolve_type(param_typ.ctype).kind {
+            CTypeKind::Pointer(pointee)
+                if self.ast_context.resolve_type_id(pointee.ctype)
+                    == self.ast_context.resolve_type_id(typ.ctype) => None,
+            _ => Some(self.convert_type(param_typ.ctype)?),
+        };
+
+        Ok((rust_name, arg_ty))
+    }
+
     pub fn convert_decl_stmt_info(
         &self,
         ctx: ExprContext,
@@ -2501,7 +2896,7 @@ impl<'c> Translation<'c> {
                 ref ident,
                 initializer,
                 typ,
-                ..
+                ref attrs,
             } => {
                 assert!(
                     is_defn,
@@ -2551,23 +2946,58 @@ impl<'c> Translation<'c> {
                 } else {
                     zeroed.to_pure_expr()
                 }.expect("Expected decl initializer to not have any statements");
+
+                // A variable with a `cleanup` attribute gets a guard holding a pointer to it,
+                // created right after the variable gets its value, whose `Drop` impl calls the
+                // cleanup function at the end of the enclosing scope. Moving the declaration to
+                // the top of the function would run cleanup at the wrong time, so such
+                // variables have to stay where they are.
+                let mut cleanup_items = vec![];
+                let mut guard = vec![];
+                if let Some(cleanup_fn) = cleanup_attr(attrs) {
+                    let (cleanup_fn, arg_ty) = self.cleanup_function(cleanup_fn, typ)?;
+                    let wrapper = self
+                        .renamer
+                        .borrow_mut()
+                        .pick_name(&format!("{}_cleanup", rust_name));
+                    cleanup_items = cleanup_wrapper_items(&wrapper, ty.clone(), &cleanup_fn, arg_ty)
+                        .into_iter()
+                        .map(|item| mk().item_stmt(item))
+                        .collect();
+
+                    let guard_name = self
+                        .renamer
+                        .borrow_mut()
+                        .pick_name(&format!("_{}_guard", rust_name));
+                    let var_ptr = mk().mutbl().addr_of_expr(mk().ident_expr(&rust_name));
+                    let guard_init = mk().call_expr(mk().path_expr(vec![&wrapper]), vec![var_ptr]);
+                    let guard_local =
+                        mk().local(mk().ident_pat(guard_name), None as Option<P<Ty>>, Some(guard_init));
+                    guard.push(mk().local_stmt(P(guard_local)));
+                }
+                let has_cleanup = !guard.is_empty();
+                // The guard takes a mutable pointer to the variable
+                let mutbl = if has_cleanup { Mutability::Mutable } else { mutbl };
+
                 let pat_mut = mk().set_mutbl("mut").ident_pat(rust_name.clone());
                 let local_mut = mk().local(pat_mut, Some(ty.clone()), Some(zeroed));
-                if has_self_reference {
+                let (decl, assign_stmts, decl_and_assign) = if has_self_reference {
                     let assign = mk().assign_expr(mk().ident_expr(rust_name), init);
 
                     let mut assign_stmts = stmts.clone();
                     assign_stmts.push(mk().semi_stmt(assign.clone()));
 
-                    let mut decl_and_assign = vec![mk().local_stmt(P(local_mut.clone()))];
+                    let mut decl_and_assign = cleanup_items;
+                    decl_and_assign.push(mk().local_stmt(P(local_mut.clone())));
                     decl_and_assign.append(&mut stmts);
-                    decl_and_assign.push(mk().expr_stmt(assign));
+                    if has_cleanup {
+                        decl_and_assign.push(mk().semi_stmt(assign));
+                        decl_and_assign.append(&mut guard);
+                    } else {
+                        decl_and_assign.push(mk().expr_stmt(assign));
+                    }
 
-                    Ok(cfg::DeclStmtInfo::new(
-                        vec![mk().local_stmt(P(local_mut))],
-                        assign_stmts,
-                        decl_and_assign,
-                    ))
+                    (vec![mk().local_stmt(P(local_mut))], assign_stmts, decl_and_assign)
                 } else {
                     let pat = mk().set_mutbl(mutbl).ident_pat(rust_name.clone());
 
@@ -2585,14 +3015,18 @@ impl<'c> Translation<'c> {
                     let mut assign_stmts = stmts.clone();
                     assign_stmts.push(mk().semi_stmt(assign));
 
-                    let mut decl_and_assign = stmts;
+                    let mut decl_and_assign = cleanup_items;
+                    decl_and_assign.append(&mut stmts);
                     decl_and_assign.push(mk().local_stmt(P(local)));
+                    decl_and_assign.append(&mut guard);
 
-                    Ok(cfg::DeclStmtInfo::new(
-                        vec![mk().local_stmt(P(local_mut))],
-                        assign_stmts,
-                        decl_and_assign,
-                    ))
+                    (vec![mk().local_stmt(P(local_mut))], assign_stmts, decl_and_assign)
+                };
+
+                if has_cleanup {
+                    Ok(cfg::DeclStmtInfo::unmovable(decl_and_assign))
+                } else {
+                    Ok(cfg::DeclStmtInfo::new(decl, assign_stmts, decl_and_assign))
                 }
             }
 
@@ -3198,7 +3632,7 @@ impl<'c> Translation<'c> {
 
             CExprKind::OffsetOf(ty, ref kind) => match kind {
                 OffsetOfKind::Constant(val) => {
-                    Ok(WithStmts::new_val(self.mk_int_lit(ty, *val, IntBase::Dec)?))
+                    Ok(WithStmts::new_val(self.mk_int_lit(ty, (*val).into(), IntBase::Dec)?))
                 }
                 OffsetOfKind::Variable(qty, field_id, expr_id) => {
                     self.use_crate(ExternCrate::Memoffset);
@@ -3253,7 +3687,13 @@ impl<'c> Translation<'c> {
                 }
             },
 
-            CExprKind::Literal(ty, ref kind) => self.convert_literal(ctx, ty, kind),
+            CExprKind::Literal(ty, ref kind) => {
+                let val = self.convert_literal(ctx, ty, kind)?;
+                Ok(val.map(|expr| {
+                    self.record_literal_provenance(expr_id, &expr);
+                    expr
+                }))
+            }
 
             CExprKind::ImplicitCast(ty, expr, kind, opt_field_id, _)
             | CExprKind::ExplicitCast(ty, expr, kind, opt_field_id, _) => {
@@ -3651,9 +4091,9 @@ impl<'c> Translation<'c> {
 
             CExprKind::CompoundLiteral(_, val) => self.convert_expr(ctx, val),
 
-            CExprKind::InitList(ty, ref ids, opt_union_field_id, _) => {
-                self.convert_init_list(ctx, ty, ids, opt_union_field_id)
-            }
+            CExprKind::InitList(ty, ref ids, opt_union_field_id, _) => self
+                .convert_init_list(ctx, ty, ids, opt_union_field_id)
+                .map_err(|e| e.add_loc(self.ast_context.display_loc(src_loc))),
 
             CExprKind::ImplicitValueInit(ty) => {
                 self.implicit_default_expr(ty.ctype, ctx.is_static)
@@ -3852,8 +4292,15 @@ impl<'c> Translation<'c> {
                     }
                 }
 
-                let block_body = mk().block(stmts.clone());
+                // The statements only run as part of the labelled block, which is the value of
+                // the expression; emitting them alongside it too would run them twice.
+                let block_body = mk().block(stmts);
                 let val: P<Expr> = mk().labelled_block_expr(block_body, lbl.pretty_print());
+                let stmts = if ctx.is_unused() {
+                    vec![mk().expr_stmt(val.clone())]
+                } else {
+                    Vec::new()
+                };
 
                 Ok(WithStmts::new(stmts, val))
             }
@@ -3995,6 +4442,12 @@ impl<'c> Translation<'c> {
 
                 let source_ty = self.convert_type(source_ty_ctype_id)?;
                 if let CTypeKind::LongDouble = target_ty_ctype {
+                    if ctx.is_const || ctx.is_static {
+                        if let Some(lit) = expr.and_then(|e| self.long_double_literal_value(e)) {
+                            return Ok(self.mk_long_double_lit(ctx, lit, lit.to_string()));
+                        }
+                    }
+
                     self.use_crate(ExternCrate::F128);
 
                     let fn_path = mk().path_expr(vec!["f128", "f128", "new"]);
@@ -4004,7 +4457,16 @@ impl<'c> Translation<'c> {
                 } else if let &CTypeKind::Enum(enum_decl_id) = target_ty_ctype {
                     // Casts targeting `enum` types...
                     let expr = expr.ok_or_else(|| format_err!("Casts to enums require a C ExprId"))?;
-                    Ok(self.enum_cast(ty.ctype, enum_decl_id, expr, val, source_ty, target_ty))
+                    self.enum_cast(ctx, ty.ctype, enum_decl_id, expr, val, source_ty, target_ty)
+                } else if let Some((constant, wrapped)) = expr
+                    .filter(|_| {
+                        kind == CastKind::IntegralCast
+                            && self.tcfg.narrowing_literals != NarrowingLiteralMode::Truncate
+                    })
+                    .and_then(|e| self.narrowed_constant(e, ty.ctype))
+                {
+                    // An integer constant that doesn't fit in the target type
+                    Ok(val.map(|x| self.narrowing_literal_cast(constant, wrapped, x, target_ty)))
                 } else {
                     // Other numeric casts translate to Rust `as` casts,
                     // unless the cast is to a function pointer then use `transmute`.
@@ -4049,11 +4511,32 @@ impl<'c> Translation<'c> {
                     Some(&CExprKind::Literal(_, CLiteral::String(ref bytes, 1))) if is_const => {
                         let target_ty = self.convert_type(ty.ctype)?;
 
-                        let mut bytes = bytes.to_owned();
-                        bytes.push(0);
-                        let byte_literal = mk().lit_expr(bytes);
-                        let val =
-                            mk().cast_expr(byte_literal, mk().ptr_ty(mk().path_ty(vec!["u8"])));
+                        // Strings used read-only in several places share a single static, as
+                        // do ones referenced from outside of Rust. Constants cannot refer to
+                        // statics, so the ones in constants share a constant instead.
+                        let needs_static = self.repeated_strings.contains(bytes)
+                            || self.string_literals_escape.get();
+                        let interned = if needs_static {
+                            Some(self.string_literal_static(bytes, ctx.is_const))
+                        } else {
+                            None
+                        };
+
+                        let val = match (interned, self.string_literal_text(bytes)) {
+                            (Some(name), _) => {
+                                mk().method_call_expr(mk().ident_expr(name), "as_ptr", vec![] as Vec<P<Expr>>)
+                            }
+                            (None, Some(text)) => {
+                                let str_literal = mk().lit_expr(format!("{}\0", text));
+                                mk().method_call_expr(str_literal, "as_ptr", vec![] as Vec<P<Expr>>)
+                            }
+                            (None, None) => {
+                                let mut bytes = bytes.to_owned();
+                                bytes.push(0);
+                                let byte_literal = mk().lit_expr(bytes);
+                                mk().cast_expr(byte_literal, mk().ptr_ty(mk().path_ty(vec!["u8"])))
+                            }
+                        };
                         let val = mk().cast_expr(val, target_ty);
                         Ok(WithStmts::new_val(val))
                     }
@@ -4070,6 +4553,36 @@ impl<'c> Translation<'c> {
                                 "as_mut_ptr"
                             };
 
+                            // Like `&` of a compound literal, a decayed array compound literal
+                            // has to outlive the statement it appears in.
+                            let val = match expr_kind {
+                                Some(&CExprKind::CompoundLiteral(..)) if !ctx.is_static => {
+                                    let mutbl = if is_const {
+                                        Mutability::Immutable
+                                    } else {
+                                        Mutability::Mutable
+                                    };
+                                    self.bind_to_temporary("compound_literal", mutbl, val)
+                                }
+                                // A safe string literal is a temporary array, so give it a
+                                // static of its own to live in, as it does in C.
+                                Some(&CExprKind::Literal(_, CLiteral::String(..)))
+                                    if !ctx.is_static
+                                        && !ctx.is_const
+                                        && self.tcfg.safe_string_literals =>
+                                {
+                                    let array_ty = self.convert_type(source_ty.ctype)?;
+                                    let mut val = val.map(|init| {
+                                        let name = self.safe_string_literal_static(array_ty, init);
+                                        mk().ident_expr(name)
+                                    });
+                                    // The static is mutable
+                                    val.set_unsafe();
+                                    val
+                                }
+                                _ => val,
+                            };
+
                             let call = val
                                 .map(|x| mk().method_call_expr(x, method, vec![] as Vec<P<Expr>>));
 
@@ -4119,7 +4632,9 @@ impl<'c> Translation<'c> {
             CastKind::IntegralToBoolean
             | CastKind::FloatingToBoolean
             | CastKind::PointerToBoolean => {
-                if let Some(expr) = expr {
+                if let Some(lit) = expr.and_then(|e| self.bool_literal_value(e)) {
+                    Ok(WithStmts::new_val(mk().lit_expr(mk().bool_lit(lit))))
+                } else if let Some(expr) = expr {
                     self.convert_condition(ctx, true, expr)
                 } else {
                     Ok(val.map(|e| self.match_bool(true, source_ty.ctype, e)))
@@ -4131,21 +4646,48 @@ impl<'c> Translation<'c> {
                 "TODO boolean to signed integral not supported",
             )),
 
-            CastKind::FloatingRealToComplex
+            // Clang converts the real value to the element type first
+            CastKind::FloatingRealToComplex | CastKind::IntegralRealToComplex => {
+                let element = match *target_ty_kind {
+                    CTypeKind::Complex(element) => element,
+                    ref k => return Err(format_err!("Cast to non-complex type {:?}", k).into()),
+                };
+                let im = self.implicit_default_expr(element, ctx.is_static)?.to_expr();
+                Ok(val.map(|re| self.mk_complex(re, im)))
+            }
+
+            CastKind::FloatingComplexToReal | CastKind::IntegralComplexToReal => {
+                Ok(val.map(|z| mk().field_expr(z, "re")))
+            }
+
+            CastKind::FloatingComplexCast
             | CastKind::FloatingComplexToIntegralComplex
-            | CastKind::FloatingComplexCast
-            | CastKind::FloatingComplexToReal
-            | CastKind::IntegralComplexToReal
-            | CastKind::IntegralRealToComplex
             | CastKind::IntegralComplexCast
-            | CastKind::IntegralComplexToFloatingComplex
-            | CastKind::IntegralComplexToBoolean => Err(TranslationError::generic(
+            | CastKind::IntegralComplexToFloatingComplex => {
+                let element = match *target_ty_kind {
+                    CTypeKind::Complex(element) => element,
+                    ref k => return Err(format_err!("Cast to non-complex type {:?}", k).into()),
+                };
+                let element_ty = self.convert_type(element)?;
+                let val = self.bind_to_temporary("z", Mutability::Immutable, val);
+                Ok(val.map(|z| {
+                    let part = |name| {
+                        mk().cast_expr(mk().field_expr(z.clone(), name), element_ty.clone())
+                    };
+                    self.mk_complex(part("re"), part("im"))
+                }))
+            }
+
+            CastKind::IntegralComplexToBoolean => Err(TranslationError::generic(
                 "TODO casts with complex numbers not supported",
             )),
 
-            CastKind::VectorSplat => Err(TranslationError::generic(
-                "TODO vector splat casts not supported",
-            )),
+            CastKind::VectorSplat => match *target_ty_kind {
+                CTypeKind::Vector(CQualTypeId { ctype, .. }, len) => {
+                    self.vector_splat(ctx, val, ctype, len)
+                }
+                _ => Err(TranslationError::generic("Vector splat cast to a non-vector type")),
+            },
         }
     }
 
@@ -4203,17 +4745,34 @@ impl<'c> Translation<'c> {
     /// like to produce Rust with _no_ casts. This function handles this simplification.
     fn enum_cast(
         &self,
+        ctx: ExprContext,
         enum_type: CTypeId,
         enum_decl: CEnumId, // ID of the enum declaration corresponding to the target type
         expr: CExprId,      // ID of initial C argument to cast
         val: WithStmts<P<Expr>>, // translated Rust argument to cast
         _source_ty: P<Ty>,  // source type of cast
         target_ty: P<Ty>,   // target type of cast
-    ) -> WithStmts<P<Expr>> {
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
         // Extract the IDs of the `EnumConstant` decls underlying the enum.
         let variants = match self.ast_context.index(enum_decl).kind {
             CDeclKind::Enum { ref variants, .. } => variants,
-            _ => panic!("{:?} does not point to an `enum` declaration"),
+            _ => {
+                return Err(
+                    format_err!("{:?} does not point to an `enum` declaration", enum_decl).into(),
+                )
+            }
+        };
+
+        // With `--checked-enum-values`, integers that might not be values of the enum go through
+        // a generated conversion that panics on anything else. It isn't a `const fn`, so static
+        // and constant initializers keep the plain cast.
+        let checked = self.tcfg.checked_enum_values && !ctx.is_static && !ctx.is_const;
+        let enum_for_i64 = |value: i64| {
+            if checked {
+                self.checked_enum_for_i64(enum_decl, value)
+            } else {
+                self.enum_for_i64(enum_type, value)
+            }
         };
 
         match self.ast_context.index(expr).kind {
@@ -4221,33 +4780,38 @@ impl<'c> Translation<'c> {
             // we are casting to. Here, we can just remove the extraneous cast instead of generating
             // a new one.
             CExprKind::DeclRef(_, decl_id, _) if variants.contains(&decl_id) => {
-                return val.map(|x| match x.kind {
+                return Ok(val.map(|x| match x.kind {
                     ast::ExprKind::Cast(ref e, _) => e.clone(),
                     _ => panic!(format!(
                         "DeclRef {:?} of enum {:?} is not cast",
                         expr, enum_decl
                     )),
-                })
+                }))
             }
 
             CExprKind::Literal(_, CLiteral::Integer(i, _)) => {
-                return val.map(|_| self.enum_for_i64(enum_type, i as i64));
+                return val.result_map(|_| enum_for_i64(i as i64));
             }
 
             CExprKind::Unary(_, c_ast::UnOp::Negate, subexpr_id, _) => {
                 if let &CExprKind::Literal(_, CLiteral::Integer(i, _)) =
                     &self.ast_context[subexpr_id].kind
                 {
-                    return val.map(|_| self.enum_for_i64(enum_type, -(i as i64)));
+                    return val.result_map(|_| enum_for_i64(-(i as i64)));
                 }
             }
 
-            // In all other cases, a cast to an enum requires a `transmute` - Rust enums cannot be
-            // converted into integral types as easily as C ones.
+            // In all other cases, keep the cast. C enums are translated to aliases of their
+            // integer type, so this is an ordinary integer cast rather than a `transmute`.
             _ => {}
         }
 
-        val.map(|x| mk().cast_expr(x, target_ty))
+        if checked {
+            let from_i64 = mk().path_expr(vec![self.enum_from_i64_fn(enum_decl)?]);
+            let i64_ty = mk().path_ty(vec!["i64"]);
+            return Ok(val.map(|x| mk().call_expr(from_i64, vec![mk().cast_expr(x, i64_ty)])));
+        }
+        Ok(val.map(|x| mk().cast_expr(x, target_ty)))
     }
 
     pub fn implicit_default_expr(
@@ -4271,12 +4835,21 @@ impl<'c> Translation<'c> {
             self.null_ptr(resolved_ty_id, is_static)
                 .map(WithStmts::new_val)
         } else if let &CTypeKind::ConstantArray(elt, sz) = resolved_ty {
-            let sz = mk().lit_expr(mk().int_lit(sz as u128, LitIntType::Unsuffixed));
-            Ok(self.implicit_default_expr(elt, is_static)?
-                .map(|elt| mk().repeat_expr(elt, sz)))
+            let elt_default = self.implicit_default_expr(elt, is_static)?;
+            if self.is_repeatable_element(elt) {
+                let sz = self.array_length_expr(sz);
+                Ok(elt_default.map(|elt| mk().repeat_expr(elt, sz)))
+            } else {
+                // `[v; n]` needs a `Copy` element type, so spell out each
+                // element of arrays of anything else
+                Ok(elt_default.map(|elt| mk().array_expr(vec![elt; sz])))
+            }
         } else if let &CTypeKind::IncompleteArray(_) = resolved_ty {
             // Incomplete arrays are translated to zero length arrays
             Ok(WithStmts::new_val(mk().array_expr(vec![] as Vec<P<Expr>>)))
+        } else if let &CTypeKind::Complex(element) = resolved_ty {
+            let zero = self.implicit_default_expr(element, is_static)?;
+            Ok(zero.map(|zero| self.mk_complex(zero.clone(), zero)))
         } else if let Some(decl_id) = resolved_ty.as_underlying_decl() {
             self.zero_initializer(decl_id, ty_id, is_static)
         } else if let &CTypeKind::VariableArray(elt, _) = resolved_ty {
@@ -4377,7 +4950,7 @@ impl<'c> Translation<'c> {
             }
 
             // Transmute the number `0` into the enum type
-            CDeclKind::Enum { .. } => WithStmts::new_val(self.enum_for_i64(type_id, 0)),
+            CDeclKind::Enum { .. } => WithStmts::new_val(self.enum_for_i64(type_id, 0)?),
 
             _ => return Err(TranslationError::generic(
                 "Declaration is not associated with a type",
@@ -4503,6 +5076,26 @@ impl<'c> Translation<'c> {
         result
     }
 
+    /// Put a `compile_error!` where the translation of `decl` would have gone, reporting the
+    /// error `e` that stopped it from being translated along with the declaration's location.
+    fn stub_failed_decl(&self, decl: &CDecl, e: &TranslationError) {
+        let name = decl.kind.get_name().map_or("declaration", |name| name.as_str());
+        let msg = match self.ast_context.display_loc(&decl.loc) {
+            Some(loc) => format!("Failed to translate {} at {}: {}", name, loc, e),
+            None => format!("Failed to translate {}: {}", name, e),
+        };
+        self.insert_item(failed_decl_stub(&msg), decl);
+    }
+
+    /// Add the side items converting a top-level declaration produced to the item stores of
+    /// their files
+    fn add_side_items(&self, side_items: IndexMap<FileId, ItemStore>) {
+        let mut item_stores = self.items.borrow_mut();
+        for (file_id, items) in side_items {
+            item_stores.entry(file_id).or_insert_with(ItemStore::new).append(items);
+        }
+    }
+
     /// If we're trying to organize item definitions into submodules, add them to a module
     /// scoped "namespace" if we have a path available, otherwise add it to the global "namespace"
     fn insert_item(&self, mut item: P<Item>, decl: &CDecl) {
@@ -4701,3 +5294,236 @@ impl<'c> Translation<'c> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_decl_stubs() {
+        syntax::with_default_globals(|| {
+            let stub = failed_decl_stub("Failed to translate x at a.c:1:5: \"bad\" init list");
+            assert_eq!(
+                pprust::item_to_string(&stub),
+                r#"compile_error!("Failed to translate x at a.c:1:5: \"bad\" init list");"#
+            );
+        });
+    }
+
+    #[test]
+    fn source_location_docs() {
+        syntax::with_default_globals(|| {
+            let mut item = mk()
+                .mutbl()
+                .static_item("x", mk().path_ty(vec!["i32"]), mk().lit_expr(mk().int_lit(0, "i32")));
+            item.attrs.push(source_location_doc("foo.c", 123));
+            assert_eq!(
+                pprust::item_to_string(&item),
+                "/// from foo.c:123\nstatic mut x: i32 = 0i32;"
+            );
+        });
+    }
+
+    #[test]
+    fn provenance_sidecar() {
+        let records = vec![LiteralProvenance {
+            expr_id: CExprId(42),
+            file: Some(PathBuf::from("src/foo.c")),
+            line: 3,
+            column: 13,
+            rust: "300i32".to_string(),
+        }];
+        let json: serde_json::Value = serde_json::from_str(&provenance_json(&records)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([{
+                "expr_id": 42,
+                "file": "src/foo.c",
+                "line": 3,
+                "column": 13,
+                "rust": "300i32",
+            }])
+        );
+    }
+
+    #[test]
+    fn size_checks() {
+        syntax::with_default_globals(|| {
+            let size = mk().call_expr(
+                mk().path_expr(vec!["size_of"]),
+                vec![] as Vec<P<Expr>>,
+            );
+            assert_eq!(
+                pprust::item_to_string(&size_check_item(size, 8)),
+                "const _: [(); 8] = [(); size_of()];"
+            );
+        });
+    }
+
+    #[test]
+    fn cleanup_wrappers() {
+        syntax::with_default_globals(|| {
+            let ty = mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_char"]));
+            let items = cleanup_wrapper_items("p_cleanup", ty, "free_it", None);
+            let items: Vec<String> = items.iter().map(|i| pprust::item_to_string(i)).collect();
+            assert_eq!(
+                items,
+                vec![
+                    "struct p_cleanup(*mut *mut libc::c_char);",
+                    concat!(
+                        "impl Drop for p_cleanup {\n",
+                        "    fn drop(&mut self) { unsafe { free_it(self.0); } }\n",
+                        "}",
+                    ),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn top_level_export_order_types_first() {
+        let typ = CQualTypeId::new(CTypeId(0));
+        let function = |name: &str, is_implicit| CDeclKind::Function {
+            is_global: true,
+            is_inline: false,
+            is_implicit,
+            is_extern: false,
+            typ: CTypeId(0),
+            name: name.to_string(),
+            parameters: vec![],
+            body: None,
+            attrs: IndexSet::new(),
+        };
+        let kinds = vec![
+            function("f", false),
+            CDeclKind::Struct {
+                name: Some("s".to_string()),
+                fields: None,
+                is_packed: false,
+                manual_alignment: None,
+                max_field_alignment: None,
+                platform_byte_size: 0,
+                platform_alignment: 0,
+            },
+            CDeclKind::Typedef { name: "s".to_string(), typ, is_implicit: false },
+            CDeclKind::Typedef { name: "t".to_string(), typ, is_implicit: false },
+            CDeclKind::Variable {
+                has_static_duration: true,
+                has_thread_duration: false,
+                is_externally_visible: true,
+                is_defn: true,
+                ident: "v".to_string(),
+                initializer: None,
+                typ,
+                attrs: IndexSet::new(),
+            },
+            function("g", true),
+            CDeclKind::MacroObject { name: "M".to_string(), replacements: vec![] },
+        ];
+        let decls: Vec<(CDeclId, CDecl)> = kinds
+            .into_iter()
+            .enumerate()
+            .map(|(i, kind)| (CDeclId(i as u64), CDecl { loc: None, kind }))
+            .collect();
+        let top = [CDeclId(0), CDeclId(4), CDeclId(5), CDeclId(6)];
+        let mut prenamed = IndexMap::new();
+        prenamed.insert(CDeclId(2), CDeclId(1));
+
+        let order = |translate_const_macros| {
+            let decls = decls.iter().map(|(id, decl)| (id, decl));
+            top_level_export_order(decls, &top, &prenamed, translate_const_macros)
+                .into_iter()
+                .map(|(CDeclId(id), is_type)| (id, is_type))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(false), vec![(1, true), (3, true), (0, false), (4, false)]);
+        assert_eq!(
+            order(true),
+            vec![(1, true), (3, true), (0, false), (4, false), (6, false)]
+        );
+    }
+
+    /// `typedef int myint; myint counter = 3; const char *a = "hi", *b = "hi";`. The repeated
+    /// string literal is interned in a static, which is added while converting `a`.
+    fn small_translation_unit() -> TypedAstContext {
+        fn global(ident: &str, typ: CQualTypeId, init: CExprId) -> CDeclKind {
+            CDeclKind::Variable {
+                has_static_duration: true,
+                has_thread_duration: false,
+                is_externally_visible: true,
+                is_defn: true,
+                ident: ident.to_string(),
+                initializer: Some(init),
+                typ,
+                attrs: IndexSet::new(),
+            }
+        }
+
+        let mut ast = TypedAstContext::new(&[]);
+        let int = CQualTypeId::new(ast.add_type(CTypeKind::Int));
+        let myint = ast.add_top_decl(CDeclKind::Typedef {
+            name: "myint".to_string(),
+            typ: int,
+            is_implicit: false,
+        });
+        let myint = CQualTypeId::new(ast.add_type(CTypeKind::Typedef(myint)));
+        let three = ast.add_expr(CExprKind::Literal(int, CLiteral::Integer(3, IntBase::Dec)));
+        ast.add_top_decl(global("counter", myint, three));
+
+        let char_ty = ast.add_type(CTypeKind::Char);
+        let const_char = CQualTypeId {
+            qualifiers: Qualifiers { is_const: true, ..Qualifiers::default() },
+            ctype: char_ty,
+        };
+        let string = CQualTypeId::new(ast.add_type(CTypeKind::ConstantArray(char_ty, 3)));
+        let pointer = CQualTypeId::new(ast.add_type(CTypeKind::Pointer(const_char)));
+        for &ident in &["a", "b"] {
+            let hi = ast.add_expr(CExprKind::Literal(string, CLiteral::String(b"hi".to_vec(), 1)));
+            let decayed = ast.add_expr(CExprKind::ImplicitCast(
+                pointer,
+                hi,
+                CastKind::ArrayToPointerDecay,
+                None,
+                LRValue::RValue,
+            ));
+            ast.add_top_decl(global(ident, pointer, decayed));
+        }
+        ast
+    }
+
+    #[test]
+    fn top_level_items_match_whole_file() {
+        let tcfg = crate::TranspilerConfig::for_tests();
+        let main_file = PathBuf::from("small.c");
+
+        let (whole_file, ..) = translate(small_translation_unit(), &tcfg, main_file.clone());
+        let item_name = regex::Regex::new(r"(?m)^(?:pub )?(?:static mut|static|type) (\w+)");
+        let expected: Vec<&str> = item_name
+            .unwrap()
+            .captures_iter(&whole_file)
+            .map(|c| c.get(1).unwrap().as_str())
+            .collect();
+        let order = vec!["myint", "counter", "STRING_LITERAL", "a", "b"];
+        assert_eq!(expected, order, "{}", whole_file);
+
+        syntax::with_default_globals(|| {
+            let mut t = Translation::new(small_translation_unit(), &tcfg, &main_file);
+            t.prepare();
+            let mut names = vec![];
+            for translated in t.top_level_items() {
+                for (_, mut side_items) in translated.side_items {
+                    let (items, _, _) = side_items.drain();
+                    names.extend(items.iter().map(|item| item.ident.to_string()));
+                }
+                match translated.result {
+                    Ok(ConvertedDecl::Item(item)) => names.push(item.ident.to_string()),
+                    Ok(ConvertedDecl::Items(items)) => {
+                        names.extend(items.iter().map(|item| item.ident.to_string()))
+                    }
+                    result => panic!("unexpected conversion result {:?}", result),
+                }
+            }
+            assert_eq!(names, expected);
+        });
+    }
+}
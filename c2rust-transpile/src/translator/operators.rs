@@ -596,6 +596,36 @@ impl<'c> Translation<'c> {
             .kind
             .is_unsigned_integral_type();
 
+        // Unsigned arithmetic involving a literal can be done on `Wrapping` values, e.g.
+        // `(::std::num::Wrapping(x) + ::std::num::Wrapping(1u32)).0`.
+        let wrapping_op = match op {
+            c_ast::BinOp::Add => Some(BinOpKind::Add),
+            c_ast::BinOp::Subtract => Some(BinOpKind::Sub),
+            c_ast::BinOp::Multiply => Some(BinOpKind::Mul),
+            c_ast::BinOp::Divide => Some(BinOpKind::Div),
+            c_ast::BinOp::Modulus => Some(BinOpKind::Rem),
+            _ => None,
+        };
+        if let (Some(bin_op), Some((lhs_id, rhs_id))) = (wrapping_op, lhs_rhs_ids) {
+            let lhs_lit = self.int_literal_operand(lhs_id);
+            let rhs_lit = self.int_literal_operand(rhs_id);
+            if self.tcfg.wrapping_literals
+                && is_unsigned_integral_type
+                && !ctx.is_const
+                && (lhs_lit.is_some() || rhs_lit.is_some())
+            {
+                // The operands have been converted to the result type, so literals are rebuilt
+                // with that type to match.
+                let ty = CQualTypeId::new(ctype);
+                let wrap = |lit: Option<(u128, IntBase)>, expr: P<Expr>| match lit {
+                    Some((val, base)) => self.mk_wrapping_int_lit(ty, val, base),
+                    None => Ok(self.wrapping_expr(expr)),
+                };
+                let val = mk().binary_expr(bin_op, wrap(lhs_lit, lhs)?, wrap(rhs_lit, rhs)?);
+                return Ok(mk().field_expr(val, "0"));
+            }
+        }
+
         match op {
             c_ast::BinOp::Add => self.convert_addition(ctx, lhs_type, rhs_type, lhs, rhs),
             c_ast::BinOp::Subtract => self.convert_subtraction(ctx, ty, lhs_type, rhs_type, lhs, rhs),
@@ -930,7 +960,7 @@ impl<'c> Translation<'c> {
                 // In this translation, there are only pointers to functions and
                 // & becomes a no-op when applied to a function.
 
-                let arg = self.convert_expr(ctx.used().set_needs_address(true), arg)?;
+                let mut arg = self.convert_expr(ctx.used().set_needs_address(true), arg)?;
 
                 if self.ast_context.is_function_pointer(ctype) {
                     Ok(arg.map(|x| mk().call_expr(mk().ident_expr("Some"), vec![x])))
@@ -946,6 +976,15 @@ impl<'c> Translation<'c> {
                         Mutability::Mutable
                     };
 
+                    // A compound literal lives until the end of its enclosing block in C,
+                    // but a Rust temporary is dropped at the end of its statement, which
+                    // would leave the pointer dangling. Give the literal a local instead.
+                    if let CExprKind::CompoundLiteral(..) = arg_kind {
+                        if !ctx.is_static {
+                            arg = self.bind_to_temporary("compound_literal", mutbl, arg);
+                        }
+                    }
+
                     arg.result_map(|a| {
                         let mut addr_of_arg: P<Expr>;
 
@@ -1037,7 +1076,27 @@ impl<'c> Translation<'c> {
                 let arg = self.convert_expr(ctx, arg)?;
                 Ok(arg)
             }
-            c_ast::UnOp::Real | c_ast::UnOp::Imag | c_ast::UnOp::Coawait => {
+            c_ast::UnOp::Real | c_ast::UnOp::Imag => {
+                let arg_ty = self.ast_context[arg]
+                    .kind
+                    .get_type()
+                    .ok_or_else(|| format_err!("bad {:?} operand", name))?;
+                let is_complex = self.ast_context.resolve_type(arg_ty).kind.is_complex();
+                match name {
+                    // `num_complex::Complex` keeps both parts in public fields, which also
+                    // makes `__real__ z = x` work
+                    c_ast::UnOp::Real if is_complex => {
+                        Ok(self.convert_expr(ctx, arg)?.map(|z| mk().field_expr(z, "re")))
+                    }
+                    c_ast::UnOp::Imag if is_complex => {
+                        Ok(self.convert_expr(ctx, arg)?.map(|z| mk().field_expr(z, "im")))
+                    }
+                    // On a real number, `__real__` is the number itself and `__imag__` is zero
+                    c_ast::UnOp::Real => self.convert_expr(ctx, arg),
+                    _ => self.implicit_default_expr(ctype, ctx.is_static),
+                }
+            }
+            c_ast::UnOp::Coawait => {
                 panic!("Unsupported extension operator")
             }
         }
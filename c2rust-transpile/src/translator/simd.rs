@@ -291,6 +291,13 @@ impl<'c> Translation<'c> {
     }
 
     /// Translate a list initializer corresponding to a vector type.
+    ///
+    /// Vectors with a matching x86 `setr` intrinsic are built by calling it. Any other
+    /// element type and length combination, as well as vectors in statics, are built as
+    /// an array of the element type which is transmuted into the vector type.
+    ///
+    /// As in C, a list with a single scalar such as `{2}` only sets the first lane; see
+    /// `vector_splat` for broadcasts.
     pub fn vector_list_initializer(
         &self,
         ctx: ExprContext,
@@ -298,51 +305,57 @@ impl<'c> Translation<'c> {
         ctype: CTypeId,
         len: usize,
     ) -> Result<WithStmts<P<Expr>>, TranslationError> {
-        let param_translation = self.convert_exprs(ctx, ids)?;
-        param_translation.and_then(|mut params| {
-            // When used in a static, we cannot call the standard functions since they
-            // are not const and so we are forced to transmute
-            let call = if ctx.is_static {
-                let tuple = mk().tuple_expr(params);
-                let transmute = transmute_expr(
-                    mk().infer_ty(),
-                    mk().infer_ty(),
-                    tuple,
-                    self.tcfg.emit_no_std,
-                );
+        let mut param_translation = self.convert_exprs(ctx, ids)?;
+
+        // Elements missing from the initializer list are zeroed, as the intrinsics
+        // expect exactly one parameter per lane
+        for _ in ids.len()..len {
+            let default = self.implicit_default_expr(ctype, ctx.is_static)?;
+            param_translation = param_translation.and_then(|mut params| -> Result<_, TranslationError> {
+                Ok(default.map(|default| {
+                    params.push(default);
+                    params
+                }))
+            })?;
+        }
 
-                self.use_feature("const_transmute");
+        let fn_call_name = if ctx.is_static {
+            None
+        } else {
+            vector_init_fn_name(&self.ast_context[ctype].kind, len)
+        };
 
-                transmute
-            } else {
-                let fn_call_name = match (&self.ast_context[ctype].kind, len) {
-                    (Float, 4) => "_mm_setr_ps",
-                    (Float, 8) => "_mm256_setr_ps",
-                    (Double, 2) => "_mm_setr_pd",
-                    (Double, 4) => "_mm256_setr_pd",
-                    (LongLong, 2) => "_mm_set_epi64x",
-                    (LongLong, 4) => "_mm256_setr_epi64x",
-                    (Char, 8) => "_mm_setr_pi8",
-                    (Char, 16) => "_mm_setr_epi8",
-                    (Char, 32) => "_mm256_setr_epi8",
-                    (Int, 2) => "_mm_setr_pi32",
-                    (Int, 4) => "_mm_setr_epi32",
-                    (Int, 8) => "_mm256_setr_epi32",
-                    (Short, 4) => "_mm_setr_pi16",
-                    (Short, 8) => "_mm_setr_epi16",
-                    (Short, 16) => "_mm256_setr_epi16",
-                    ref e => Err(format_err!("Unknown vector init list: {:?}", e))?,
-                };
+        let mut is_unsafe = false;
+        let mut val = param_translation.and_then(|mut params| -> Result<_, TranslationError> {
+            let call = match fn_call_name {
+                Some(fn_call_name) => {
+                    self.import_simd_function(fn_call_name)?;
 
-                self.import_simd_function(fn_call_name)?;
+                    // rust is missing support for _mm_setr_epi64x, so we have to use
+                    // the reverse arguments for _mm_set_epi64x
+                    if fn_call_name == "_mm_set_epi64x" {
+                        params.reverse();
+                    }
 
-                // rust is missing support for _mm_setr_epi64x, so we have to use
-                // the reverse arguments for _mm_set_epi64x
-                if fn_call_name == "_mm_set_epi64x" {
-                    params.reverse();
+                    mk().call_expr(mk().ident_expr(fn_call_name), params)
+                }
+                // When used in a static, we cannot call the standard functions since they
+                // are not const and so we are forced to transmute
+                None => {
+                    let elem_ty = self.convert_type(ctype)?;
+                    let array_ty = mk().array_ty(elem_ty, mk().lit_expr(mk().int_lit(len as u128, "")));
+                    if ctx.is_static {
+                        self.use_feature("const_transmute");
+                    }
+                    is_unsafe = true;
+
+                    transmute_expr(
+                        array_ty,
+                        mk().infer_ty(),
+                        mk().array_expr(params),
+                        self.tcfg.emit_no_std,
+                    )
                 }
-
-                mk().call_expr(mk().ident_expr(fn_call_name), params)
             };
 
             if ctx.is_used() {
@@ -353,7 +366,57 @@ impl<'c> Translation<'c> {
                     self.panic_or_err("No value for unused shuffle vector return"),
                 ))
             }
-        })
+        })?;
+        val.merge_unsafe(is_unsafe);
+
+        Ok(val)
+    }
+
+    /// Translate a vector splat, which copies the scalar `val` into every lane of a vector of
+    /// `len` elements of type `ctype`. Clang inserts these to broadcast the scalar operand of
+    /// a vector operation, such as the `2` in `v * 2`.
+    ///
+    /// Vectors with a matching x86 `set1` intrinsic are built by calling it. Any other element
+    /// type and length combination, as well as vectors in statics, are built as a repeated
+    /// array of the element type which is transmuted into the vector type.
+    pub fn vector_splat(
+        &self,
+        ctx: ExprContext,
+        val: WithStmts<P<Expr>>,
+        ctype: CTypeId,
+        len: usize,
+    ) -> Result<WithStmts<P<Expr>>, TranslationError> {
+        let fn_call_name = if ctx.is_static {
+            None
+        } else {
+            vector_splat_fn_name(&self.ast_context[ctype].kind, len)
+        };
+
+        match fn_call_name {
+            Some(fn_call_name) => {
+                self.import_simd_function(fn_call_name)?;
+                Ok(val.map(|val| mk().call_expr(mk().ident_expr(fn_call_name), vec![val])))
+            }
+            None => {
+                let elem_ty = self.convert_type(ctype)?;
+                let len = mk().lit_expr(mk().int_lit(len as u128, ""));
+                let array_ty = mk().array_ty(elem_ty, len.clone());
+                if ctx.is_static {
+                    self.use_feature("const_transmute");
+                }
+
+                let mut val = val.map(|val| {
+                    transmute_expr(
+                        array_ty,
+                        mk().infer_ty(),
+                        mk().repeat_expr(val, len),
+                        self.tcfg.emit_no_std,
+                    )
+                });
+                val.set_unsafe();
+                Ok(val)
+            }
+        }
     }
 
     /// Convert a shuffle operation into the equivalent Rust SIMD library calls.
@@ -582,3 +645,75 @@ impl<'c> Translation<'c> {
         }
     }
 }
+
+/// Name of the x86 intrinsic which builds a vector of `len` elements of the given kind
+/// from its lanes in memory order, if there is one.
+fn vector_init_fn_name(kind: &CTypeKind, len: usize) -> Option<&'static str> {
+    let name = match (kind, len) {
+        (Float, 4) => "_mm_setr_ps",
+        (Float, 8) => "_mm256_setr_ps",
+        (Double, 2) => "_mm_setr_pd",
+        (Double, 4) => "_mm256_setr_pd",
+        (LongLong, 2) => "_mm_set_epi64x",
+        (LongLong, 4) => "_mm256_setr_epi64x",
+        (Char, 8) => "_mm_setr_pi8",
+        (Char, 16) => "_mm_setr_epi8",
+        (Char, 32) => "_mm256_setr_epi8",
+        (Int, 2) => "_mm_setr_pi32",
+        (Int, 4) => "_mm_setr_epi32",
+        (Int, 8) => "_mm256_setr_epi32",
+        (Short, 4) => "_mm_setr_pi16",
+        (Short, 8) => "_mm_setr_epi16",
+        (Short, 16) => "_mm256_setr_epi16",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+/// Get the x86 intrinsic which broadcasts one value of type `kind` to a vector of `len` elements.
+fn vector_splat_fn_name(kind: &CTypeKind, len: usize) -> Option<&'static str> {
+    let name = match (kind, len) {
+        (Float, 4) => "_mm_set1_ps",
+        (Float, 8) => "_mm256_set1_ps",
+        (Double, 2) => "_mm_set1_pd",
+        (Double, 4) => "_mm256_set1_pd",
+        (LongLong, 2) => "_mm_set1_epi64x",
+        (LongLong, 4) => "_mm256_set1_epi64x",
+        (Char, 8) => "_mm_set1_pi8",
+        (Char, 16) => "_mm_set1_epi8",
+        (Char, 32) => "_mm256_set1_epi8",
+        (Int, 2) => "_mm_set1_pi32",
+        (Int, 4) => "_mm_set1_epi32",
+        (Int, 8) => "_mm256_set1_epi32",
+        (Short, 4) => "_mm_set1_pi16",
+        (Short, 8) => "_mm_set1_epi16",
+        (Short, 16) => "_mm256_set1_epi16",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splat_intrinsics() {
+        assert_eq!(vector_splat_fn_name(&Int, 4), Some("_mm_set1_epi32"));
+        assert_eq!(vector_splat_fn_name(&Float, 8), Some("_mm256_set1_ps"));
+        assert_eq!(vector_splat_fn_name(&LongLong, 2), Some("_mm_set1_epi64x"));
+        assert_eq!(vector_splat_fn_name(&Int, 3), None);
+
+        // Every splat intrinsic broadcasts to the same vectors as a `setr` intrinsic builds
+        for kind in &[Float, Double, LongLong, Char, Int, Short] {
+            for len in 1..=32 {
+                assert_eq!(
+                    vector_splat_fn_name(kind, len).is_some(),
+                    vector_init_fn_name(kind, len).is_some()
+                );
+            }
+        }
+    }
+}
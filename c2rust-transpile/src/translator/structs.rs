@@ -2,25 +2,25 @@
 //! This module provides translation for bitfield structs and operations on them. Generated code
 //! requires the use of the c2rust-bitfields crate.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::Index;
 
 use super::TranslationError;
-use crate::c_ast::{BinOp, CDeclId, CDeclKind, CExprId, CRecordId, CTypeId};
+use crate::c_ast::{
+    BinOp, CDeclId, CDeclKind, CExprId, CExprKind, CFieldId, CRecordId, CTypeId, CTypeKind,
+};
 use crate::translator::{ExprContext, Translation, PADDING_SUFFIX};
 use crate::with_stmts::WithStmts;
 use c2rust_ast_builder::mk;
 use c2rust_ast_printer::pprust;
 use syntax::ast::{
     self, AttrStyle, BinOpKind, Expr, ExprKind, Lit, LitIntType, LitKind, MetaItemKind,
-    NestedMetaItem, StmtKind, StrStyle, StructField, Ty, TyKind,
+    Mutability, NestedMetaItem, StmtKind, StrStyle, StructField, Ty, TyKind,
 };
 use syntax::ptr::P;
 use syntax::source_map::symbol::Symbol;
 use syntax_pos::DUMMY_SP;
 
-use itertools::EitherOrBoth::{Both, Right};
-use itertools::Itertools;
 
 #[derive(Debug)]
 enum FieldType {
@@ -28,7 +28,7 @@ enum FieldType {
         start_bit: u64,
         field_name: String,
         bytes: u64,
-        attrs: Vec<(String, P<Ty>, String)>,
+        attrs: Vec<(String, P<Ty>, (u64, u64))>,
     },
     Padding {
         bytes: u64,
@@ -72,6 +72,50 @@ fn assigment_metaitem(lhs: &str, rhs: &str) -> NestedMetaItem {
     mk().nested_meta_item(NestedMetaItem::MetaItem(meta_item))
 }
 
+/// Build the bytes of a bitfield group of `bytes` bytes holding the values in `inits`, each with
+/// the first and last bit of the group it occupies. Each byte is the bitwise or of the parts of
+/// the masked values that overlap it, shifted into place, which, unlike the `set_*` methods of
+/// c2rust-bitfields, is a constant expression.
+fn packed_bitfield_bytes(bytes: u64, inits: &[(P<Expr>, (u64, u64))]) -> P<Expr> {
+    let storage_ty = if inits.iter().any(|(_, (start, end))| end - start >= 64) {
+        "u128"
+    } else {
+        "u64"
+    };
+    let int_lit = |val: u128| mk().lit_expr(mk().int_lit(val, LitIntType::Unsuffixed));
+
+    let byte_exprs = (0..bytes).map(|byte| {
+        let byte_start = byte * 8;
+        let byte_end = byte_start + 7;
+        let parts = inits
+            .iter()
+            .filter(|(_, (start, end))| *start <= byte_end && *end >= byte_start)
+            .map(|(val, (start, end))| {
+                let mask = u128::max_value() >> (127 - (end - start));
+                let val = mk().cast_expr(val.clone(), mk().ident_ty(storage_ty));
+                let part = mk().binary_expr(BinOpKind::BitAnd, val, int_lit(mask));
+                if *start > byte_start {
+                    let shift = int_lit((start - byte_start).into());
+                    mk().binary_expr(BinOpKind::Shl, part, shift)
+                } else if *start < byte_start {
+                    let shift = int_lit((byte_start - start).into());
+                    mk().binary_expr(BinOpKind::Shr, part, shift)
+                } else {
+                    part
+                }
+            });
+        let byte_expr = parts
+            .fold(None, |acc, part| match acc {
+                Some(acc) => Some(mk().binary_expr(BinOpKind::BitOr, acc, part)),
+                None => Some(part),
+            })
+            .unwrap_or_else(|| int_lit(0));
+        mk().cast_expr(byte_expr, mk().ident_ty("u8"))
+    });
+
+    mk().array_expr(byte_exprs.collect())
+}
+
 impl<'a> Translation<'a> {
     /// This method aggregates bitfield struct field information by way of:
     /// 1. Collecting consecutive bytes of bitfields into a single FieldType::BitfieldGroup
@@ -207,9 +251,8 @@ impl<'a> Translation<'a> {
 
                         let bit_start = platform_bit_offset - start_bit;
                         let bit_end = bit_start + bitfield_width - 1;
-                        let bit_range = format!("{}..={}", bit_start, bit_end);
 
-                        attrs.push((field_name.clone(), ty, bit_range));
+                        attrs.push((field_name.clone(), ty, (bit_start, bit_end)));
                     }
                     Some(_) => unreachable!("Found last bitfield group which is not a group"),
                     None => {
@@ -227,8 +270,7 @@ impl<'a> Translation<'a> {
                             }
                         }
 
-                        let bit_range = format!("0..={}", bitfield_width - 1);
-                        let attrs = vec![(field_name.clone(), ty, bit_range)];
+                        let attrs = vec![(field_name.clone(), ty, (0, bitfield_width - 1))];
 
                         last_bitfield_group = Some(FieldType::BitfieldGroup {
                             start_bit: platform_bit_offset,
@@ -311,10 +353,11 @@ impl<'a> Translation<'a> {
                             TyKind::Path(_, path) => pprust::path_to_string(path),
                             _ => unreachable!("Found type other than path"),
                         };
+                        let (bit_start, bit_end) = attr.2;
                         let field_attr_items = vec![
                             assigment_metaitem("name", &attr.0),
                             assigment_metaitem("ty", &ty_str),
-                            assigment_metaitem("bits", &attr.2),
+                            assigment_metaitem("bits", &format!("{}..={}", bit_start, bit_end)),
                         ];
 
                         mk().meta_item("bitfield", MetaItemKind::List(field_attr_items))
@@ -361,19 +404,16 @@ impl<'a> Translation<'a> {
         Ok(field_entries)
     }
 
-    /// Here we output a block to generate a struct literal initializer in.
-    /// It looks like this in locals and (sectioned) statics:
+    /// Here we output a struct literal initializer, packing the values of bitfields into the
+    /// bytes of their group with shifts and masks so that it can also initialize statics.
+    /// It looks like this:
     ///
     /// ```no_run
-    /// {
-    ///     let mut init = Foo {
-    ///         bf1_bf2: [0; 2],
-    ///         non_bf: 32,
-    ///         _pad: [0; 2],
-    ///     };
-    ///     init.set_bf1(-12);
-    ///     init.set_bf2(34);
-    ///     init
+    /// Foo {
+    ///     bf1_bf2: [(-12 as u64 & 1023) as u8,
+    ///               ((-12 as u64 & 1023) >> 8 | (34 as u64 & 63) << 2) as u8],
+    ///     non_bf: 32,
+    ///     _pad: [0; 2],
     /// }
     /// ```
     pub fn convert_struct_literal(
@@ -401,9 +441,9 @@ impl<'a> Translation<'a> {
         };
 
         let mut fields = Vec::with_capacity(field_decl_ids.len());
+        let mut bitfield_groups = Vec::new();
         let reorganized_fields =
             self.get_field_types(struct_id, field_decl_ids, platform_byte_size)?;
-        let local_pat = mk().mutbl().ident_pat("init");
         let mut padding_count = 0;
         let mut next_padding_field = || {
             let field_name = self
@@ -414,20 +454,13 @@ impl<'a> Translation<'a> {
             field_name
         };
 
-        // Add in zero inits for both padding as well as bitfield groups
+        // Add in zero inits for padding, and set aside bitfield groups until the values of
+        // their bitfields are known
         for field_type in reorganized_fields {
             match field_type {
                 FieldType::BitfieldGroup {
-                    field_name, bytes, ..
-                } => {
-                    let array_expr = mk().repeat_expr(
-                        mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed)),
-                        mk().lit_expr(mk().int_lit(bytes.into(), LitIntType::Unsuffixed)),
-                    );
-                    let field = mk().field(field_name, array_expr);
-
-                    fields.push(WithStmts::new_val(field));
-                }
+                    field_name, bytes, attrs, ..
+                } => bitfield_groups.push((field_name, bytes, attrs)),
                 FieldType::Padding { bytes } => {
                     let field_name = next_padding_field();
                     let array_expr = mk().repeat_expr(
@@ -474,18 +507,18 @@ impl<'a> Translation<'a> {
 
                     let use_inner_type =
                         is_packed && self.ast_context.is_aligned_struct_type(typ.ctype);
-                    Some((field_name, typ, bitfield_width, use_inner_type))
+                    Some((*field_id, field_name, typ, bitfield_width, use_inner_type))
                 }
                 _ => None,
             }
         });
-        let zipped_iter = field_expr_ids.iter().zip_longest(field_info_iter);
-        let mut bitfield_inits = Vec::new();
+        let field_inits = self.struct_field_inits(field_decl_ids, field_expr_ids);
+        let mut bitfield_inits = HashMap::new();
 
         // Specified record fields which are not bitfields need to be added
-        for item in zipped_iter {
-            match item {
-                Right((field_name, ty, bitfield_width, use_inner_type)) => {
+        for (field_id, field_name, ty, bitfield_width, use_inner_type) in field_info_iter {
+            match field_inits.get(&field_id) {
+                None => {
                     if bitfield_width.is_some() {
                         continue;
                     }
@@ -505,8 +538,33 @@ impl<'a> Translation<'a> {
                     let field = init.map(|init| mk().field(field_name, init));
                     fields.push(field);
                 }
-                Both(field_id, (field_name, _, bitfield_width, use_inner_type)) => {
-                    let mut expr = self.convert_expr(ctx.used(), *field_id)?;
+                Some(&expr_id) => {
+                    if let Some(len) = self.flexible_array_init_len(ty.ctype, expr_id) {
+                        return Err(format_err!(
+                            "Initializing flexible array member `{}` with {} elements is not supported",
+                            field_name,
+                            len
+                        )
+                        .into());
+                    }
+
+                    let expr = match self.struct_init_list(expr_id) {
+                        // A nested initializer list for a field holding the inner
+                        // structure can build that structure directly, rather than
+                        // wrapping it in the outer structure only to unwrap it again
+                        Some((inner_id, inner_ids)) if use_inner_type => {
+                            self.convert_struct_literal(ctx, inner_id, inner_ids)?
+                        }
+                        _ => {
+                            let expr = self.convert_expr(ctx.used(), expr_id)?;
+                            if use_inner_type {
+                                // See comment above
+                                expr.map(|fi| mk().field_expr(fi, "0"))
+                            } else {
+                                expr
+                            }
+                        }
+                    };
 
                     if !expr.is_pure() {
                         return Err(TranslationError::generic(
@@ -514,59 +572,136 @@ impl<'a> Translation<'a> {
                         ));
                     }
 
-                    if use_inner_type {
-                        // See comment above
-                        expr = expr.map(|fi| mk().field_expr(fi, "0"));
-                    }
-
                     if bitfield_width.is_some() {
-                        bitfield_inits.push((field_name, expr));
+                        bitfield_inits.insert(field_name, (expr, expr_id));
 
                         continue;
                     }
 
                     fields.push(expr.map(|expr| mk().field(field_name, expr)));
                 }
-                _ => unreachable!(),
             }
         }
 
-        fields
-            .into_iter()
-            .collect::<WithStmts<Vec<ast::Field>>>()
-            .and_then(|fields| {
-                let struct_expr = mk().struct_expr(name.as_str(), fields);
-                let local_variable =
-                    P(mk().local(local_pat, None as Option<P<Ty>>, Some(struct_expr)));
-
-                let mut is_unsafe = false;
-                let mut stmts = vec![mk().local_stmt(local_variable)];
-
-                // Now we must use the bitfield methods to initialize bitfields
-                for (field_name, val) in bitfield_inits {
-                    let field_name_setter = format!("set_{}", field_name);
-                    let struct_ident = mk().ident_expr("init");
+        // Zero bitfield groups with no initialized bitfields, and pack the values of the
+        // bitfields of the others into their bytes
+        for (field_name, bytes, attrs) in bitfield_groups {
+            let mut is_unsafe = false;
+            let mut stmts = Vec::new();
+            let inits: Vec<_> = attrs
+                .iter()
+                .filter_map(|(name, _, bit_range)| {
+                    let (val, expr_id) = bitfield_inits.remove(name)?;
+                    // The value is used once for every byte it overlaps, so one with side
+                    // effects is bound to a temporary to only evaluate it once. Static
+                    // initializers are constant, so they never have side effects.
+                    let val = if !ctx.is_static && !self.ast_context.is_expr_pure(expr_id) {
+                        self.bind_to_temporary("bitfield_init", Mutability::Immutable, val)
+                    } else {
+                        val
+                    };
                     is_unsafe |= val.is_unsafe();
-                    let val = val
-                        .to_pure_expr()
-                        .expect("Expected no statements in bitfield initializer");
-                    let expr = mk().method_call_expr(struct_ident, field_name_setter, vec![val]);
+                    let (val_stmts, val) = val.discard_unsafe();
+                    stmts.extend(val_stmts);
+                    Some((val, *bit_range))
+                })
+                .collect();
+
+            let array_expr = if inits.is_empty() {
+                mk().repeat_expr(
+                    mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed)),
+                    mk().lit_expr(mk().int_lit(bytes.into(), LitIntType::Unsuffixed)),
+                )
+            } else {
+                packed_bitfield_bytes(bytes, &inits)
+            };
+            // Keep the temporaries in a block so that the initializer stays an expression,
+            // which a nested struct initializer needs
+            let array_expr = if stmts.is_empty() {
+                array_expr
+            } else {
+                stmts.push(mk().expr_stmt(array_expr));
+                mk().block_expr(mk().block(stmts))
+            };
+            let field = mk().field(field_name, array_expr);
 
-                    stmts.push(mk().expr_stmt(expr));
-                }
+            if is_unsafe {
+                fields.push(WithStmts::new_unsafe_val(field));
+            } else {
+                fields.push(WithStmts::new_val(field));
+            }
+        }
 
-                let struct_ident = mk().ident_expr("init");
+        Ok(fields
+            .into_iter()
+            .collect::<WithStmts<Vec<ast::Field>>>()
+            .map(|fields| mk().struct_expr(name.as_str(), fields)))
+    }
 
-                stmts.push(mk().expr_stmt(struct_ident));
+    /// If `field_ty` is a flexible array member, i.e. `T[]` or `T[0]`, and `expr_id` gives it
+    /// elements, return how many. Such a field is translated to a zero-length array, so it has
+    /// no room for them.
+    fn flexible_array_init_len(&self, field_ty: CTypeId, expr_id: CExprId) -> Option<usize> {
+        match self.ast_context.resolve_type(field_ty).kind {
+            CTypeKind::IncompleteArray(_) | CTypeKind::ConstantArray(_, 0) => {}
+            _ => return None,
+        }
 
-                let val = mk().block_expr(mk().block(stmts));
+        match self.ast_context[expr_id].kind {
+            CExprKind::InitList(ty, _, _, _) => match self.ast_context.resolve_type(ty.ctype).kind {
+                CTypeKind::ConstantArray(_, len) if len > 0 => Some(len),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 
-                if is_unsafe {
-                    Ok(WithStmts::new_unsafe_val(val))
-                } else {
-                    Ok(WithStmts::new_val(val))
+    /// If `expr_id` is an initializer list for a struct, return the struct
+    /// and the initializer expressions of its fields.
+    fn struct_init_list(&self, expr_id: CExprId) -> Option<(CRecordId, &[CExprId])> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::InitList(ty, ref ids, _, _) => {
+                match self.ast_context.resolve_type(ty.ctype).kind {
+                    CTypeKind::Struct(struct_id) => Some((struct_id, ids)),
+                    _ => None,
                 }
+            }
+            _ => None,
+        }
+    }
+
+    /// Pairs the fields of a struct with the expressions that initialize them.
+    ///
+    /// Clang resolves any designators (`.field = value`) while building the
+    /// semantic form of an initializer list, so `field_expr_ids` has one entry
+    /// per initializable field in declaration order, possibly truncated.
+    /// Unnamed bitfields cannot be initialized and have no entry. Fields that
+    /// were skipped by a designator are `ImplicitValueInit`; these are left out
+    /// of the resulting map so that they get the usual default value.
+    fn struct_field_inits(
+        &self,
+        field_decl_ids: &[CFieldId],
+        field_expr_ids: &[CExprId],
+    ) -> HashMap<CFieldId, CExprId> {
+        let initializable_fields = field_decl_ids.iter().filter(|&&field_id| {
+            match self.ast_context.index(field_id).kind {
+                CDeclKind::Field {
+                    ref name,
+                    bitfield_width: Some(_),
+                    ..
+                } => !name.is_empty(),
+                _ => true,
+            }
+        });
+
+        initializable_fields
+            .zip(field_expr_ids)
+            .filter(|&(_, &expr_id)| match self.ast_context[expr_id].kind {
+                CExprKind::ImplicitValueInit(..) => false,
+                _ => true,
             })
+            .map(|(&field_id, &expr_id)| (field_id, expr_id))
+            .collect()
     }
 
     /// This method handles zero-initializing bitfield structs including bitfields
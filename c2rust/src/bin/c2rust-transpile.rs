@@ -8,7 +8,9 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use c2rust_transpile::{Diagnostic, ReplaceMode, TranspilerConfig};
+use c2rust_transpile::{
+    Diagnostic, LiteralBaseMode, NarrowingLiteralMode, ReplaceMode, TranspilerConfig,
+};
 
 fn main() {
     let yaml = load_yaml!("../transpile.yaml");
@@ -55,6 +57,7 @@ fn main() {
 
         incremental_relooper: !matches.is_present("no-incremental-relooper"),
         fail_on_error: matches.is_present("fail-on-error"),
+        stub_failed_decls: matches.is_present("stub-failed-decls"),
         fail_on_multiple: matches.is_present("fail-on-multiple"),
         filter: {
             if matches.is_present("filter") {
@@ -87,6 +90,27 @@ fn main() {
         translate_valist: true,
 
         translate_const_macros: matches.is_present("translate-const-macros"),
+        emit_char_casts: matches.is_present("emit-char-casts"),
+        literal_base: match matches.value_of("literal-base") {
+            Some("preserve") => LiteralBaseMode::PreserveSource,
+            Some("decimal") => LiteralBaseMode::Decimal,
+            Some("hex") => LiteralBaseMode::Hex,
+            _ => panic!("Invalid literal base"),
+        },
+        group_digits: matches.is_present("group-digits"),
+        wrapping_literals: matches.is_present("wrapping-literals"),
+        narrowing_literals: match matches.value_of("narrowing-literals") {
+            Some("truncate") => NarrowingLiteralMode::Truncate,
+            Some("warn") => NarrowingLiteralMode::Warn,
+            Some("strict") => NarrowingLiteralMode::Strict,
+            _ => panic!("Invalid narrowing literal mode"),
+        },
+        source_location_docs: matches.is_present("source-location-docs"),
+        prefer_byte_strings: matches.is_present("prefer-byte-strings"),
+        named_array_lengths: matches.is_present("named-array-lengths"),
+        emit_provenance: matches.is_present("emit-provenance"),
+        safe_string_literals: matches.is_present("safe-string-literals"),
+        checked_enum_values: matches.is_present("checked-enum-values"),
         disable_refactoring: matches.is_present("disable-refactoring"),
 
         use_c_loop_info: !matches.is_present("ignore-c-loop-info"),
@@ -73,6 +73,27 @@ class CFile:
         self.translate_const_macros = "translate_const_macros" in flags
         self.reorganize_definitions = "reorganize_definitions" in flags
         self.emit_build_files = "emit_build_files" in flags
+        self.translate_only = "translate_only" in flags
+        self.named_array_lengths = "named_array_lengths" in flags
+        self.wrapping_literals = "wrapping_literals" in flags
+        self.safe_string_literals = "safe_string_literals" in flags
+        self.checked_enum_values = "checked_enum_values" in flags
+        self.source_location_docs = "source_location_docs" in flags
+        self.prefer_byte_strings = "prefer_byte_strings" in flags
+        self.group_digits = "group_digits" in flags
+        self.emit_char_casts = "emit_char_casts" in flags
+        self.stub_failed_decls = "stub_failed_decls" in flags
+        self.emit_provenance = "emit_provenance" in flags
+        self.narrowing_literals = None
+        if "narrowing_literals_warn" in flags:
+            self.narrowing_literals = "warn"
+        elif "narrowing_literals_strict" in flags:
+            self.narrowing_literals = "strict"
+        self.literal_base = None
+        if "literal_base_decimal" in flags:
+            self.literal_base = "decimal"
+        elif "literal_base_hex" in flags:
+            self.literal_base = "hex"
 
     def translate(self, cc_db, extra_args: List[str] = []) -> RustFile:
         extensionless_file, _ = os.path.splitext(self.path)
@@ -102,6 +123,30 @@ class CFile:
             args.append("--reorganize-definitions")
         if self.emit_build_files:
             args.append("--emit-build-files")
+        if self.literal_base:
+            args.append("--literal-base=" + self.literal_base)
+        if self.named_array_lengths:
+            args.append("--named-array-lengths")
+        if self.wrapping_literals:
+            args.append("--wrapping-literals")
+        if self.safe_string_literals:
+            args.append("--safe-string-literals")
+        if self.checked_enum_values:
+            args.append("--checked-enum-values")
+        if self.source_location_docs:
+            args.append("--source-location-docs")
+        if self.prefer_byte_strings:
+            args.append("--prefer-byte-strings")
+        if self.group_digits:
+            args.append("--group-digits")
+        if self.emit_char_casts:
+            args.append("--emit-char-casts")
+        if self.stub_failed_decls:
+            args.append("--stub-failed-decls")
+        if self.emit_provenance:
+            args.append("--emit-provenance")
+        if self.narrowing_literals:
+            args.append("--narrowing-literals=" + self.narrowing_literals)
 
         if self.logLevel == 'DEBUG':
             args.append("--log-level=debug")
@@ -392,6 +437,13 @@ class TestDirectory:
                 self.generated_files["rust_src"].append(self.full_path + "/src/build.rs")
                 self.generated_files["rust_src"].append(self.full_path + "/src/c2rust-lib.rs")
                 self.generated_files["rust_src"].append(self.full_path + "/src/rust-toolchain")
+            if c_file.emit_provenance:
+                provenance_file, _ = os.path.splitext(translated_rust_file.path)
+                self.generated_files["rust_src"].append(provenance_file + ".provenance.json")
+
+            # Translations that aren't meant to build are only inspected by the tests
+            if c_file.translate_only:
+                continue
 
             _, rust_file_short = os.path.split(translated_rust_file.path)
             extensionless_rust_file, _ = os.path.splitext(rust_file_short)
@@ -0,0 +1,19 @@
+//! prefer_byte_strings
+
+void byte_strings(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    if (buffer_size < 5) return;
+
+    unsigned char u[] = "hi";
+    unsigned char padded[4] = "x";
+    // Plain chars may be signed, so they aren't initialized from byte strings
+    char c[] = "yo";
+
+    buffer[i++] = u[0];
+    buffer[i++] = u[2];
+    buffer[i++] = padded[0];
+    buffer[i++] = padded[3];
+    buffer[i++] = c[1];
+}
@@ -0,0 +1,25 @@
+static const char *static_utf8 = "static";
+
+void const_strings(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    // Valid UTF-8 is emitted as a `str` literal
+    const char *utf8 = "h\xc3\xa9llo";
+    buffer[i++] = utf8[0];
+    buffer[i++] = (unsigned char)utf8[1];
+    buffer[i++] = (unsigned char)utf8[2];
+    buffer[i++] = utf8[6];
+
+    // Invalid UTF-8 and interior nulls fall back to a byte string
+    const char *invalid = "\xff\xfe";
+    buffer[i++] = (unsigned char)invalid[0];
+    buffer[i++] = invalid[2];
+
+    const char *nul = "a\0b";
+    buffer[i++] = nul[0];
+    buffer[i++] = nul[1];
+    buffer[i++] = nul[2];
+
+    buffer[i++] = static_utf8[0];
+}
@@ -0,0 +1,53 @@
+void designated_arrays(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0, j;
+
+    if (buffer_size < 29) return;
+
+    // designators leave gaps which take the default value
+    int gaps[6] = { [4] = 4, [1] = 1 };
+    for (j = 0; j < 6; j++) buffer[i++] = gaps[j];
+
+    // positional elements continue from the last designator
+    int reset[5] = { 1, [3] = 3, 4, [1] = 5, 6 };
+    for (j = 0; j < 5; j++) buffer[i++] = reset[j];
+
+    // the last designator for an index wins
+    int duplicate[3] = { [1] = 1, [1] = 2 };
+    for (j = 0; j < 3; j++) buffer[i++] = duplicate[j];
+
+    // a designator can extend an array of unknown size
+    int extended[] = { 1, [4] = 9 };
+    buffer[i++] = sizeof(extended) / sizeof(extended[0]);
+    for (j = 0; j < 5; j++) buffer[i++] = extended[j];
+
+    // range designators
+    int range[5] = { [1 ... 3] = 7 };
+    for (j = 0; j < 5; j++) buffer[i++] = range[j];
+
+    static int designated_static[4] = { [2] = 2 };
+    for (j = 0; j < 4; j++) buffer[i++] = designated_static[j];
+}
+
+void range_designators(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0, j, counter = 0;
+
+    if (buffer_size < 23) return;
+
+    int full[4] = { [0 ... 3] = 5 };
+    for (j = 0; j < 4; j++) buffer[i++] = full[j];
+
+    int partial[6] = { 1, [2 ... 4] = 8, 9 };
+    for (j = 0; j < 6; j++) buffer[i++] = partial[j];
+
+    // the initializer of a range is only evaluated once
+    int once[3] = { [0 ... 2] = ++counter };
+    for (j = 0; j < 3; j++) buffer[i++] = once[j];
+
+    int partial_once[4] = { [1 ... 2] = ++counter, 7 };
+    for (j = 0; j < 4; j++) buffer[i++] = partial_once[j];
+    buffer[i++] = counter;
+
+    // later ranges override earlier ones where they overlap
+    int overlap[5] = { [0 ... 3] = 1, [2 ... 4] = 2 };
+    for (j = 0; j < 5; j++) buffer[i++] = overlap[j];
+}
@@ -0,0 +1,30 @@
+enum lengths {
+    SMALL = 2,
+    MAX = 6,
+};
+
+static char table[MAX] = {1, 2, 3};
+
+void enum_lengths(const unsigned buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 16) return;
+
+    // The size is an enum constant, so the missing elements are only known once it is
+    // evaluated
+    char buf[MAX] = {4, 5};
+    int pairs[SMALL + 1][SMALL] = {{6}, {7, 8}};
+
+    for (int j = 0; j < MAX; j++) {
+        buffer[i++] = table[j] + buf[j];
+    }
+    for (int j = 0; j < SMALL + 1; j++) {
+        for (int k = 0; k < SMALL; k++) {
+            buffer[i++] = pairs[j][k];
+        }
+    }
+    buffer[i++] = sizeof(buf);
+    buffer[i++] = sizeof(table) / sizeof(table[0]);
+    buffer[i++] = sizeof(pairs) / sizeof(pairs[0]);
+    buffer[i++] = sizeof(pairs[0]) / sizeof(pairs[0][0]);
+}
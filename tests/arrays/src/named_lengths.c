@@ -0,0 +1,25 @@
+//! named_array_lengths
+
+struct quad {
+    int cells[4];
+};
+
+static int totals[4] = {1, 2, 3, 4};
+
+void named_lengths(const unsigned buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 9) return;
+
+    // Every array here has 4 elements, so they all share one length constant
+    int zeros[4] = {0};
+    struct quad q = {{5, 6, 7, 8}};
+
+    for (int j = 0; j < 4; j++) {
+        buffer[i++] = totals[j] + zeros[j];
+    }
+    for (int j = 0; j < 4; j++) {
+        buffer[i++] = q.cells[j];
+    }
+    buffer[i++] = sizeof(zeros) / sizeof(zeros[0]);
+}
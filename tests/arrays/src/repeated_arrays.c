@@ -0,0 +1,37 @@
+struct pair {
+    int a, b;
+};
+
+static int static_table[1000] = {0};
+
+void repeated_arrays(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0, j, sum;
+
+    if (buffer_size < 9) return;
+
+    char zeroed[4096] = {0};
+    for (sum = 0, j = 0; j < 4096; j++) sum += zeroed[j];
+    buffer[i++] = sum;
+
+    for (sum = 0, j = 0; j < 1000; j++) sum += static_table[j];
+    buffer[i++] = sum;
+
+    int sevens[64] = { 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+                       7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+                       7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+                       7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7 };
+    for (sum = 0, j = 0; j < 64; j++) sum += sevens[j];
+    buffer[i++] = sum;
+
+    struct pair pairs[16] = {0};
+    for (sum = 0, j = 0; j < 16; j++) sum += pairs[j].a + pairs[j].b;
+    buffer[i++] = sum;
+
+    int *pointers[8] = {0};
+    for (sum = 0, j = 0; j < 8; j++) sum += pointers[j] == 0;
+    buffer[i++] = sum;
+
+    // elements which differ must still be spelled out
+    int distinct[4] = { 1, 2, 3, 4 };
+    for (j = 0; j < 4; j++) buffer[i++] = distinct[j];
+}
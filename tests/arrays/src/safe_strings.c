@@ -0,0 +1,41 @@
+//! safe_string_literals
+
+#include <stddef.h>
+
+static char greeting[] = "hi";
+
+static int length(char *s) {
+    int n = 0;
+    while (s[n]) n++;
+    return n;
+}
+
+void safe_strings(const unsigned buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 17) return;
+
+    // None of these should need a transmute, and the pointers must stay valid and
+    // null-terminated after the statement that created them
+    char *mutable = "abc";
+    const char *shared = "de";
+    char local[5] = "xy";
+    wchar_t *wide = L"w";
+
+    local[0] = 'q';
+
+    for (int j = 0; j < 4; j++) {
+        buffer[i++] = mutable[j];
+    }
+    for (int j = 0; j < 3; j++) {
+        buffer[i++] = shared[j];
+    }
+    for (int j = 0; j < 5; j++) {
+        buffer[i++] = local[j];
+    }
+    buffer[i++] = wide[0];
+    buffer[i++] = wide[1];
+    buffer[i++] = length(greeting);
+    buffer[i++] = length(mutable);
+    buffer[i++] = length("fghij");
+}
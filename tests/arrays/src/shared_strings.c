@@ -0,0 +1,25 @@
+static const char *greeting(void) {
+    return "hello";
+}
+
+void shared_strings(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    const char *a = "hello";
+    const char *b = "hello";
+
+    // Identical string literals share their storage
+    buffer[i++] = a == b;
+    buffer[i++] = a == greeting();
+    buffer[i++] = a[0];
+    buffer[i++] = b[4];
+    buffer[i++] = b[5];
+
+    // Mutable arrays initialized from the same literal stay distinct
+    char c[] = "hello";
+    char d[] = "hello";
+    c[0] = 'j';
+    buffer[i++] = c[0];
+    buffer[i++] = d[0];
+}
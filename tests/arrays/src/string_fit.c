@@ -0,0 +1,23 @@
+void string_fit(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    // Exact fit: the null terminator is dropped
+    char exact[3] = "abc";
+    for (int j = 0; j < 3; j++)
+        buffer[i++] = exact[j];
+
+    // Over-fit: excess characters are truncated
+    char over[2] = "abcd";
+    for (int j = 0; j < 2; j++)
+        buffer[i++] = over[j];
+
+    // Under-fit: the remainder is zero padded
+    char under[6] = "abc";
+    for (int j = 0; j < 6; j++)
+        buffer[i++] = under[j];
+
+    static char static_under[5] = "xy";
+    for (int j = 0; j < 5; j++)
+        buffer[i++] = static_under[j];
+}
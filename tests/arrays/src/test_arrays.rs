@@ -3,6 +3,17 @@ extern crate libc;
 use arrays::rust_entry;
 use incomplete_arrays::{rust_test_sized_array,rust_entry2,rust_check_some_ints};
 use variable_arrays::{rust_variable_arrays, rust_alloca_arrays};
+use wide_strings::rust_wide_strings;
+use const_strings::rust_const_strings;
+use designated_arrays::{rust_designated_arrays, rust_range_designators};
+use repeated_arrays::rust_repeated_arrays;
+use lookup_tables::rust_lookup_tables;
+use string_fit::rust_string_fit;
+use shared_strings::rust_shared_strings;
+use named_lengths::{rust_named_lengths, ARRAY_LEN_4};
+use enum_lengths::rust_enum_lengths;
+use safe_strings::rust_safe_strings;
+use byte_strings::rust_byte_strings;
 use self::libc::{c_int, c_uint};
 
 #[link(name = "test")]
@@ -24,6 +35,42 @@ extern "C" {
 
     #[no_mangle]
     fn check_some_ints() -> bool;
+
+    #[no_mangle]
+    fn wide_strings(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn const_strings(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn designated_arrays(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn range_designators(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn repeated_arrays(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn lookup_tables(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn string_fit(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn shared_strings(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn named_lengths(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn enum_lengths(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn safe_strings(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn byte_strings(_: c_uint, _: *mut c_int);
 }
 
 #[no_mangle]
@@ -34,6 +81,18 @@ pub static rust_SOME_INTS: [u32; 4] = [2,0,1,8];
 const BUFFER_SIZE: usize = 49;
 const BUFFER_SIZE2: usize = 2;
 const BUFFER_SIZEV: usize = 88;
+const BUFFER_SIZEW: usize = 14;
+const BUFFER_SIZEC: usize = 10;
+const BUFFER_SIZED: usize = 29;
+const BUFFER_SIZER: usize = 23;
+const BUFFER_SIZERA: usize = 9;
+const BUFFER_SIZEL: usize = 8;
+const BUFFER_SIZES: usize = 16;
+const BUFFER_SIZESH: usize = 7;
+const BUFFER_SIZENL: usize = 9;
+const BUFFER_SIZEEL: usize = 16;
+const BUFFER_SIZESS: usize = 17;
+const BUFFER_SIZEBS: usize = 5;
 
 pub fn test_sized_array_impls() {
     unsafe {
@@ -134,3 +193,203 @@ pub fn test_alloca_arrays() {
         assert_eq!(buffer[index], rust_buffer[index], "index: {}", index);
     }
 }
+
+pub fn test_wide_strings() {
+    let mut buffer = [0; BUFFER_SIZEW];
+    let mut rust_buffer = [0; BUFFER_SIZEW];
+    let expected_buffer = [3, 104, 105, 0, 104, 105, 0, 0x1F631, 0, 3, 105, 105, 105, 0];
+
+    unsafe {
+       wide_strings(BUFFER_SIZEW as u32, buffer.as_mut_ptr());
+       rust_wide_strings(BUFFER_SIZEW as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_const_strings() {
+    let mut buffer = [0; BUFFER_SIZEC];
+    let mut rust_buffer = [0; BUFFER_SIZEC];
+    let expected_buffer = [104, 0xc3, 0xa9, 0, 0xff, 0, 97, 0, 98, 115];
+
+    unsafe {
+       const_strings(BUFFER_SIZEC as u32, buffer.as_mut_ptr());
+       rust_const_strings(BUFFER_SIZEC as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_designated_arrays() {
+    let mut buffer = [0; BUFFER_SIZED];
+    let mut rust_buffer = [0; BUFFER_SIZED];
+    let expected_buffer = [
+        0, 1, 0, 0, 4, 0,
+        1, 5, 6, 3, 4,
+        0, 2, 0,
+        5, 1, 0, 0, 0, 9,
+        0, 7, 7, 7, 0,
+        0, 0, 2, 0,
+    ];
+
+    unsafe {
+       designated_arrays(BUFFER_SIZED as u32, buffer.as_mut_ptr());
+       rust_designated_arrays(BUFFER_SIZED as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_range_designators() {
+    let mut buffer = [0; BUFFER_SIZER];
+    let mut rust_buffer = [0; BUFFER_SIZER];
+    let expected_buffer = [
+        5, 5, 5, 5,
+        1, 0, 8, 8, 8, 9,
+        1, 1, 1,
+        0, 2, 2, 7, 2,
+        1, 1, 2, 2, 2,
+    ];
+
+    unsafe {
+       range_designators(BUFFER_SIZER as u32, buffer.as_mut_ptr());
+       rust_range_designators(BUFFER_SIZER as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_repeated_arrays() {
+    let mut buffer = [0; BUFFER_SIZERA];
+    let mut rust_buffer = [0; BUFFER_SIZERA];
+    let expected_buffer = [0, 0, 448, 0, 8, 1, 2, 3, 4];
+
+    unsafe {
+       repeated_arrays(BUFFER_SIZERA as u32, buffer.as_mut_ptr());
+       rust_repeated_arrays(BUFFER_SIZERA as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_lookup_tables() {
+    let mut buffer = [0; BUFFER_SIZEL];
+    let mut rust_buffer = [0; BUFFER_SIZEL];
+    let expected_buffer = [127572, 110, -196568, -32768, 255, 0, 254, 3];
+
+    unsafe {
+       lookup_tables(BUFFER_SIZEL as u32, buffer.as_mut_ptr());
+       rust_lookup_tables(BUFFER_SIZEL as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_string_fit() {
+    let mut buffer = [0; BUFFER_SIZES];
+    let mut rust_buffer = [0; BUFFER_SIZES];
+    let expected_buffer = [
+        97, 98, 99,
+        97, 98,
+        97, 98, 99, 0, 0, 0,
+        120, 121, 0, 0, 0,
+    ];
+
+    unsafe {
+       string_fit(BUFFER_SIZES as u32, buffer.as_mut_ptr());
+       rust_string_fit(BUFFER_SIZES as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_shared_strings() {
+    let mut buffer = [0; BUFFER_SIZESH];
+    let mut rust_buffer = [0; BUFFER_SIZESH];
+    let expected_buffer = [1, 1, 104, 111, 0, 106, 104];
+
+    unsafe {
+       shared_strings(BUFFER_SIZESH as u32, buffer.as_mut_ptr());
+       rust_shared_strings(BUFFER_SIZESH as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_named_lengths() {
+    let mut buffer = [0; BUFFER_SIZENL];
+    let mut rust_buffer = [0; BUFFER_SIZENL];
+    let expected_buffer = [1, 2, 3, 4, 5, 6, 7, 8, 4];
+
+    unsafe {
+       named_lengths(BUFFER_SIZENL as u32, buffer.as_mut_ptr());
+       rust_named_lengths(BUFFER_SIZENL as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+    assert_eq!(ARRAY_LEN_4, 4);
+}
+
+pub fn test_enum_lengths() {
+    let mut buffer = [0; BUFFER_SIZEEL];
+    let mut rust_buffer = [0; BUFFER_SIZEEL];
+    let expected_buffer = [5, 7, 3, 0, 0, 0, 6, 0, 7, 8, 0, 0, 6, 6, 3, 2];
+
+    unsafe {
+       enum_lengths(BUFFER_SIZEEL as u32, buffer.as_mut_ptr());
+       rust_enum_lengths(BUFFER_SIZEEL as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_safe_strings() {
+    let mut buffer = [0; BUFFER_SIZESS];
+    let mut rust_buffer = [0; BUFFER_SIZESS];
+    let expected_buffer = [97, 98, 99, 0, 100, 101, 0, 113, 121, 0, 0, 0, 119, 0, 2, 3, 5];
+
+    unsafe {
+       safe_strings(BUFFER_SIZESS as u32, buffer.as_mut_ptr());
+       rust_safe_strings(BUFFER_SIZESS as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The translation was made with --safe-string-literals, so check the source itself
+    let src = include_str!("safe_strings.rs");
+    assert!(!src.contains("transmute"));
+    // Literals live in statics rather than being leaked on every evaluation
+    assert!(!src.contains("Box::leak"));
+    assert!(src.contains("static mut STRING_LITERAL: [libc::c_char; 4] = [97, 98, 99, 0];"));
+}
+
+pub fn test_byte_strings() {
+    let mut buffer = [0; BUFFER_SIZEBS];
+    let mut rust_buffer = [0; BUFFER_SIZEBS];
+    let expected_buffer = [104, 0, 120, 0, 111];
+
+    unsafe {
+       byte_strings(BUFFER_SIZEBS as u32, buffer.as_mut_ptr());
+       rust_byte_strings(BUFFER_SIZEBS as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The translation was made with --prefer-byte-strings, so check the source itself
+    let src = include_str!("byte_strings.rs");
+    assert!(src.contains(r#"[libc::c_uchar; 3] = *b"hi\x00";"#));
+    assert!(src.contains(r#"[libc::c_uchar; 4] = *b"x\x00\x00\x00";"#));
+    assert!(!src.contains(r#"*b"yo"#));
+}
@@ -0,0 +1,32 @@
+#include <stddef.h>
+#include <uchar.h>
+
+static wchar_t static_wide[] = L"hi";
+static char16_t static_utf16[] = u"hi";
+static char32_t static_utf32[4] = U"hi";
+
+void wide_strings(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    wchar_t wide[] = L"hi";
+    buffer[i++] = sizeof(wide) / sizeof(wchar_t);
+    buffer[i++] = wide[0];
+    buffer[i++] = wide[1];
+    buffer[i++] = wide[2];
+
+    char16_t *utf16 = u"hi";
+    buffer[i++] = utf16[0];
+    buffer[i++] = utf16[1];
+    buffer[i++] = utf16[2];
+
+    const char32_t *utf32 = U"\U0001F631";
+    buffer[i++] = utf32[0];
+    buffer[i++] = utf32[1];
+
+    buffer[i++] = sizeof(static_wide) / sizeof(wchar_t);
+    buffer[i++] = static_wide[1];
+    buffer[i++] = static_utf16[1];
+    buffer[i++] = static_utf32[1];
+    buffer[i++] = static_utf32[3];
+}
@@ -0,0 +1,20 @@
+//! narrowing_literals_strict, translate_only
+
+// The same constants as narrowing_literals.c. The truncating ones become `compile_error!`, so
+// this translation is only inspected by the tests and never built.
+void narrowing_literals_strict(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    char c = 300;
+    unsigned char uc = -1;
+    short s = 70000;
+    unsigned short us = 65535;
+    signed char table[2] = { 300, 1 };
+
+    buffer[i++] = c;
+    buffer[i++] = uc;
+    buffer[i++] = s;
+    buffer[i++] = us;
+    buffer[i++] = table[0];
+}
@@ -0,0 +1,49 @@
+extern crate libc;
+
+use narrowing_literals::rust_narrowing_literals;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn narrowing_literals(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 5;
+
+pub fn test_narrowing_literals() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [44, 255, 4464, 65535, 44];
+
+    unsafe {
+        narrowing_literals(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_narrowing_literals(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_narrowing_literal_modes() {
+    // Both translations are of the same constants: warnings in narrowing_literals.rs, errors in
+    // narrowing_literals_strict.rs
+    let warn = include_str!("narrowing_literals.rs");
+    let strict = include_str!("narrowing_literals_strict.rs");
+
+    assert!(warn.contains("// 70000 truncated to 4464 in libc::c_short"));
+    assert!(warn.contains("// -1 truncated to 255 in libc::c_uchar"));
+    assert!(warn.contains("// 300 truncated to 44 in libc::c_schar"));
+    assert!(!warn.contains("compile_error!"));
+
+    assert!(strict.contains("integer constant 70000 does not fit in libc::c_short"));
+    assert!(strict.contains("integer constant -1 does not fit in libc::c_uchar"));
+    // Including those in tables of integer constants
+    assert!(strict.contains("integer constant 300 does not fit in libc::c_schar"));
+
+    // `char` might be signed or unsigned, so its constant is never reported
+    assert!(!warn.contains("truncated to 44"));
+    assert!(!strict.contains("does not fit in libc::c_char"));
+    // Constants that fit are left alone
+    assert!(!strict.contains("65535 does not fit"));
+}
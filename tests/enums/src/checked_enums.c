@@ -0,0 +1,24 @@
+//! checked_enum_values
+
+enum level {
+    LOW = -1,
+    MID = 0,
+    HIGH = 1,
+    TOP = 1,
+};
+
+// Static initializers keep plain casts, since the conversion isn't a `const fn`
+static enum level static_level = (enum level)5;
+
+void entry7(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 4) return;
+
+    // Converted through the generated match, which accepts the value of each variant
+    for (int x = -1; x <= 1; x++) {
+        enum level l = (enum level)x;
+        buffer[i++] = l;
+    }
+    buffer[i++] = static_level;
+}
@@ -0,0 +1,27 @@
+enum permissions {
+    PERM_READ = 1,
+    PERM_WRITE = 2,
+    PERM_EXEC = 4,
+    PERM_ALL = 7,
+};
+
+static enum permissions static_perms = (enum permissions)3;
+
+void entry6(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 5) return;
+
+    // combinations of flags
+    enum permissions read_exec = 5;
+    enum permissions read_write = (enum permissions)3;
+    buffer[i++] = read_exec;
+    buffer[i++] = read_write;
+    buffer[i++] = static_perms;
+
+    // an exact variant, and a value which isn't made of variants
+    enum permissions all = 7;
+    enum permissions other = 8;
+    buffer[i++] = all;
+    buffer[i++] = other;
+}
@@ -5,6 +5,8 @@ use enum_ret::{Color, rust_entry2};
 use enum_duplicate::{e, rust_entry3};
 use top_enum::{E as otherE, rust_entry4};
 use big_enum::{E1, E2, E3, rust_entry5};
+use flag_enum::rust_entry6;
+use checked_enums::rust_entry7;
 use non_canonical_enum_def::{rust_abc, hrtimer_restart, HRTIMER_RESTART, HRTIMER_NORESTART};
 
 use self::libc::{c_int, c_uint};
@@ -25,6 +27,12 @@ extern "C" {
 
     #[no_mangle]
     fn entry5(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn entry6(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn entry7(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 10;
@@ -32,6 +40,8 @@ const BUFFER_SIZE2: usize = 7;
 const BUFFER_SIZE3: usize = 4;
 const BUFFER_SIZE4: usize = 1;
 const BUFFER_SIZE5: usize = 6;
+const BUFFER_SIZE6: usize = 5;
+const BUFFER_SIZE7: usize = 4;
 
 
 pub fn test_variants() {
@@ -109,3 +119,42 @@ pub fn test_buffer5() {
     assert_eq!(buffer, rust_buffer);
     assert_eq!(buffer, expected_buffer);
 }
+
+pub fn test_buffer6() {
+    let mut buffer = [0; BUFFER_SIZE6];
+    let mut rust_buffer = [0; BUFFER_SIZE6];
+    let expected_buffer = [5, 3, 3, 7, 8];
+
+    unsafe {
+        entry6(BUFFER_SIZE6 as u32, buffer.as_mut_ptr());
+        rust_entry6(BUFFER_SIZE6 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_buffer7() {
+    let mut buffer = [0; BUFFER_SIZE7];
+    let mut rust_buffer = [0; BUFFER_SIZE7];
+    let expected_buffer = [-1, 0, 1, 5];
+
+    unsafe {
+        entry7(BUFFER_SIZE7 as u32, buffer.as_mut_ptr());
+        rust_entry7(BUFFER_SIZE7 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The translation was made with --checked-enum-values, so check the source itself
+    let src = include_str!("checked_enums.rs");
+    assert!(!src.contains("transmute"));
+    assert!(src.contains("pub fn level_from_i64(value: i64) -> level {"));
+    assert!(src.contains("-1 => LOW,"));
+    assert!(src.contains("1 => HIGH,"));
+    // `TOP` shares its value with `HIGH`, so it would never match
+    assert!(!src.contains("=> TOP,"));
+    assert!(src.contains(r#"_ => panic!("{} is not a value of enum level", value),"#));
+    assert!(src.contains("level_from_i64(x as i64)"));
+}
@@ -0,0 +1,25 @@
+#include <complex.h>
+
+static double _Complex z = 1.0 + 2.0 * I;
+static float _Complex zf = 3.0f - 4.0f * I;
+static double _Complex parts = {5.0, 6.0};
+static double _Complex zero;
+
+double _Complex complex_global(void) {
+    return z;
+}
+
+float _Complex complex_float_global(void) {
+    return zf;
+}
+
+void complex_parts(const unsigned sz, double buffer[]) {
+    double _Complex real_only = 7.0;
+
+    buffer[0] = __real__ parts;
+    buffer[1] = __imag__ parts;
+    buffer[2] = __real__ zero;
+    buffer[3] = __imag__ zero;
+    buffer[4] = __real__ real_only;
+    buffer[5] = __imag__ real_only;
+}
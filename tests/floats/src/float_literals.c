@@ -0,0 +1,51 @@
+#include <math.h>
+
+static double static_hex = 0x1.8p3;
+
+double hex_half(void) {
+    return 0x1p-1;
+}
+
+double hex_large(void) {
+    return 0x1.fp10;
+}
+
+double hex_subnormal(void) {
+    return 0x0.0000000000001p-1022;
+}
+
+float hex_float(void) {
+    return 0x1.8p1f;
+}
+
+double hex_static(void) {
+    return static_hex;
+}
+
+double overflowing_literal(void) {
+    return 1e999;
+}
+
+float overflowing_float_literal(void) {
+    return -1e99f;
+}
+
+int non_finite_macros(void) {
+    double inf = INFINITY;
+    double nan = NAN;
+    return isinf(inf) && isnan(nan);
+}
+
+static const double static_negative_zero = -0.0;
+
+double negative_zero(void) {
+    return -0.0;
+}
+
+float negative_zero_float(void) {
+    return -0.0f;
+}
+
+double negative_zero_static(void) {
+    return static_negative_zero;
+}
@@ -0,0 +1,46 @@
+//! extern_crate_num_complex
+
+extern crate libc;
+extern crate num_complex;
+
+use complex::{rust_complex_float_global, rust_complex_global, rust_complex_parts};
+use self::libc::{c_double, c_uint};
+use self::num_complex::Complex;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn complex_global() -> Complex<c_double>;
+    #[no_mangle]
+    fn complex_float_global() -> Complex<f32>;
+    #[no_mangle]
+    fn complex_parts(_: c_uint, _: *mut c_double);
+}
+
+const BUFFER_SIZE: usize = 6;
+
+pub fn test_complex_globals() {
+    unsafe {
+        let z = rust_complex_global();
+        assert_eq!(z, complex_global());
+        assert_eq!((z.re, z.im), (1.0, 2.0));
+
+        let zf = rust_complex_float_global();
+        assert_eq!(zf, complex_float_global());
+        assert_eq!((zf.re, zf.im), (3.0, -4.0));
+    }
+}
+
+pub fn test_complex_parts() {
+    let mut buffer = [0.0; BUFFER_SIZE];
+    let mut rust_buffer = [0.0; BUFFER_SIZE];
+    let expected_buffer = [5.0, 6.0, 0.0, 0.0, 7.0, 0.0];
+
+    unsafe {
+        complex_parts(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_complex_parts(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,69 @@
+extern crate libc;
+
+use float_literals::{rust_hex_half, rust_hex_large, rust_hex_subnormal, rust_hex_float, rust_hex_static};
+use float_literals::{rust_overflowing_literal, rust_overflowing_float_literal, rust_non_finite_macros};
+use float_literals::{rust_negative_zero, rust_negative_zero_float, rust_negative_zero_static};
+use self::libc::{c_double, c_float, c_int};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn hex_half() -> c_double;
+    #[no_mangle]
+    fn hex_large() -> c_double;
+    #[no_mangle]
+    fn hex_subnormal() -> c_double;
+    #[no_mangle]
+    fn hex_float() -> c_float;
+    #[no_mangle]
+    fn hex_static() -> c_double;
+    #[no_mangle]
+    fn overflowing_literal() -> c_double;
+    #[no_mangle]
+    fn overflowing_float_literal() -> c_float;
+    #[no_mangle]
+    fn non_finite_macros() -> c_int;
+    #[no_mangle]
+    fn negative_zero() -> c_double;
+    #[no_mangle]
+    fn negative_zero_float() -> c_float;
+    #[no_mangle]
+    fn negative_zero_static() -> c_double;
+}
+
+pub fn test_hex_floats() {
+    unsafe {
+        assert_eq!(hex_half().to_bits(), rust_hex_half().to_bits());
+        assert_eq!(rust_hex_half(), 0.5);
+        assert_eq!(hex_large().to_bits(), rust_hex_large().to_bits());
+        assert_eq!(rust_hex_large(), 1984.);
+        assert_eq!(hex_subnormal().to_bits(), rust_hex_subnormal().to_bits());
+        assert_eq!(rust_hex_subnormal().to_bits(), 1);
+        assert_eq!(hex_float().to_bits(), rust_hex_float().to_bits());
+        assert_eq!(rust_hex_float(), 3.);
+        assert_eq!(hex_static().to_bits(), rust_hex_static().to_bits());
+        assert_eq!(rust_hex_static(), 12.);
+    }
+}
+
+pub fn test_non_finite_floats() {
+    unsafe {
+        assert_eq!(overflowing_literal(), rust_overflowing_literal());
+        assert_eq!(rust_overflowing_literal(), std::f64::INFINITY);
+        assert_eq!(overflowing_float_literal(), rust_overflowing_float_literal());
+        assert_eq!(rust_overflowing_float_literal(), std::f32::NEG_INFINITY);
+        assert_eq!(non_finite_macros(), rust_non_finite_macros());
+        assert_eq!(rust_non_finite_macros(), 1);
+    }
+}
+
+pub fn test_negative_zero() {
+    unsafe {
+        assert_eq!(negative_zero().to_bits(), rust_negative_zero().to_bits());
+        assert_eq!(rust_negative_zero().to_bits(), (-0.0f64).to_bits());
+        assert_eq!(negative_zero_float().to_bits(), rust_negative_zero_float().to_bits());
+        assert_eq!(rust_negative_zero_float().to_bits(), (-0.0f32).to_bits());
+        assert_eq!(negative_zero_static().to_bits(), rust_negative_zero_static().to_bits());
+        assert_eq!(rust_negative_zero_static().to_bits(), (-0.0f64).to_bits());
+    }
+}
@@ -18,3 +18,39 @@ lbl: ;
 
   return x + y;
 }
+
+struct pair {
+  int a;
+  int b;
+};
+
+void stmt_expr_init(const unsigned buffer_size, int buffer[const]) {
+  int i = 0;
+  int calls = 0;
+
+  int x = ({
+    int y = 2;
+    calls++;
+    y * 3;
+  });
+
+  int sum = ({
+    int s = 0;
+    for (int j = 1; j <= 4; j++)
+      s += j;
+    calls++;
+    s;
+  });
+
+  int arr[3] = { ({ calls++; x + 1; }), sum, ({ int t = x; t - 1; }) };
+  struct pair p = { .a = ({ calls++; arr[0]; }), .b = ({ arr[2] * 2; }) };
+
+  buffer[i++] = x;
+  buffer[i++] = sum;
+  buffer[i++] = arr[0];
+  buffer[i++] = arr[1];
+  buffer[i++] = arr[2];
+  buffer[i++] = p.a;
+  buffer[i++] = p.b;
+  buffer[i++] = calls;
+}
@@ -1,8 +1,16 @@
 extern crate libc;
 
-use stmt_expr::rust_stmt_expr_func;
+use stmt_expr::{rust_stmt_expr_func, rust_stmt_expr_init};
 
-use self::libc::c_int;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn stmt_expr_init(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
 
 pub fn test_stmt_expr_relooper() {
     unsafe {
@@ -12,3 +20,17 @@ pub fn test_stmt_expr_relooper() {
         assert_eq!(rust_stmt_expr_func(3), 29);
     }
 }
+
+pub fn test_stmt_expr_init() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [6, 10, 7, 10, 5, 7, 10, 4];
+
+    unsafe {
+        stmt_expr_init(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_stmt_expr_init(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,12 @@
+//! emit_char_casts
+
+void char_casts(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    if (buffer_size < 3) return;
+
+    buffer[i++] = 'A';
+    buffer[i++] = '\n';
+    buffer[i++] = ' ';
+}
@@ -12,3 +12,11 @@ int multibyte_chars(const unsigned sz, int buffer[]) {
 
   return i;
 }
+
+void multi_chars(const unsigned sz, int buffer[]) {
+  int magic = 'ABCD';
+
+  buffer[0] = magic;
+  buffer[1] = 'AB';
+  buffer[2] = '\x01\x02';
+}
@@ -0,0 +1,11 @@
+//! literal_base_decimal
+
+void decimal_literals(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    buffer[i++] = 0xFF;
+    buffer[i++] = 0777;
+    buffer[i++] = 0b1101;
+    buffer[i++] = 0x7fffffff;
+}
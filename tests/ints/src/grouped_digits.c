@@ -0,0 +1,15 @@
+//! group_digits
+
+void grouped_digits(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    if (buffer_size < 3) return;
+
+    unsigned int mask = 0xdeadbeef;
+
+    buffer[i++] = 1000000;
+    buffer[i++] = mask >> 16;
+    // Too short to be grouped
+    buffer[i++] = 999;
+}
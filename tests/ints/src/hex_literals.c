@@ -0,0 +1,11 @@
+//! literal_base_hex
+
+void hex_literals(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    buffer[i++] = 255;
+    buffer[i++] = 0777;
+    buffer[i++] = 13;
+    buffer[i++] = 2147483647;
+}
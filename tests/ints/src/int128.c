@@ -0,0 +1,23 @@
+static const unsigned __int128 big_words[2] = {
+    -(unsigned __int128)1,
+    18446744073709551615ULL,
+};
+
+void int128(const unsigned buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 8) return;
+
+    // Constants past u64::MAX, and the minimum signed 128-bit value
+    unsigned __int128 above = (unsigned __int128)18446744073709551615ULL + 2;
+    __int128 min = -(__int128)((unsigned __int128)1 << 126) * 2;
+
+    buffer[i++] = (int)(above >> 64);
+    buffer[i++] = (int)above;
+    buffer[i++] = (int)(min >> 96);
+    buffer[i++] = (int)min;
+    buffer[i++] = (int)(big_words[0] >> 96);
+    buffer[i++] = (int)big_words[0];
+    buffer[i++] = (int)(big_words[1] >> 64);
+    buffer[i++] = (int)big_words[1];
+}
@@ -0,0 +1,6 @@
+//! emit_provenance
+
+// The provenance sidecar should trace this literal back to its line and column
+int literal_provenance(void) {
+    return 12345;
+}
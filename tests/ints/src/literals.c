@@ -0,0 +1,69 @@
+#include <stdbool.h>
+
+void int_literals(const unsigned sz, int buffer[]) {
+    int i = 0;
+
+    // Hexadecimal and octal constants keep their base in the generated Rust
+    buffer[i++] = 0xFF;
+    buffer[i++] = 0777;
+    buffer[i++] = 0x0;
+    buffer[i++] = (unsigned char)0x1ff;
+
+    // Binary constants (C23 / GNU extension)
+    buffer[i++] = 0b1101;
+    buffer[i++] = 0b0;
+    buffer[i++] = (int)0b11111111111111111111111111111111u;
+
+    // Fixed-width types get a literal suffix, platform-dependent ones keep a cast
+    long long suffixed = 42LL;
+    long cast = 42L;
+    buffer[i++] = suffixed == cast;
+    buffer[i++] = 0xFFu + 1 == 0x100;
+
+    // Unsigned 64-bit constants above INT64_MAX
+    unsigned long long max = 0xFFFFFFFFFFFFFFFFULL;
+    unsigned long max_dec = 18446744073709551615UL;
+    buffer[i++] = max == max_dec;
+    buffer[i++] = (int)(max >> 32);
+    buffer[i++] = (int)(max_dec % 1000);
+}
+
+void char_literals(const unsigned sz, int buffer[]) {
+    int i = 0;
+
+    buffer[i++] = 'A';
+    buffer[i++] = ' ';
+    buffer[i++] = '\n';
+    buffer[i++] = '\0';
+    buffer[i++] = u'✓';
+}
+
+struct flags {
+    bool enabled;
+    _Bool visible;
+};
+
+void bool_literals(const unsigned sz, int buffer[]) {
+    int i = 0, x = 3;
+
+    if (sz < 10) return;
+
+    _Bool b = 1;
+    _Bool f = 0;
+    _Bool nonzero = 2;
+    bool cmp = (x != 0);
+    buffer[i++] = b;
+    buffer[i++] = f;
+    buffer[i++] = nonzero;
+    buffer[i++] = cmp;
+
+    bool bools[3] = { 1, 0, true };
+    buffer[i++] = bools[0];
+    buffer[i++] = bools[1];
+    buffer[i++] = bools[2];
+
+    struct flags s = { true, false };
+    buffer[i++] = s.enabled;
+    buffer[i++] = s.visible;
+    buffer[i++] = sizeof(bools);
+}
@@ -0,0 +1,26 @@
+extern crate libc;
+
+use int128::rust_int128;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn int128(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_int128() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1, 1, i32::min_value(), 0, -1, -1, 0, -1];
+
+    unsafe {
+        int128(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_int128(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
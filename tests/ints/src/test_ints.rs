@@ -1,7 +1,7 @@
 extern crate libc;
 
 use size_t::rust_entry;
-use chars::rust_multibyte_chars;
+use chars::{rust_multi_chars, rust_multibyte_chars};
 use self::libc::{c_int, c_uint};
 
 #[link(name = "test")]
@@ -11,6 +11,9 @@ extern "C" {
 
     #[no_mangle]
     fn multibyte_chars(_: c_uint, _: *mut c_int) -> c_int;
+
+    #[no_mangle]
+    fn multi_chars(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 10;
@@ -42,3 +45,17 @@ pub fn test_chars_buffer() {
     assert_eq!(buffer, rust_buffer);
     assert_eq!(buffer, expected_buffer);
 }
+
+pub fn test_multi_chars_buffer() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [0x41424344, 0x4142, 0x0102, 0, 0, 0, 0, 0, 0, 0];
+
+    unsafe {
+        multi_chars(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_multi_chars(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
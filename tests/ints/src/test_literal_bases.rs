@@ -0,0 +1,44 @@
+extern crate libc;
+
+use decimal_literals::rust_decimal_literals;
+use hex_literals::rust_hex_literals;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn decimal_literals(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn hex_literals(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 4;
+
+pub fn test_decimal_literals() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [255, 511, 13, 2147483647];
+
+    unsafe {
+        decimal_literals(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_decimal_literals(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_hex_literals() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [255, 511, 13, 2147483647];
+
+    unsafe {
+        hex_literals(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_hex_literals(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
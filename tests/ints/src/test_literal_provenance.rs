@@ -0,0 +1,25 @@
+extern crate libc;
+
+use literal_provenance::rust_literal_provenance;
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn literal_provenance() -> c_int;
+}
+
+pub fn test_literal_provenance() {
+    let ret = unsafe { literal_provenance() };
+    let rust_ret = unsafe { rust_literal_provenance() };
+    assert_eq!(ret, rust_ret);
+
+    let provenance = include_str!("literal_provenance.provenance.json");
+    let record = provenance
+        .split('}')
+        .find(|record| record.contains("\"rust\": \"12345i32\""))
+        .expect("no provenance record for the literal");
+    assert!(record.contains("literal_provenance.c\""), "{}", record);
+    assert!(record.contains("\"line\": 5,"), "{}", record);
+    assert!(record.contains("\"column\": 12,"), "{}", record);
+}
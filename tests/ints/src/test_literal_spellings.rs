@@ -0,0 +1,56 @@
+extern crate libc;
+
+use grouped_digits::rust_grouped_digits;
+use char_casts::rust_char_casts;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn grouped_digits(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn char_casts(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 3;
+
+pub fn test_grouped_digits() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1000000, 0xdead, 999];
+
+    unsafe {
+        grouped_digits(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_grouped_digits(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The translation was made with --group-digits, so check the source itself
+    let src = include_str!("grouped_digits.rs");
+    assert!(src.contains("1_000_000"));
+    assert!(src.contains("0xdead_beef"));
+    assert!(!src.contains("999_"));
+}
+
+pub fn test_char_casts() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [65, 10, 32];
+
+    unsafe {
+        char_casts(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_char_casts(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The translation was made with --emit-char-casts, so check the source itself
+    let src = include_str!("char_casts.rs");
+    assert!(src.contains("'A' as i32"));
+    assert!(src.contains("'\\n' as i32"));
+    assert!(src.contains("' ' as i32"));
+}
@@ -0,0 +1,62 @@
+extern crate libc;
+
+use literals::{rust_bool_literals, rust_char_literals, rust_int_literals};
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn int_literals(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn char_literals(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn bool_literals(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 12;
+const CHAR_BUFFER_SIZE: usize = 5;
+const BOOL_BUFFER_SIZE: usize = 10;
+
+pub fn test_int_literals() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [255, 511, 0, 255, 13, 0, -1, 1, 1, 1, -1, 615];
+
+    unsafe {
+        int_literals(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_int_literals(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_char_literals() {
+    let mut buffer = [0; CHAR_BUFFER_SIZE];
+    let mut rust_buffer = [0; CHAR_BUFFER_SIZE];
+    let expected_buffer = [65, 32, 10, 0, '✓' as i32];
+
+    unsafe {
+        char_literals(CHAR_BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_char_literals(CHAR_BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_bool_literals() {
+    let mut buffer = [0; BOOL_BUFFER_SIZE];
+    let mut rust_buffer = [0; BOOL_BUFFER_SIZE];
+    let expected_buffer = [1, 0, 1, 1, 1, 0, 1, 1, 0, 3];
+
+    unsafe {
+        bool_literals(BOOL_BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_bool_literals(BOOL_BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
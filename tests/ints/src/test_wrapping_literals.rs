@@ -0,0 +1,26 @@
+extern crate libc;
+
+use wrapping_literals::rust_wrapping_literals;
+use self::libc::c_uint;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn wrapping_literals(_: c_uint, _: *mut c_uint);
+}
+
+const BUFFER_SIZE: usize = 7;
+
+pub fn test_wrapping_literals() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [0, 4294967295, 4294967293, 2147483647, 1, 4294967295, 4294967289];
+
+    unsafe {
+        wrapping_literals(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_wrapping_literals(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
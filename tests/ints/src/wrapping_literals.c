@@ -0,0 +1,16 @@
+//! wrapping_literals
+
+void wrapping_literals(const unsigned buffer_size, unsigned buffer[const])
+{
+    unsigned max = 0xffffffff;
+    unsigned long long big = 1;
+    int i = 0;
+
+    buffer[i++] = max + 1;
+    buffer[i++] = 0 - 1u;
+    buffer[i++] = 3 * max;
+    buffer[i++] = max / 2;
+    buffer[i++] = (max + 7) % 5;
+    buffer[i++] = (unsigned)(big - 2);
+    buffer[i++] = max - (unsigned)i;
+}
@@ -0,0 +1,8 @@
+//! source_location_docs
+
+// Each translated item gets a doc comment naming the line it was declared on
+static int counter = 3;
+
+int source_locations(void) {
+    return counter;
+}
@@ -0,0 +1,22 @@
+extern crate libc;
+
+use source_locations::rust_source_locations;
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn source_locations() -> c_int;
+}
+
+pub fn test_source_location_docs() {
+    let ret = unsafe { source_locations() };
+    let rust_ret = unsafe { rust_source_locations() };
+    assert_eq!(ret, rust_ret);
+
+    let src = include_str!("source_locations.rs");
+    assert!(src.contains("/// from source_locations.c:4\nstatic mut counter: libc::c_int ="));
+    assert!(src.contains(
+        "/// from source_locations.c:6\npub unsafe extern \"C\" fn rust_source_locations()"
+    ));
+}
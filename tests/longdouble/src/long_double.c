@@ -48,3 +48,6 @@ unsigned int cast2uint(long double a) {
 
 const long double ld1 = 1.0;
 const long double ld2 = ld1 + 2.0;
+const long double ld3 = 2.5L;
+long double ld4 = 10;
+long double ld5 = 0.1;
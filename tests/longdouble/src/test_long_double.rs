@@ -4,7 +4,7 @@ extern crate libc;
 extern crate f128 as float128;
 extern crate num_traits;
 
-use long_double::{rust_long_double_ops, rust_cast2double, rust_cast2float, rust_cast2uint, rust_ld1, rust_ld2};
+use long_double::{rust_long_double_ops, rust_cast2double, rust_cast2float, rust_cast2uint, rust_ld1, rust_ld2, rust_ld3, rust_ld4, rust_ld5};
 use self::float128::f128;
 use self::libc::{c_double, c_float, c_uint};
 
@@ -46,5 +46,8 @@ pub fn test_global_f128s() {
     unsafe {
         assert_eq!(rust_ld1, f128::new(1.0));
         assert_eq!(rust_ld2, f128::new(3.0));
+        assert_eq!(rust_ld3, f128::new(2.5));
+        assert_eq!(rust_ld4, f128::new(10.0));
+        assert_eq!(rust_ld5, f128::new(0.1f64));
     }
 }
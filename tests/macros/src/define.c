@@ -63,3 +63,15 @@ int stmt_expr_inc(void) {
   // used
   return inc(b);
 }
+
+// String literals in const, static and local initializers
+#define GREETING "hi"
+#define GREETING_PTR ((const char *)"hey")
+
+static const char static_greeting[] = GREETING;
+
+int string_initializers(void) {
+  char local[] = GREETING;
+  const char *ptr = GREETING_PTR;
+  return static_greeting[0] + static_greeting[1] + local[0] + local[1] + ptr[2];
+}
@@ -2,13 +2,16 @@ extern crate libc;
 
 use define::{TEST_CONST1, TEST_CONST2, TEST_PARENS, rust_reference_define};
 use define::{ZSTD_WINDOWLOG_MAX_32, ZSTD_WINDOWLOG_MAX_64, rust_test_zstd};
-use define::{rust_fns, rust_stmt_expr_inc};
-use self::libc::{c_int, c_uint, c_ulong};
+use define::{rust_fns, rust_stmt_expr_inc, rust_string_initializers, GREETING};
+use self::libc::{c_char, c_int, c_uint, c_ulong};
 
 #[link(name = "test")]
 extern "C" {
     #[no_mangle]
     fn reference_define() -> c_uint;
+
+    #[no_mangle]
+    fn string_initializers() -> c_int;
 }
 
 pub fn test_define() {
@@ -27,3 +30,12 @@ pub fn test_macro_stmt_expr() {
 
     assert_eq!(ret, 2);
 }
+
+pub fn test_string_initializers() {
+    let ret = unsafe { string_initializers() };
+    let rust_ret = unsafe { rust_string_initializers() };
+
+    assert_eq!(ret, rust_ret);
+    assert_eq!(ret, 2 * (b'h' + b'i') as c_int + b'y' as c_int);
+    assert_eq!(GREETING, [b'h' as c_char, b'i' as c_char, 0]);
+}
@@ -0,0 +1,28 @@
+#include <stdlib.h>
+
+static int freed = 0;
+
+static void free_it(char **p)
+{
+        free(*p);
+        freed++;
+}
+
+void cleanup_test(const unsigned int buffer_size, int buffer[const])
+{
+        int i = 0;
+
+        {
+                __attribute__((cleanup(free_it))) char *p = malloc(4);
+                p[0] = 'a';
+                buffer[i++] = p[0];
+                buffer[i++] = freed;
+        }
+        buffer[i++] = freed;
+
+        for (int j = 0; j < 2; j++) {
+                __attribute__((cleanup(free_it))) char *p = malloc(4);
+                p = realloc(p, 8);
+        }
+        buffer[i++] = freed;
+}
@@ -0,0 +1,43 @@
+struct point { int x, y; };
+union number { int i; float f; };
+
+static struct point global_pt = {};
+
+// C23 allows empty initializers for scalars as well as aggregates, but
+// older compilers reject them.
+#if __STDC_VERSION__ > 201710L
+static char global_c = {};
+static int *global_p = {};
+#else
+static char global_c = 0;
+static int *global_p = 0;
+#endif
+
+void empty_init(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 11) return;
+
+#if __STDC_VERSION__ > 201710L
+    char c = {};
+    void *p = {};
+#else
+    char c = 0;
+    void *p = 0;
+#endif
+    int xs[3] = {};
+    struct point pt = {};
+    union number n = {};
+
+    buffer[i++] = c;
+    buffer[i++] = p == 0;
+    buffer[i++] = xs[0];
+    buffer[i++] = xs[2];
+    buffer[i++] = sizeof(xs) / sizeof(xs[0]);
+    buffer[i++] = pt.x;
+    buffer[i++] = pt.y;
+    buffer[i++] = n.i;
+    buffer[i++] = global_pt.y;
+    buffer[i++] = global_c;
+    buffer[i++] = global_p == 0;
+}
@@ -0,0 +1,11 @@
+//! stub_failed_decls, translate_only
+
+// Inline assembly isn't translated without --translate-asm, so this function becomes a
+// compile_error! stub, and the translation is only inspected by the tests and never built.
+void failed_decl(void) {
+    __asm__ volatile("" ::: "memory");
+}
+
+int translated_decl(void) {
+    return 1;
+}
@@ -0,0 +1,8 @@
+//! stub_failed_decls, translate_only
+
+// Braces around a scalar initializer aren't translated, so this becomes a compile_error! stub
+// whose message points at the initializer, and the translation is only inspected by the tests
+// and never built.
+int braced = { 5 };
+
+int unbraced = 5;
@@ -0,0 +1,26 @@
+extern crate libc;
+
+use cleanup::rust_cleanup_test;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn cleanup_test(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 4;
+
+pub fn test_cleanup() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [97, 0, 1, 3];
+
+    unsafe {
+        cleanup_test(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_cleanup_test(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
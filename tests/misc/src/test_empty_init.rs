@@ -0,0 +1,26 @@
+extern crate libc;
+
+use empty_init::rust_empty_init;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn empty_init(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 11;
+
+pub fn test_buffer() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [0, 1, 0, 0, 3, 0, 0, 0, 0, 0, 1];
+
+    unsafe {
+        empty_init(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_empty_init(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
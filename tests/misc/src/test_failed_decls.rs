@@ -0,0 +1,8 @@
+pub fn test_failed_decl_stubs() {
+    // The translation was made with --stub-failed-decls, so check the source itself
+    let src = include_str!("failed_decls.rs");
+    assert!(src.contains("compile_error!(\"Failed to translate failed_decl at "));
+    assert!(src.contains("Inline assembly"));
+    // Declarations that translate are unaffected
+    assert!(src.contains("pub unsafe extern \"C\" fn rust_translated_decl() -> libc::c_int"));
+}
@@ -0,0 +1,12 @@
+pub fn test_scalar_init_list_error() {
+    // The translation was made with --stub-failed-decls, so check the source itself
+    let src = include_str!("scalar_init_list.rs");
+    assert!(src.contains("compile_error!(\"Failed to translate rust_braced at "));
+    // The message names the Rust type and the location of the first initializer
+    assert!(src.contains(
+        "Init list not implemented for libc::c_int (Int), starting with the initializer at "
+    ));
+    assert!(src.contains("scalar_init_list.c:6:16"));
+    // The same value without braces translates
+    assert!(src.contains("pub static mut rust_unbraced: libc::c_int = "));
+}
@@ -1,7 +1,7 @@
 extern crate libc;
 
 use x86::{
-    ShuffleVectors, VectorInitLists, rust_unpack_128_2x128, rust_zero_init_all, rust_call_all, rust_call_all_used, rust_vector_init_lists, rust_vector_init_lists_used,
+    ShuffleVectors, VectorInitLists, rust_unpack_128_2x128, rust_zero_init_all, rust_call_all, rust_call_all_used, rust_vector_init_lists, rust_vector_init_lists_used, rust_vector_init_lists_partial,
     rust_static_m128, rust_static_m256, rust_static_m128d, rust_static_m256d, rust_static_m128i, rust_static_m256i, rust_simd_fn_codegen,
     rust_static_uninit_m128, rust_static_uninit_m256, rust_static_uninit_m128d, rust_static_uninit_m256d, rust_static_uninit_m128i, rust_static_uninit_m256i,
 };
@@ -32,6 +32,9 @@ extern "C" {
     #[no_mangle]
     fn vector_init_lists_used() -> VectorInitLists;
 
+    #[no_mangle]
+    fn vector_init_lists_partial() -> VectorInitLists;
+
     #[no_mangle]
     static static_m128: __m128;
 
@@ -195,9 +198,12 @@ pub fn test_vector_init_lists() {
     let c2 = unsafe { vector_init_lists_used() };
     let r1 = unsafe { rust_vector_init_lists() };
     let r2 = unsafe { rust_vector_init_lists_used() };
+    let c3 = unsafe { vector_init_lists_partial() };
+    let r3 = unsafe { rust_vector_init_lists_partial() };
 
     assert_eq!(c1, r1);
     assert_eq!(c2, r2);
+    assert_eq!(c3, r3);
 }
 
 pub fn test_static_init_lists() {
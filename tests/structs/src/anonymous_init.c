@@ -0,0 +1,45 @@
+struct tagged {
+    int tag;
+    union {
+        int i;
+        float f;
+    };
+    struct {
+        int x, y;
+    };
+};
+
+struct aligned_pair {
+    int a, b;
+} __attribute__((aligned(8)));
+
+struct packed_holder {
+    char c;
+    struct aligned_pair pair;
+} __attribute__((packed));
+
+void anonymous_init(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 11) return;
+
+    // anonymous members initialized by brace-enclosed sublists
+    struct tagged positional = { 1, { 2 }, { 3, 4 } };
+    buffer[i++] = positional.tag;
+    buffer[i++] = positional.i;
+    buffer[i++] = positional.x;
+    buffer[i++] = positional.y;
+
+    // anonymous members initialized through their flattened names
+    struct tagged designated = { .tag = 5, .f = 1.5f, .y = 6 };
+    buffer[i++] = designated.tag;
+    buffer[i++] = (int)(designated.f * 2);
+    buffer[i++] = designated.x;
+    buffer[i++] = designated.y;
+
+    // an aligned struct nested in a packed one is split into outer and inner types
+    struct packed_holder holder = { 7, { 8, 9 } };
+    buffer[i++] = holder.c;
+    buffer[i++] = holder.pair.a;
+    buffer[i++] = holder.pair.b;
+}
@@ -271,3 +271,37 @@ void use_renamed_field(void) {
 
     rr.use = 0;
 }
+
+// Adjacent bitfields sharing one byte of storage, `lo` in bits 0-2 and `hi` in bits 3-7
+struct adjacent_bits {
+    unsigned char lo: 3;
+    unsigned char hi: 5;
+};
+
+struct adjacent_bits static_adjacent_bits = {6, 30};
+
+unsigned char adjacent_bits_storage(void) {
+    struct adjacent_bits ab = {5, 17};
+
+    return *(unsigned char *)&ab;
+}
+
+// A bitfield that spans both bytes of its storage, initialized by a call that must only run once
+struct spanning_bits {
+    unsigned short low: 4;
+    unsigned short wide: 10;
+};
+
+static int spanning_calls = 0;
+
+static unsigned short next_wide(void) {
+    return 700 + ++spanning_calls;
+}
+
+int bitfield_call_init(void) {
+    spanning_calls = 0;
+
+    struct spanning_bits sb = {3, next_wide()};
+
+    return spanning_calls * 1024 + sb.wide;
+}
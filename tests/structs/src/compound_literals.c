@@ -0,0 +1,37 @@
+struct point {
+    int x, y;
+};
+
+static int sum(struct point p) {
+    return p.x + p.y;
+}
+
+static int sum_ptr(const struct point *p) {
+    return p->x + p->y;
+}
+
+static void scale(struct point *p, int factor) {
+    p->x *= factor;
+    p->y *= factor;
+}
+
+void compound_literals(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    // Passed by value directly to a function
+    buffer[i++] = sum((struct point){1, 2});
+    buffer[i++] = sum((struct point){.y = 4});
+
+    // Taking the address of a compound literal
+    buffer[i++] = sum_ptr(&(struct point){5, 6});
+
+    struct point *p = &(struct point){7, 8};
+    scale(p, 2);
+    buffer[i++] = p->x;
+    buffer[i++] = p->y;
+
+    // Arrays can be compound literals too
+    int *xs = (int[]){9, 10, 11};
+    buffer[i++] = xs[2];
+}
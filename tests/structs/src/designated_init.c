@@ -0,0 +1,40 @@
+struct point {
+    int x, y, z;
+};
+
+struct with_gap {
+    int a;
+    int:4;
+    int b;
+    unsigned char c;
+};
+
+void designated_init(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 12) return;
+
+    // designators in reverse order
+    struct point reordered = { .z = 3, .y = 2, .x = 1 };
+    buffer[i++] = reordered.x;
+    buffer[i++] = reordered.y;
+    buffer[i++] = reordered.z;
+
+    // positional initializers following a designator
+    struct point mixed = { .y = 5, 6 };
+    buffer[i++] = mixed.x;
+    buffer[i++] = mixed.y;
+    buffer[i++] = mixed.z;
+
+    // a later designator overrides an earlier one
+    struct point overridden = { 7, 8, .x = 9 };
+    buffer[i++] = overridden.x;
+    buffer[i++] = overridden.y;
+    buffer[i++] = overridden.z;
+
+    // unnamed bitfields take no initializer
+    struct with_gap gap = { .c = 10, .a = 11 };
+    buffer[i++] = gap.a;
+    buffer[i++] = gap.b;
+    buffer[i++] = gap.c;
+}
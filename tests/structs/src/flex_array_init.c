@@ -0,0 +1,11 @@
+//! stub_failed_decls, translate_only
+
+// Flexible array members are translated to zero-length arrays, which have no room for the
+// elements of an initializer, so this becomes a compile_error! stub, and the translation is
+// only inspected by the tests and never built.
+struct flex_init {
+  int len;
+  int data[];
+};
+
+struct flex_init initialized = { 3, { 1, 2, 3 } };
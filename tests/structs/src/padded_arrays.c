@@ -0,0 +1,35 @@
+struct entry {
+    int key;
+    const char *name;
+    double weights[2];
+    union {
+        int i;
+        float f;
+    } extra;
+};
+
+static struct entry static_table[4] = {
+    { 1, "one", { 1.5 } },
+    [2] = { .key = 3 },
+};
+
+static struct entry static_zeroed[3];
+
+void padded_arrays(const unsigned buffer_size, int buffer[const])
+{
+    int i = 0;
+
+    struct entry local_table[5] = { { 9 } };
+
+    for (int j = 0; j < 4; j++) {
+        buffer[i++] = static_table[j].key;
+        buffer[i++] = static_table[j].name != 0;
+        buffer[i++] = (int)(static_table[j].weights[0] * 2);
+    }
+
+    for (int j = 0; j < 3; j++)
+        buffer[i++] = static_zeroed[j].key + static_zeroed[j].extra.i;
+
+    for (int j = 0; j < 5; j++)
+        buffer[i++] = local_table[j].key + (local_table[j].name != 0);
+}
@@ -0,0 +1,26 @@
+extern crate libc;
+
+use anonymous_init::rust_anonymous_init;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn anonymous_init(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 11;
+
+pub fn test_buffer() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1, 2, 3, 4, 5, 3, 0, 6, 7, 8, 9];
+
+    unsafe {
+        anonymous_init(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_anonymous_init(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -5,8 +5,9 @@ use bitfields::{
     three_byte_date, rust_compare_three_byte_date, rust_write_three_byte_date, padded_bitfield,
     rust_ops_padded_bitfield, rust_ops_padded_bitfield_init, mixed_bitfields, rust_init_bitfield_array,
     rust_static_date, from_csmith, rust_init_from_csmith, rust_get_bf_ptr, rust_modify_bf_ptr,
-    two_eight_bits, rust_two_eight_bits_init, rust_multiple_assignments, rust_ma_results,
-    rust_use_renamed_field,
+    two_eight_bits, rust_two_eight_bits_init, adjacent_bits, rust_multiple_assignments, rust_ma_results,
+    rust_use_renamed_field, rust_static_adjacent_bits, rust_adjacent_bits_storage,
+    rust_bitfield_call_init,
 };
 
 extern "C" {
@@ -38,6 +39,12 @@ extern "C" {
     fn multiple_assignments();
     #[no_mangle]
     static ma_results: [u8; 17];
+    #[no_mangle]
+    static static_adjacent_bits: adjacent_bits;
+    #[no_mangle]
+    fn adjacent_bits_storage() -> u8;
+    #[no_mangle]
+    fn bitfield_call_init() -> i32;
 }
 
 pub fn test_three_byte_date() {
@@ -276,3 +283,24 @@ pub fn test_multiple_assignments() {
         assert_eq!(ma_results, rust_ma_results);
     }
 }
+
+// Initializers pack the values of adjacent bitfields into their shared storage
+pub fn test_adjacent_bits_init() {
+    unsafe {
+        assert_eq!(adjacent_bits_storage(), 5 | 17 << 3);
+        assert_eq!(rust_adjacent_bits_storage(), adjacent_bits_storage());
+
+        assert_eq!(static_adjacent_bits.lo_hi, [6 | 30 << 3]);
+        assert_eq!(rust_static_adjacent_bits.lo_hi, static_adjacent_bits.lo_hi);
+        assert_eq!(rust_static_adjacent_bits.lo(), 6);
+        assert_eq!(rust_static_adjacent_bits.hi(), 30);
+    }
+}
+
+// A bitfield initializer is evaluated once, even though its value is split across bytes
+pub fn test_bitfield_call_init() {
+    unsafe {
+        assert_eq!(bitfield_call_init(), 1024 + 701);
+        assert_eq!(rust_bitfield_call_init(), bitfield_call_init());
+    }
+}
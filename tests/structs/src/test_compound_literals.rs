@@ -0,0 +1,26 @@
+extern crate libc;
+
+use compound_literals::rust_compound_literals;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn compound_literals(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 6;
+
+pub fn test_buffer() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3, 4, 11, 14, 16, 11];
+
+    unsafe {
+        compound_literals(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_compound_literals(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,33 @@
+extern crate libc;
+
+use padded_arrays::rust_padded_arrays;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn padded_arrays(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 20;
+
+pub fn test_buffer() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [
+        1, 1, 3,
+        0, 0, 0,
+        3, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+        9, 0, 0, 0, 0,
+    ];
+
+    unsafe {
+        padded_arrays(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_padded_arrays(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,48 @@
+extern crate libc;
+
+use zero_inits::rust_zero_inits;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn zero_inits(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 39;
+
+/// The text of the initializer given to the variable `name` in `src`
+fn initializer<'a>(src: &'a str, name: &str) -> &'a str {
+    let decl = &src[src.find(&format!(" {}: ", name)).unwrap()..];
+    decl[decl.find('=').unwrap() + 1..decl.find(';').unwrap()].trim()
+}
+
+pub fn test_buffer() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [
+        0, 1, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 0, 0,
+        0, 1, 0, 0, 0, 0, 1, 1, 1,
+    ];
+
+    unsafe {
+        zero_inits(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_zero_inits(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_same_translation() {
+    let src = include_str!("zero_inits.rs");
+
+    for &(empty, zero) in &[
+        ("empty_static", "zero_static"),
+        ("empty_union", "zero_union"),
+        ("empty_array", "zero_array"),
+        ("empty_local", "zero_local"),
+    ] {
+        assert_eq!(initializer(src, empty), initializer(src, zero), "{} and {}", empty, zero);
+    }
+}
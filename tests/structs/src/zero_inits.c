@@ -0,0 +1,57 @@
+struct inner {
+    char tag;
+    double weight;
+};
+
+struct mixed {
+    int count;
+    float scale;
+    int *ptr;
+    int cells[3];
+    struct inner in;
+};
+
+union overlay {
+    int word;
+    char bytes[4];
+};
+
+// `{}` and `{0}` zero the same things, so each pair should translate identically
+static struct mixed empty_static = {};
+static struct mixed zero_static = {0};
+static union overlay empty_union = {};
+static union overlay zero_union = {0};
+static struct inner empty_array[2] = {};
+static struct inner zero_array[2] = {0};
+
+static void push_mixed(int buffer[const], int *i, struct mixed *m) {
+    buffer[(*i)++] = m->count;
+    buffer[(*i)++] = m->scale == 0.0f;
+    buffer[(*i)++] = m->ptr == 0;
+    for (int j = 0; j < 3; j++) {
+        buffer[(*i)++] = m->cells[j];
+    }
+    buffer[(*i)++] = m->in.tag;
+    buffer[(*i)++] = m->in.weight == 0.0;
+}
+
+void zero_inits(const unsigned buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 39) return;
+
+    struct mixed empty_local = {};
+    struct mixed zero_local = {0};
+
+    push_mixed(buffer, &i, &empty_static);
+    push_mixed(buffer, &i, &zero_static);
+    push_mixed(buffer, &i, &empty_local);
+    push_mixed(buffer, &i, &zero_local);
+    buffer[i++] = empty_union.word;
+    buffer[i++] = zero_union.word;
+    buffer[i++] = empty_array[1].tag;
+    buffer[i++] = zero_array[1].tag;
+    buffer[i++] = empty_array[1].weight == 0.0;
+    buffer[i++] = zero_array[1].weight == 0.0;
+    buffer[i++] = sizeof(empty_array) == sizeof(zero_array);
+}
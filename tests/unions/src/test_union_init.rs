@@ -0,0 +1,26 @@
+extern crate libc;
+
+use union_init::rust_union_init;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn union_init(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 14;
+
+pub fn test_buffer() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [0, 3, 1, 2, 0, 0, 0, 8, 5, 7, 9, 1, 2, 4];
+
+    unsafe {
+        union_init(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_union_init(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,80 @@
+union U {
+    int a;
+    int b;
+    char bytes[4];
+};
+
+static union U static_empty = {};
+
+// Initializing the second member, which is bigger than the first, uses the whole union
+union Wide {
+    char c;
+    long long l;
+};
+
+static union Wide static_wide = { .l = 7LL << 36 };
+
+union Narrow {
+    long long l;
+    char c;
+};
+
+// Layout attributes aren't carried over to the Rust unions, so their sizes aren't checked
+union __attribute__((packed)) Packed {
+    char c[5];
+    int i;
+};
+
+union __attribute__((aligned(16))) Aligned {
+    char c;
+    int i;
+};
+
+struct Pair {
+    int x;
+    int y;
+};
+
+union WithPair {
+    int a;
+    struct Pair s;
+};
+
+static union WithPair static_pair = { .s = {3, 4} };
+
+void union_init(const unsigned int buffer_size, int buffer[const]) {
+    int i = 0;
+
+    if (buffer_size < 14) return;
+
+    union U zeroed = {0};
+    buffer[i++] = zeroed.a;
+
+    // a designator selects a member other than the first
+    union U designated = { .b = 3 };
+    buffer[i++] = designated.b;
+
+    union U nested = { .bytes = { 1, 2 } };
+    buffer[i++] = nested.bytes[0];
+    buffer[i++] = nested.bytes[1];
+    buffer[i++] = nested.bytes[2];
+
+    // an empty initializer zero-initializes the union
+    union U empty = {};
+    buffer[i++] = empty.a;
+    buffer[i++] = static_empty.a;
+
+    union Wide wide = { .l = 5LL << 40 };
+    buffer[i++] = sizeof(wide);
+    buffer[i++] = (int)(wide.l >> 40);
+    buffer[i++] = (int)(static_wide.l >> 36);
+
+    union Narrow narrow = { .c = 9 };
+    buffer[i++] = narrow.c;
+
+    // a nested brace list initializes a struct member
+    union WithPair pair = { .s = {1, 2} };
+    buffer[i++] = pair.s.x;
+    buffer[i++] = pair.s.y;
+    buffer[i++] = static_pair.s.y;
+}